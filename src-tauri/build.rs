@@ -1,3 +1,23 @@
 fn main() {
+    emit_image_crate_version();
     tauri_build::build()
 }
+
+/// 从 Cargo.lock 读取实际解析到的 image 版本，注入为 IMAGE_CRATE_VERSION 环境变量；
+/// 找不到 Cargo.lock 或其中没有 image 时为 "unknown"
+fn emit_image_crate_version() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let version = lock
+        .split("[[package]]")
+        .find(|package| package.lines().any(|line| line.trim() == "name = \"image\""))
+        .and_then(|package| {
+            package.lines().find_map(|line| {
+                line.trim().strip_prefix("version = \"")?.strip_suffix('"').map(str::to_string)
+            })
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=IMAGE_CRATE_VERSION={}", version);
+}
@@ -3,10 +3,25 @@
 
 use image::DynamicImage;
 use base64::{Engine as _, engine::general_purpose};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// 单次加载的图像最大字节数（50MB）
 const MAX_IMAGE_SIZE: usize = 50 * 1024 * 1024;
 
+/// Tauri IPC 返回的 RGBA 颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RgbaColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
 /// 从 base64 数据加载图像
 ///
 /// # 参数
@@ -37,13 +52,28 @@ pub fn image_load_base64(image_data: &str) -> Result<DynamicImage, String> {
         .decode(&base64_data)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
     
-    let img = image::load_from_memory(&decoded)
+    image_load_sniffed(&decoded, None)
+}
+
+/// 按文件头识别格式并解码图像字节，无法识别时使用 fallback 指定的格式
+///
+/// # 异常
+/// * 格式无法识别且未提供 fallback
+/// * 图像数据损坏
+/// * 分辨率宽高为零
+pub fn image_load_sniffed(bytes: &[u8], fallback: Option<image::ImageFormat>) -> Result<DynamicImage, String> {
+    let format = match (image::guess_format(bytes), fallback) {
+        (Ok(format), _) => format,
+        (Err(_), Some(format)) => format,
+        (Err(e), None) => return Err(format!("Failed to load image: {}", e)),
+    };
+    let img = image::load_from_memory_with_format(bytes, format)
         .map_err(|e| format!("Failed to load image: {}", e))?;
-    
+
     if img.width() == 0 || img.height() == 0 {
         return Err("Invalid image dimensions: width or height is zero".to_string());
     }
-    
+
     Ok(img)
 }
 
@@ -71,6 +101,216 @@ pub fn image_fetch_base64_data(image_data: &str) -> Result<Vec<u8>, String> {
         .map_err(|e| format!("Failed to decode base64: {}", e))
 }
 
+/// JPEG 色度抽样方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromaSubsampling {
+    /// 4:4:4，不抽样，适合文字和锐利边缘
+    Yuv444,
+    /// 4:2:2，水平方向减半
+    Yuv422,
+    /// 4:2:0，水平垂直均减半，照片体积约减半且肉眼差异很小
+    #[default]
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    /// 解析 "4:4:4" / "4:2:2" / "4:2:0"，未指定时默认 4:2:0
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None => Ok(Self::default()),
+            Some("4:4:4") => Ok(Self::Yuv444),
+            Some("4:2:2") => Ok(Self::Yuv422),
+            Some("4:2:0") => Ok(Self::Yuv420),
+            Some(other) => Err(format!("Invalid chroma subsampling: {} (expected 4:4:4, 4:2:2 or 4:2:0)", other)),
+        }
+    }
+
+    /// 从 JPEG 帧头（SOF）读取实际使用的色度抽样，非 YCbCr 三通道或无法识别时返回 None
+    pub fn detect(jpeg: &[u8]) -> Option<Self> {
+        let mut pos = 2;
+        while *jpeg.get(pos)? == 0xFF {
+            let marker = *jpeg.get(pos + 1)?;
+            if marker == 0xDA || marker == 0xD9 {
+                return None;
+            }
+            let length = u16::from_be_bytes([*jpeg.get(pos + 2)?, *jpeg.get(pos + 3)?]) as usize;
+            // SOF0..SOF15，排除 DHT(C4)、JPG(C8)、DAC(CC)
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                let frame = jpeg.get(pos + 4..pos + 2 + length)?;
+                if *frame.get(5)? != 3 {
+                    return None;
+                }
+                // 各分量占 3 字节：ID、水平/垂直采样因子、量化表；色度分量须为 1x1
+                let (luma, cb, cr) = (*frame.get(7)?, *frame.get(10)?, *frame.get(13)?);
+                return match (luma, cb, cr) {
+                    (0x11, 0x11, 0x11) => Some(Self::Yuv444),
+                    (0x21, 0x11, 0x11) => Some(Self::Yuv422),
+                    (0x22, 0x11, 0x11) => Some(Self::Yuv420),
+                    _ => None,
+                };
+            }
+            pos += 2 + length;
+        }
+        None
+    }
+
+    fn sampling_factor(self) -> jpeg_encoder::SamplingFactor {
+        match self {
+            Self::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+            Self::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+            Self::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+/// 以指定质量和色度抽样将图像编码为 JPEG（透明通道被丢弃）
+///
+/// # 参数
+/// * `img` — 待编码的图像
+/// * `quality` — JPEG 质量（1-100）
+/// * `subsampling` — 色度抽样方式
+///
+/// # 返回值
+/// * `Ok(Vec<u8>)` — 编码后的 JPEG 字节
+///
+/// # 异常
+/// * 宽或高超过 JPEG 上限 65535
+/// * 编码失败
+pub fn image_encode_jpeg(img: &DynamicImage, quality: u8, subsampling: ChromaSubsampling) -> Result<Vec<u8>, String> {
+    image_encode_jpeg_with_exif(img, quality, subsampling, None)
+}
+
+/// 同 image_encode_jpeg，并可写入 APP1 EXIF 段
+///
+/// `exif` 为完整的 APP1 内容（含 "Exif\0\0" 前缀），长度不能超过单个 APP 段上限 65533 字节
+pub fn image_encode_jpeg_with_exif(
+    img: &DynamicImage,
+    quality: u8,
+    subsampling: ChromaSubsampling,
+    exif: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    image_encode_jpeg_with_metadata(img, quality, subsampling, exif, None)
+}
+
+/// 同 image_encode_jpeg_with_exif，并可写入 ICC 色彩配置文件（较大的配置文件自动拆分为多个 APP2 段）
+pub fn image_encode_jpeg_with_metadata(
+    img: &DynamicImage,
+    quality: u8,
+    subsampling: ChromaSubsampling,
+    exif: Option<&[u8]>,
+    icc: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let rgb = img.to_rgb8();
+    let width = u16::try_from(rgb.width())
+        .map_err(|_| format!("Image too wide for JPEG: {}", rgb.width()))?;
+    let height = u16::try_from(rgb.height())
+        .map_err(|_| format!("Image too tall for JPEG: {}", rgb.height()))?;
+
+    let mut buffer = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut buffer, quality.clamp(1, 100));
+    encoder.set_sampling_factor(subsampling.sampling_factor());
+    if let Some(exif) = exif {
+        encoder.add_app_segment(1, exif)
+            .map_err(|e| format!("Failed to embed EXIF: {}", e))?;
+    }
+    if let Some(icc) = icc {
+        encoder.add_icc_profile(icc)
+            .map_err(|e| format!("Failed to embed color profile: {}", e))?;
+    }
+    encoder
+        .encode(rgb.as_raw(), width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+    Ok(buffer)
+}
+
+/// 解码后的图像及重新编码时需要保留的元数据
+pub struct ImageWithMetadata {
+    pub image: DynamicImage,
+    /// 带 "Exif\0\0" 前缀的 APP1 内容
+    pub exif: Option<Vec<u8>>,
+    /// ICC 色彩配置文件
+    pub icc: Option<Vec<u8>>,
+}
+
+/// 解码图片文件字节，同时取出 EXIF 和 ICC 配置文件，
+/// 供重新编码 JPEG 时通过 image_encode_jpeg_with_metadata 原样写回
+pub fn image_load_with_metadata(bytes: &[u8]) -> Result<ImageWithMetadata, String> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?
+        .into_decoder()
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+    let exif = decoder.exif_metadata().ok().flatten()
+        .map(|exif| if exif.starts_with(b"Exif\0\0") { exif } else { [b"Exif\0\0".as_slice(), &exif].concat() });
+    let icc = decoder.icc_profile().ok().flatten().filter(|icc| !icc.is_empty());
+    let image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+
+    Ok(ImageWithMetadata { image, exif, icc })
+}
+
+/// 将图像按指定格式编码为字节
+///
+/// # 参数
+/// * `img` — 待编码的图像
+/// * `format` — 目标格式
+/// * `quality` — JPEG 质量（1-100），其他格式忽略
+///
+/// # 返回值
+/// * `Ok(Vec<u8>)` — 编码后的文件字节；JPEG 使用默认 4:2:0 色度抽样
+///
+/// # 异常
+/// * 编码失败或格式不支持
+pub fn image_encode_bytes(img: &DynamicImage, format: image::ImageFormat, quality: u8) -> Result<Vec<u8>, String> {
+    if format == image::ImageFormat::Jpeg {
+        return image_encode_jpeg(img, quality, ChromaSubsampling::default());
+    }
+
+    let mut buffer = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buffer), format)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+    Ok(buffer)
+}
+
+/// 将图像编码为 PNG data URL
+///
+/// # 参数
+/// * `img` — 待编码的图像
+///
+/// # 返回值
+/// * `Ok(String)` — data:image/png;base64 前缀的图片数据
+///
+/// # 异常
+/// * PNG 编码失败
+pub fn image_encode_png_base64(img: &DynamicImage) -> Result<String, String> {
+    let buffer = image_encode_bytes(img, image::ImageFormat::Png, 100)?;
+    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&buffer)))
+}
+
+/// 将 src 像素以 source-over 方式混合到 dst（均为非预乘 RGBA）
+pub fn pixel_blend_over(dst: &mut [u8], src: &[u8]) {
+    let src_a = src[3] as f32 / 255.0;
+    if src_a <= 0.0 {
+        return;
+    }
+    if src_a >= 1.0 {
+        dst.copy_from_slice(&src[..4]);
+        return;
+    }
+
+    let dst_a = dst[3] as f32 / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    for c in 0..3 {
+        let value = (src[c] as f32 * src_a + dst[c] as f32 * dst_a * (1.0 - src_a)) / out_a;
+        dst[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+    dst[3] = (out_a * 255.0).round() as u8;
+}
+
 /// Tauri IPC 命令：将图像按方向旋转
 ///
 /// # 参数
@@ -107,6 +347,9 @@ pub fn image_update_rotation(image_data: String, direction: String) -> Result<St
 /// brightness: integer -100..100, contrast: float multiplier (e.g. 1.0 normal)
 #[tauri::command]
 pub fn image_update_adjustments(image_data: String, brightness: i32, contrast: f32) -> Result<String, String> {
+    if !contrast.is_finite() {
+        return Err(format!("Invalid contrast: {}", contrast));
+    }
     let img = image_load_base64(&image_data)?;
     let mut rgba = img.to_rgba8();
 
@@ -140,3 +383,4646 @@ pub fn image_update_adjustments(image_data: String, brightness: i32, contrast: f
     let result = format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&buffer));
     Ok(result)
 }
+
+/// Tauri IPC 命令：用补丁图像覆盖底图的指定区域
+///
+/// 用于实时画面只有局部变化时，仅传输变化区域而非整帧
+///
+/// # 参数
+/// * `base` — base64 编码的底图
+/// * `patch` — base64 编码的补丁图像
+/// * `x` — 补丁左上角在底图中的横坐标
+/// * `y` — 补丁左上角在底图中的纵坐标
+///
+/// # 返回值
+/// * `Ok(String)` — 更新后的 base64 编码 PNG 图片数据
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * 补丁超出底图边界
+#[tauri::command]
+pub fn image_update_region(base: String, patch: String, x: u32, y: u32) -> Result<String, String> {
+    let mut base_rgba = image_load_base64(&base)?.to_rgba8();
+    let patch_rgba = image_load_base64(&patch)?.to_rgba8();
+
+    let fits_x = x.checked_add(patch_rgba.width()).is_some_and(|right| right <= base_rgba.width());
+    let fits_y = y.checked_add(patch_rgba.height()).is_some_and(|bottom| bottom <= base_rgba.height());
+    if !fits_x || !fits_y {
+        return Err(format!(
+            "Patch {}x{} at ({}, {}) exceeds base image bounds {}x{}",
+            patch_rgba.width(), patch_rgba.height(), x, y, base_rgba.width(), base_rgba.height()
+        ));
+    }
+
+    // 直接覆盖像素（含 alpha），不做混合
+    image::imageops::replace(&mut base_rgba, &patch_rgba, x as i64, y as i64);
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(base_rgba))
+}
+
+/// Tauri IPC 命令：反相图像颜色（RGB 取 255 - 值，alpha 保持不变）
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(String)` — 反相后的 base64 编码 PNG 图片数据
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_invert(image_data: String) -> Result<String, String> {
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+
+    rgba.par_chunks_exact_mut(4).for_each(|chunk| {
+        chunk[0] = 255 - chunk[0];
+        chunk[1] = 255 - chunk[1];
+        chunk[2] = 255 - chunk[2];
+    });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// Tauri IPC 命令：按阈值将 alpha 二值化，RGB 保持不变
+///
+/// 抗锯齿笔迹边缘为半透明，二值化后得到用于模板/抠图的清晰蒙版
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `threshold` — alpha 大于该值的像素变为完全不透明，其余变为完全透明
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 base64 编码 PNG 图片数据
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_alpha_threshold(image_data: String, threshold: u8) -> Result<String, String> {
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+
+    rgba.par_chunks_exact_mut(4).for_each(|chunk| {
+        chunk[3] = if chunk[3] > threshold { 255 } else { 0 };
+    });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// Tauri IPC 命令：在底图的矩形区域内平铺图案（alpha 混合）
+///
+/// 图案从矩形左上角开始按尺寸取模循环，图案大于或小于矩形都能正确平铺，
+/// 矩形以外的像素保持不变
+///
+/// # 参数
+/// * `base` — base64 编码的底图
+/// * `pattern` — base64 编码的图案
+/// * `x` / `y` — 填充区域左上角坐标
+/// * `width` / `height` — 填充区域尺寸
+///
+/// # 返回值
+/// * `Ok(String)` — 填充后的 base64 编码 PNG 图片数据
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * 填充区域为空或超出底图边界
+#[tauri::command]
+pub fn image_update_pattern_fill(
+    base: String,
+    pattern: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<String, String> {
+    let mut base_rgba = image_load_base64(&base)?.to_rgba8();
+    let pattern_rgba = image_load_base64(&pattern)?.to_rgba8();
+
+    if width == 0 || height == 0 {
+        return Err("Fill region width and height must be greater than zero".to_string());
+    }
+    let fits_x = x.checked_add(width).is_some_and(|right| right <= base_rgba.width());
+    let fits_y = y.checked_add(height).is_some_and(|bottom| bottom <= base_rgba.height());
+    if !fits_x || !fits_y {
+        return Err(format!(
+            "Fill region {}x{} at ({}, {}) exceeds base image bounds {}x{}",
+            width, height, x, y, base_rgba.width(), base_rgba.height()
+        ));
+    }
+
+    let (pattern_w, pattern_h) = pattern_rgba.dimensions();
+    let row_len = base_rgba.width() as usize * 4;
+
+    base_rgba
+        .par_chunks_exact_mut(row_len)
+        .enumerate()
+        .skip(y as usize)
+        .take(height as usize)
+        .for_each(|(row, line)| {
+            let pattern_y = (row as u32 - y) % pattern_h;
+            for col in x..x + width {
+                let pattern_x = (col - x) % pattern_w;
+                let src = pattern_rgba.get_pixel(pattern_x, pattern_y);
+                let offset = col as usize * 4;
+                pixel_blend_over(&mut line[offset..offset + 4], &src.0);
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(base_rgba))
+}
+
+/// 校验矩形区域非空且完全位于图像范围内
+fn rect_validate_bounds(img: &DynamicImage, x: u32, y: u32, width: u32, height: u32) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err("Region width and height must be greater than zero".to_string());
+    }
+    let fits_x = x.checked_add(width).is_some_and(|right| right <= img.width());
+    let fits_y = y.checked_add(height).is_some_and(|bottom| bottom <= img.height());
+    if !fits_x || !fits_y {
+        return Err(format!(
+            "Region {}x{} at ({}, {}) exceeds image bounds {}x{}",
+            width, height, x, y, img.width(), img.height()
+        ));
+    }
+    Ok(())
+}
+
+/// 计算像素的平均 RGBA，完全透明的像素不参与平均；全部透明时返回 (0, 0, 0, 0)
+fn color_calc_average<'a>(pixels: impl Iterator<Item = &'a image::Rgba<u8>>) -> RgbaColor {
+    let mut sums = [0u64; 4];
+    let mut count = 0u64;
+    for pixel in pixels {
+        if pixel[3] == 0 {
+            continue;
+        }
+        for (sum, &value) in sums.iter_mut().zip(pixel.0.iter()) {
+            *sum += value as u64;
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        return RgbaColor { r: 0, g: 0, b: 0, a: 0 };
+    }
+
+    let average = |sum: u64| ((sum as f64 / count as f64).round()) as u8;
+    RgbaColor {
+        r: average(sums[0]),
+        g: average(sums[1]),
+        b: average(sums[2]),
+        a: average(sums[3]),
+    }
+}
+
+/// Tauri IPC 命令：计算图像矩形区域的平均颜色
+///
+/// 完全透明的像素不参与平均；区域内全部透明时返回 (0, 0, 0, 0)
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `x` / `y` — 区域左上角坐标
+/// * `width` / `height` — 区域尺寸
+///
+/// # 返回值
+/// * `Ok(RgbaColor)` — 区域平均 RGBA 颜色
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * 区域为空或超出图像边界
+#[tauri::command]
+pub fn image_fetch_region_color(
+    image_data: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaColor, String> {
+    let img = image_load_base64(&image_data)?;
+    rect_validate_bounds(&img, x, y, width, height)?;
+
+    let region = img.crop_imm(x, y, width, height).to_rgba8();
+    Ok(color_calc_average(region.pixels()))
+}
+
+/// 计算图像（或 [x, y, width, height] 区域）平均颜色叠加到白色背景后的 RGB，
+/// 透明部分视为白色画布
+pub fn image_calc_background_color(image_data: &str, region: Option<[u32; 4]>) -> Result<[u8; 3], String> {
+    let img = image_load_base64(image_data)?;
+    let average = match region {
+        Some([x, y, width, height]) => {
+            rect_validate_bounds(&img, x, y, width, height)?;
+            color_calc_average(img.crop_imm(x, y, width, height).to_rgba8().pixels())
+        }
+        None => color_calc_average(img.to_rgba8().pixels()),
+    };
+
+    let alpha = average.a as u32;
+    let over_white = |value: u8| ((value as u32 * alpha + 255 * (255 - alpha) + 127) / 255) as u8;
+    Ok([over_white(average.r), over_white(average.g), over_white(average.b)])
+}
+
+/// WCAG 相对亮度（0..1）
+pub fn color_calc_relative_luminance(rgb: [u8; 3]) -> f32 {
+    0.2126 * channel_calc_to_linear(rgb[0])
+        + 0.7152 * channel_calc_to_linear(rgb[1])
+        + 0.0722 * channel_calc_to_linear(rgb[2])
+}
+
+/// WCAG 对比度（1..21），与两色顺序无关
+pub fn color_calc_contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let (la, lb) = (color_calc_relative_luminance(a), color_calc_relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// 2×2 盒式平均将图像缩小一半（奇数边舍弃最后一行/列）
+fn image_calc_half_size(src: &image::RgbaImage) -> image::RgbaImage {
+    let (src_w, src_h) = src.dimensions();
+    let (dst_w, dst_h) = (src_w / 2, src_h / 2);
+    let mut dst = image::RgbaImage::new(dst_w, dst_h);
+    let src_raw = src.as_raw();
+    let src_row_len = src_w as usize * 4;
+
+    dst.par_chunks_exact_mut(dst_w as usize * 4)
+        .enumerate()
+        .for_each(|(y, line)| {
+            let top = y * 2 * src_row_len;
+            let bottom = top + src_row_len;
+            for x in 0..dst_w as usize {
+                let left = x * 2 * 4;
+                for c in 0..4 {
+                    let sum = src_raw[top + left + c] as u32
+                        + src_raw[top + left + 4 + c] as u32
+                        + src_raw[bottom + left + c] as u32
+                        + src_raw[bottom + left + 4 + c] as u32;
+                    line[x * 4 + c] = ((sum + 2) / 4) as u8;
+                }
+            }
+        });
+
+    dst
+}
+
+/// Tauri IPC 命令：生成用于平滑缩放的 mipmap 金字塔
+///
+/// 第 0 级为原图，之后每一级由上一级 2×2 盒式平均缩小一半；
+/// 下一级宽或高将小于 1 像素时提前停止
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `levels` — 额外生成的层级数（不含第 0 级）
+///
+/// # 返回值
+/// * `Ok(Vec<String>)` — 从大到小排列的 base64 编码 PNG 图片数据
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_mipmaps(image_data: String, levels: u32) -> Result<Vec<String>, String> {
+    let mut current = image_load_base64(&image_data)?.to_rgba8();
+    let mut result = Vec::with_capacity(levels as usize + 1);
+    result.push(image_encode_png_base64(&DynamicImage::ImageRgba8(current.clone()))?);
+
+    for _ in 0..levels {
+        if current.width() < 2 || current.height() < 2 {
+            break;
+        }
+        current = image_calc_half_size(&current);
+        result.push(image_encode_png_base64(&DynamicImage::ImageRgba8(current.clone()))?);
+    }
+
+    Ok(result)
+}
+
+/// Tauri IPC 命令：取色器，返回指定点附近圆形范围内的平均颜色
+///
+/// 坐标超出图像时夹取到边缘；完全透明的像素不参与平均
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `x` / `y` — 取色点坐标
+/// * `sample_radius` — 采样半径（像素），0 表示只取单个像素
+///
+/// # 返回值
+/// * `Ok(RgbaColor)` — 采样范围内的平均 RGBA 颜色
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_fetch_pixel_color(image_data: String, x: u32, y: u32, sample_radius: u32) -> Result<RgbaColor, String> {
+    let rgba = image_load_base64(&image_data)?.to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    let cx = x.min(width - 1) as i64;
+    let cy = y.min(height - 1) as i64;
+    let radius = sample_radius as i64;
+
+    let x_range = (cx - radius).max(0)..=(cx + radius).min(width as i64 - 1);
+    let y_range = (cy - radius).max(0)..=(cy + radius).min(height as i64 - 1);
+
+    let samples = y_range.flat_map(|py| x_range.clone().map(move |px| (px, py)))
+        .filter(|&(px, py)| (px - cx) * (px - cx) + (py - cy) * (py - cy) <= radius * radius)
+        .map(|(px, py)| rgba.get_pixel(px as u32, py as u32));
+
+    Ok(color_calc_average(samples))
+}
+
+/// 沿一个方向滑动固定长度窗口，返回窗口内能量和最大的起点；
+/// 能量相同时取最靠近居中位置的起点
+fn energy_calc_best_window(line_energy: &[u64], window: usize) -> usize {
+    if window >= line_energy.len() {
+        return 0;
+    }
+
+    let mut prefix = Vec::with_capacity(line_energy.len() + 1);
+    prefix.push(0u64);
+    for &value in line_energy {
+        prefix.push(prefix.last().copied().unwrap_or(0) + value);
+    }
+
+    let center = (line_energy.len() - window) / 2;
+    (0..=line_energy.len() - window)
+        .max_by_key(|&start| {
+            let energy = prefix[start + window] - prefix[start];
+            (energy, std::cmp::Reverse(start.abs_diff(center)))
+        })
+        .unwrap_or(center)
+}
+
+/// Tauri IPC 命令：按内容能量智能裁剪并缩放到目标尺寸
+///
+/// 以灰度梯度幅值作为能量图，在原图中选取目标宽高比的最大裁剪窗口，
+/// 沿可移动方向滑动并保留能量最多的位置，避免居中裁剪丢失偏离中心的主体。
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `target_width` / `target_height` — 输出尺寸
+///
+/// # 返回值
+/// * `Ok(String)` — 裁剪缩放后的 PNG data URL
+///
+/// # 异常
+/// * 目标尺寸为 0
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_smart_crop(image_data: String, target_width: u32, target_height: u32) -> Result<String, String> {
+    if target_width == 0 || target_height == 0 {
+        return Err("Target size must be non-zero".to_string());
+    }
+
+    let img = image_load_base64(&image_data)?;
+    let (width, height) = (img.width(), img.height());
+
+    let target_ratio = target_width as f64 / target_height as f64;
+    let (crop_width, crop_height) = if width as f64 / height as f64 > target_ratio {
+        (((height as f64 * target_ratio).round() as u32).clamp(1, width), height)
+    } else {
+        (width, ((width as f64 / target_ratio).round() as u32).clamp(1, height))
+    };
+
+    let gray = img.to_luma8();
+    let mut column_energy = vec![0u64; width as usize];
+    let mut row_energy = vec![0u64; height as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let value = gray.get_pixel(x, y)[0] as i32;
+            let right = gray.get_pixel((x + 1).min(width - 1), y)[0] as i32;
+            let below = gray.get_pixel(x, (y + 1).min(height - 1))[0] as i32;
+            let energy = ((right - value).abs() + (below - value).abs()) as u64;
+            column_energy[x as usize] += energy;
+            row_energy[y as usize] += energy;
+        }
+    }
+
+    let crop_x = energy_calc_best_window(&column_energy, crop_width as usize) as u32;
+    let crop_y = energy_calc_best_window(&row_energy, crop_height as usize) as u32;
+
+    let cropped = img.crop_imm(crop_x, crop_y, crop_width, crop_height)
+        .resize_exact(target_width, target_height, image::imageops::FilterType::Lanczos3);
+
+    image_encode_png_base64(&cropped)
+}
+
+/// 内容感知缩放单次最多处理的接缝数（宽、高各自计算），限制耗时
+const SEAM_CARVE_MAX_SEAMS: u32 = 512;
+
+/// 计算能量图（灰度梯度幅值），pixels 为按行排列的 RGBA 像素
+fn energy_calc_map(pixels: &[[u8; 4]], width: usize, height: usize) -> Vec<u32> {
+    let luma = |p: &[u8; 4]| (p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000;
+    let mut energy = vec![0u32; width * height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let left = luma(&pixels[y * width + x.saturating_sub(1)]);
+            let right = luma(&pixels[y * width + (x + 1).min(width - 1)]);
+            let up = luma(&pixels[y.saturating_sub(1) * width + x]);
+            let down = luma(&pixels[(y + 1).min(height - 1) * width + x]);
+            energy[y * width + x] = left.abs_diff(right) + up.abs_diff(down);
+        }
+    }
+
+    energy
+}
+
+/// 动态规划寻找能量最小的竖直接缝，返回每行接缝所在列
+fn seam_find_vertical(energy: &[u32], width: usize, height: usize) -> Vec<usize> {
+    let mut cost: Vec<u64> = energy[..width].iter().map(|&e| e as u64).collect();
+    let mut from = vec![0usize; width * height];
+
+    for y in 1..height {
+        let mut next = vec![0u64; width];
+        for x in 0..width {
+            let lo = x.saturating_sub(1);
+            let hi = (x + 1).min(width - 1);
+            let best = (lo..=hi).min_by_key(|&px| cost[px]).unwrap_or(x);
+            next[x] = cost[best] + energy[y * width + x] as u64;
+            from[y * width + x] = best;
+        }
+        cost = next;
+    }
+
+    let mut seam = vec![0usize; height];
+    seam[height - 1] = (0..width).min_by_key(|&x| cost[x]).unwrap_or(0);
+    for y in (1..height).rev() {
+        seam[y - 1] = from[y * width + seam[y]];
+    }
+    seam
+}
+
+/// 逐条删除或插入竖直接缝，将宽度调整到 target_width
+fn seam_carve_width(mut pixels: Vec<[u8; 4]>, mut width: usize, height: usize, target_width: usize) -> (Vec<[u8; 4]>, usize) {
+    if target_width < width {
+        while width > target_width {
+            let energy = energy_calc_map(&pixels, width, height);
+            let seam = seam_find_vertical(&energy, width, height);
+
+            let mut carved = Vec::with_capacity((width - 1) * height);
+            for (y, &skip) in seam.iter().enumerate() {
+                let row = &pixels[y * width..(y + 1) * width];
+                carved.extend_from_slice(&row[..skip]);
+                carved.extend_from_slice(&row[skip + 1..]);
+            }
+            pixels = carved;
+            width -= 1;
+        }
+    } else if target_width > width {
+        // 一次性选出 n 条互不重复的低能量接缝再插入，避免反复复制同一条接缝
+        let insert_count = target_width - width;
+        let mut working = pixels.clone();
+        let mut working_width = width;
+        let mut index_map: Vec<usize> = (0..width * height).map(|i| i % width).collect();
+        let mut seams: Vec<Vec<usize>> = Vec::with_capacity(insert_count);
+
+        for _ in 0..insert_count.min(working_width - 1) {
+            let energy = energy_calc_map(&working, working_width, height);
+            let seam = seam_find_vertical(&energy, working_width, height);
+            seams.push(seam.iter().enumerate().map(|(y, &x)| index_map[y * working_width + x]).collect());
+
+            let mut next_working = Vec::with_capacity((working_width - 1) * height);
+            let mut next_map = Vec::with_capacity((working_width - 1) * height);
+            for (y, &skip) in seam.iter().enumerate() {
+                let start = y * working_width;
+                for x in (0..working_width).filter(|&x| x != skip) {
+                    next_working.push(working[start + x]);
+                    next_map.push(index_map[start + x]);
+                }
+            }
+            working = next_working;
+            index_map = next_map;
+            working_width -= 1;
+        }
+
+        let new_width = width + seams.len();
+        let mut expanded = Vec::with_capacity(new_width * height);
+        for y in 0..height {
+            let mut duplicate = vec![false; width];
+            for seam in &seams {
+                duplicate[seam[y]] = true;
+            }
+            for x in 0..width {
+                let pixel = pixels[y * width + x];
+                expanded.push(pixel);
+                if duplicate[x] {
+                    let neighbor = pixels[y * width + (x + 1).min(width - 1)];
+                    let mut blended = [0u8; 4];
+                    for c in 0..4 {
+                        blended[c] = ((pixel[c] as u16 + neighbor[c] as u16) / 2) as u8;
+                    }
+                    expanded.push(blended);
+                }
+            }
+        }
+        pixels = expanded;
+        width = new_width;
+    }
+
+    (pixels, width)
+}
+
+/// 转置按行排列的像素，用于复用竖直接缝逻辑处理高度
+fn pixel_calc_transpose(pixels: &[[u8; 4]], width: usize, height: usize) -> Vec<[u8; 4]> {
+    let mut transposed = Vec::with_capacity(pixels.len());
+    for x in 0..width {
+        for y in 0..height {
+            transposed.push(pixels[y * width + x]);
+        }
+    }
+    transposed
+}
+
+/// Tauri IPC 命令：接缝裁剪（seam carving）内容感知缩放
+///
+/// 反复删除（或插入）能量最小的竖直/水平接缝直到达到目标尺寸，
+/// 背景等平坦区域优先被删除，主体不被拉伸变形。
+/// 宽、高方向各自最多处理 512 条接缝，超出部分改用普通缩放补足。
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `target_width` / `target_height` — 目标尺寸
+///
+/// # 返回值
+/// * `Ok(String)` — 缩放后的 PNG data URL
+///
+/// # 异常
+/// * 目标尺寸为 0 或单边超过 16384
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub async fn image_format_content_aware(image_data: String, target_width: u32, target_height: u32) -> Result<String, String> {
+    crate::canvas_validate_size(target_width, target_height)?;
+
+    tauri::async_runtime::spawn_blocking(move || image_calc_content_aware(&image_data, target_width, target_height))
+        .await
+        .map_err(|e| format!("Content-aware resize task failed: {}", e))?
+}
+
+/// 接缝裁剪的实际处理，目标尺寸已由 image_format_content_aware 校验
+fn image_calc_content_aware(image_data: &str, target_width: u32, target_height: u32) -> Result<String, String> {
+    let mut img = image_load_base64(image_data)?;
+
+    // 缩小超出接缝上限的部分先用普通缩放处理，放大超出的部分最后补足
+    let pre_width = img.width().min(target_width.saturating_add(SEAM_CARVE_MAX_SEAMS));
+    let pre_height = img.height().min(target_height.saturating_add(SEAM_CARVE_MAX_SEAMS));
+    if (pre_width, pre_height) != (img.width(), img.height()) {
+        img = img.resize_exact(pre_width, pre_height, image::imageops::FilterType::Lanczos3);
+    }
+
+    let rgba = img.to_rgba8();
+    let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+    let pixels: Vec<[u8; 4]> = rgba.pixels().map(|p| p.0).collect();
+
+    let carve_width = target_width.min(pre_width.saturating_add(SEAM_CARVE_MAX_SEAMS)) as usize;
+    let carve_height = target_height.min(pre_height.saturating_add(SEAM_CARVE_MAX_SEAMS)) as usize;
+
+    let (pixels, width) = seam_carve_width(pixels, width, height, carve_width);
+    let transposed = pixel_calc_transpose(&pixels, width, height);
+    let (transposed, height) = seam_carve_width(transposed, height, width, carve_height);
+    let pixels = pixel_calc_transpose(&transposed, height, width);
+
+    let raw: Vec<u8> = pixels.into_iter().flatten().collect();
+    let carved = image::RgbaImage::from_raw(width as u32, height as u32, raw)
+        .ok_or_else(|| "Failed to build carved image".to_string())?;
+
+    let mut result = DynamicImage::ImageRgba8(carved);
+    if (result.width(), result.height()) != (target_width, target_height) {
+        result = result.resize_exact(target_width, target_height, image::imageops::FilterType::Lanczos3);
+    }
+
+    image_encode_png_base64(&result)
+}
+
+/// SSIM 滑动窗口边长与步长
+const SSIM_WINDOW: u32 = 8;
+const SSIM_STRIDE: u32 = 4;
+
+/// 单个窗口的 SSIM 值
+fn ssim_calc_window(a: &image::GrayImage, b: &image::GrayImage, x0: u32, y0: u32, size: (u32, u32)) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (w, h) = size;
+    let n = (w * h) as f64;
+    let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let va = a.get_pixel(x, y)[0] as f64;
+            let vb = b.get_pixel(x, y)[0] as f64;
+            sum_a += va;
+            sum_b += vb;
+            sum_aa += va * va;
+            sum_bb += vb * vb;
+            sum_ab += va * vb;
+        }
+    }
+
+    let mean_a = sum_a / n;
+    let mean_b = sum_b / n;
+    let var_a = (sum_aa / n - mean_a * mean_a).max(0.0);
+    let var_b = (sum_bb / n - mean_b * mean_b).max(0.0);
+    let covariance = sum_ab / n - mean_a * mean_b;
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2))
+}
+
+/// Tauri IPC 命令：计算两张图片的结构相似度（SSIM）
+///
+/// 在灰度图上以 8×8 窗口、步长 4 滑动计算 SSIM 并取平均，
+/// 用于量化增强或压缩设置造成的损失
+///
+/// # 参数
+/// * `image_a` / `image_b` — base64 编码的图片数据，尺寸必须一致
+///
+/// # 返回值
+/// * `Ok(f32)` — 平均 SSIM，1.0 表示完全相同
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * 两张图片尺寸不一致
+#[tauri::command]
+pub fn image_calc_ssim(image_a: String, image_b: String) -> Result<f32, String> {
+    let a = image_load_base64(&image_a)?.to_luma8();
+    let b = image_load_base64(&image_b)?.to_luma8();
+
+    if a.dimensions() != b.dimensions() {
+        return Err(format!(
+            "Image size mismatch: {}x{} vs {}x{}",
+            a.width(), a.height(), b.width(), b.height()
+        ));
+    }
+
+    let (width, height) = a.dimensions();
+    let window = (SSIM_WINDOW.min(width), SSIM_WINDOW.min(height));
+    let window_starts = |length: u32, size: u32| -> Vec<u32> {
+        let mut starts: Vec<u32> = (0..=length - size).step_by(SSIM_STRIDE as usize).collect();
+        if starts.last() != Some(&(length - size)) {
+            starts.push(length - size);
+        }
+        starts
+    };
+    let xs = window_starts(width, window.0);
+    let ys = window_starts(height, window.1);
+
+    let total: f64 = ys.par_iter()
+        .map(|&y| xs.iter().map(|&x| ssim_calc_window(&a, &b, x, y, window)).sum::<f64>())
+        .sum();
+
+    Ok((total / (xs.len() * ys.len()) as f64) as f32)
+}
+
+/// 双线性采样单个通道，坐标超出边界时夹取到边缘像素
+fn channel_sample_bilinear(img: &image::RgbaImage, x: f32, y: f32, channel: usize) -> u8 {
+    let max_x = (img.width() - 1) as f32;
+    let max_y = (img.height() - 1) as f32;
+    let x = x.clamp(0.0, max_x);
+    let y = y.clamp(0.0, max_y);
+
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(img.width() - 1), (y0 + 1).min(img.height() - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let value = |px: u32, py: u32| img.get_pixel(px, py)[channel] as f32;
+    let top = value(x0, y0) * (1.0 - fx) + value(x1, y0) * fx;
+    let bottom = value(x0, y1) * (1.0 - fx) + value(x1, y1) * fx;
+    (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8
+}
+
+/// Tauri IPC 命令：色差（chromatic aberration）效果
+///
+/// 绿色通道保持不动，红色通道沿 angle 方向平移 offset 像素，蓝色通道反向平移，
+/// 形成彩色镶边效果；超出边界的采样夹取到边缘
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `offset` — 平移距离（像素，可为小数）
+/// * `angle` — 平移方向（角度，0 为水平向右，90 为竖直向下）
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL
+///
+/// # 异常
+/// * 参数非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_chromatic_aberration(image_data: String, offset: f32, angle: f32) -> Result<String, String> {
+    if !offset.is_finite() || !angle.is_finite() {
+        return Err("Offset and angle must be finite".to_string());
+    }
+
+    let source = image_load_base64(&image_data)?.to_rgba8();
+    let width = source.width();
+    let radians = angle.to_radians();
+    let (dx, dy) = (offset * radians.cos(), offset * radians.sin());
+
+    let mut output = source.clone();
+    output.par_chunks_exact_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let (fx, fy) = (x as f32, y as f32);
+                pixel[0] = channel_sample_bilinear(&source, fx - dx, fy - dy, 0);
+                pixel[2] = channel_sample_bilinear(&source, fx + dx, fy + dy, 2);
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 运动模糊的最大距离（像素），限制每个像素的采样次数
+const MOTION_BLUR_MAX_DISTANCE: f32 = 512.0;
+
+/// Tauri IPC 命令：运动模糊，表现速度感
+///
+/// 每个像素取以自身为中心、沿 angle 方向长 distance 的线段上的等距采样平均值，
+/// 采样间隔约 1 像素；超出边界的采样夹取到边缘。颜色按 alpha 预乘后再求平均，
+/// 透明像素的颜色不会渗入相邻的不透明区域。distance 为 0 时图像不变；
+/// 大图处理耗时较长，在后台线程中执行
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `distance` — 模糊距离（像素，0 到 512）
+/// * `angle` — 模糊方向（角度，0 为水平，90 为竖直）
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL
+///
+/// # 异常
+/// * 参数非法或距离超出范围
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub async fn image_update_motion_blur(image_data: String, distance: f32, angle: f32) -> Result<String, String> {
+    if !distance.is_finite() || !angle.is_finite() {
+        return Err("Distance and angle must be finite".to_string());
+    }
+    if !(0.0..=MOTION_BLUR_MAX_DISTANCE).contains(&distance) {
+        return Err(format!("Motion blur distance must be between 0 and {}", MOTION_BLUR_MAX_DISTANCE));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || image_calc_motion_blur(&image_data, distance, angle))
+        .await
+        .map_err(|e| format!("Motion blur task failed: {}", e))?
+}
+
+/// 运动模糊的实际处理，参数已由 image_update_motion_blur 校验
+fn image_calc_motion_blur(image_data: &str, distance: f32, angle: f32) -> Result<String, String> {
+    let source = image_load_base64(image_data)?.to_rgba8();
+    if distance == 0.0 {
+        return image_encode_png_base64(&DynamicImage::ImageRgba8(source));
+    }
+
+    // 预乘 alpha，采样平均后再还原
+    let mut premultiplied = source;
+    premultiplied.pixels_mut().for_each(|pixel| {
+        let alpha = pixel[3] as u32;
+        for value in &mut pixel.0[..3] {
+            *value = ((*value as u32 * alpha + 127) / 255) as u8;
+        }
+    });
+
+    let width = premultiplied.width();
+    let radians = angle.to_radians();
+    let samples = distance.ceil() as u32 + 1;
+    // 采样偏移沿方向均匀分布在 [-distance/2, distance/2]
+    let offsets: Vec<(f32, f32)> = (0..samples)
+        .map(|i| {
+            let t = i as f32 / (samples - 1) as f32 * distance - distance / 2.0;
+            (t * radians.cos(), t * radians.sin())
+        })
+        .collect();
+
+    let mut output = premultiplied.clone();
+    output.par_chunks_exact_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let mut sums = [0u32; 4];
+                for &(dx, dy) in &offsets {
+                    let (sx, sy) = (x as f32 + dx, y as f32 + dy);
+                    for (channel, sum) in sums.iter_mut().enumerate() {
+                        *sum += channel_sample_bilinear(&premultiplied, sx, sy, channel) as u32;
+                    }
+                }
+                let alpha_sum = sums[3];
+                pixel[3] = (alpha_sum as f32 / samples as f32).round() as u8;
+                for (value, sum) in pixel[..3].iter_mut().zip(sums) {
+                    *value = if alpha_sum == 0 {
+                        0
+                    } else {
+                        (sum as f32 * 255.0 / alpha_sum as f32).round().min(255.0) as u8
+                    };
+                }
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 移轴效果的最大模糊半径（高斯 sigma，像素）
+const TILT_SHIFT_MAX_BLUR: f32 = 100.0;
+
+/// 移轴效果预先计算的模糊层数，行间模糊程度在相邻两层之间线性插值
+const TILT_SHIFT_BLUR_LEVELS: usize = 4;
+
+/// Tauri IPC 命令：移轴（选择性对焦）效果，模拟微缩模型
+///
+/// 以 focus_center 为中心、高 focus_width 的水平带保持清晰，带外向上、向下逐渐模糊，
+/// 到图像上下边缘时达到 max_blur。模糊使用 fast_blur（多次盒式模糊近似高斯，耗时与半径无关），
+/// 预先生成若干层后按行混合；大图处理耗时较长，在后台线程中执行
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `focus_center` — 清晰带中心位置，占图像高度的比例（0 到 1）
+/// * `focus_width` — 清晰带高度，占图像高度的比例（0 到 1）
+/// * `max_blur` — 边缘处的模糊半径（高斯 sigma，0 到 100），为 0 时图像不变
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL
+///
+/// # 异常
+/// * 参数非法或超出范围
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub async fn image_update_tilt_shift(image_data: String, focus_center: f32, focus_width: f32, max_blur: f32) -> Result<String, String> {
+    if !(0.0..=1.0).contains(&focus_center) || !(0.0..=1.0).contains(&focus_width) {
+        return Err("Focus center and width must be between 0 and 1".to_string());
+    }
+    if !(0.0..=TILT_SHIFT_MAX_BLUR).contains(&max_blur) {
+        return Err(format!("Max blur must be between 0 and {}", TILT_SHIFT_MAX_BLUR));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || image_calc_tilt_shift(&image_data, focus_center, focus_width, max_blur))
+        .await
+        .map_err(|e| format!("Tilt-shift task failed: {}", e))?
+}
+
+/// 移轴效果的实际处理，参数已由 image_update_tilt_shift 校验
+fn image_calc_tilt_shift(image_data: &str, focus_center: f32, focus_width: f32, max_blur: f32) -> Result<String, String> {
+    let source = image_load_base64(image_data)?.to_rgba8();
+    if max_blur == 0.0 {
+        return image_encode_png_base64(&DynamicImage::ImageRgba8(source));
+    }
+
+    let (width, height) = source.dimensions();
+    let band_top = ((focus_center - focus_width / 2.0) * height as f32).max(0.0);
+    let band_bottom = ((focus_center + focus_width / 2.0) * height as f32).min(height as f32);
+
+    // 第 0 层为原图，第 i 层 sigma 为 max_blur * i / TILT_SHIFT_BLUR_LEVELS
+    let blurred: Vec<image::RgbaImage> = (1..=TILT_SHIFT_BLUR_LEVELS)
+        .into_par_iter()
+        .map(|level| image::imageops::fast_blur(&source, max_blur * level as f32 / TILT_SHIFT_BLUR_LEVELS as f32))
+        .collect();
+    let levels: Vec<&image::RgbaImage> = std::iter::once(&source).chain(blurred.iter()).collect();
+
+    let row_len = width as usize * 4;
+    let mut output = source.clone();
+    output.par_chunks_exact_mut(row_len)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let center = y as f32 + 0.5;
+            let strength = if center < band_top {
+                (band_top - center) / band_top
+            } else if center > band_bottom {
+                (center - band_bottom) / (height as f32 - band_bottom)
+            } else {
+                0.0
+            };
+            if strength <= 0.0 {
+                return;
+            }
+
+            let position = strength.min(1.0) * TILT_SHIFT_BLUR_LEVELS as f32;
+            let lower = (position.floor() as usize).min(TILT_SHIFT_BLUR_LEVELS - 1);
+            let t = position - lower as f32;
+            let offset = y * row_len;
+            let below = &levels[lower].as_raw()[offset..offset + row_len];
+            let above = &levels[lower + 1].as_raw()[offset..offset + row_len];
+            for ((value, &a), &b) in row.iter_mut().zip(below).zip(above) {
+                *value = (a as f32 * (1.0 - t) + b as f32 * t).round() as u8;
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 色调分离的抖动方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMode {
+    /// 不抖动，直接量化
+    #[default]
+    None,
+    /// Floyd–Steinberg 误差扩散
+    FloydSteinberg,
+    /// 4×4 Bayer 有序抖动
+    Ordered,
+}
+
+impl DitherMode {
+    /// 解析 "none" / "floyd_steinberg" / "ordered"，未指定时不抖动
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("none") => Ok(Self::None),
+            Some("floyd_steinberg") => Ok(Self::FloydSteinberg),
+            Some("ordered") => Ok(Self::Ordered),
+            Some(other) => Err(format!("Invalid dither mode: {} (expected none, floyd_steinberg or ordered)", other)),
+        }
+    }
+}
+
+/// 4×4 Bayer 阈值矩阵
+const BAYER_MATRIX_4X4: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5],
+];
+
+/// 将通道值量化到最近的级别
+fn channel_calc_quantize(value: f32, step: f32) -> u8 {
+    ((value.clamp(0.0, 255.0) / step).round() * step).round().clamp(0.0, 255.0) as u8
+}
+
+/// Floyd–Steinberg 误差扩散量化，必须按光栅顺序串行处理
+fn pixel_update_floyd_steinberg(rgba: &mut image::RgbaImage, step: f32) {
+    let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+    let mut values: Vec<f32> = rgba.as_raw().iter().map(|&v| v as f32).collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            for c in 0..3 {
+                let index = (y * width + x) * 4 + c;
+                let old = values[index];
+                let new = channel_calc_quantize(old, step) as f32;
+                values[index] = new;
+                let error = old - new;
+
+                let mut spread = |dx: isize, dy: usize, weight: f32| {
+                    let nx = x as isize + dx;
+                    let ny = y + dy;
+                    if nx >= 0 && (nx as usize) < width && ny < height {
+                        values[(ny * width + nx as usize) * 4 + c] += error * weight;
+                    }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+
+    for (dst, (i, &value)) in rgba.iter_mut().zip(values.iter().enumerate()) {
+        if i % 4 != 3 {
+            *dst = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Tauri IPC 命令：色调分离，将每个颜色通道量化为指定级数
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `levels` — 每通道级数，取值 2..=256；256 时图像不变
+/// * `dither` — 抖动方式（"none"/"floyd_steinberg"/"ordered"），默认不抖动，
+///   抖动可以减轻级数较少时的色带
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL（alpha 通道保持不变）
+///
+/// # 异常
+/// * 级数超出范围或抖动方式非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_posterize(image_data: String, levels: u16, dither: Option<String>) -> Result<String, String> {
+    if !(2..=256).contains(&levels) {
+        return Err(format!("Levels must be between 2 and 256, got {}", levels));
+    }
+    let dither = DitherMode::parse(dither.as_deref())?;
+
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+    let step = 255.0 / (levels - 1) as f32;
+    let width = rgba.width() as usize;
+
+    match dither {
+        DitherMode::None => {
+            let lut: Vec<u8> = (0..=255u16)
+                .map(|v| channel_calc_quantize(v as f32, step))
+                .collect();
+            rgba.par_chunks_exact_mut(4).for_each(|pixel| {
+                for value in &mut pixel[..3] {
+                    *value = lut[*value as usize];
+                }
+            });
+        }
+        DitherMode::Ordered => {
+            rgba.par_chunks_exact_mut(width * 4)
+                .enumerate()
+                .for_each(|(y, row)| {
+                    for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                        let threshold = (BAYER_MATRIX_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+                        for value in &mut pixel[..3] {
+                            *value = channel_calc_quantize(*value as f32 + threshold * step, step);
+                        }
+                    }
+                });
+        }
+        DitherMode::FloydSteinberg => pixel_update_floyd_steinberg(&mut rgba, step),
+    }
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// 图像重采样插值方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// 最近邻，速度最快，适合像素画
+    Nearest,
+    /// 双线性
+    #[default]
+    Bilinear,
+    /// 双三次（Catmull-Rom），适合照片
+    Bicubic,
+}
+
+impl Interpolation {
+    /// 解析 "nearest" / "bilinear" / "bicubic"，未指定时默认双线性
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("bilinear") => Ok(Self::Bilinear),
+            Some("nearest") => Ok(Self::Nearest),
+            Some("bicubic") => Ok(Self::Bicubic),
+            Some(other) => Err(format!("Invalid interpolation: {} (expected nearest, bilinear or bicubic)", other)),
+        }
+    }
+}
+
+/// Catmull-Rom 三次卷积核
+fn kernel_calc_cubic(t: f32) -> f32 {
+    let t = t.abs();
+    if t < 1.0 {
+        1.5 * t * t * t - 2.5 * t * t + 1.0
+    } else if t < 2.0 {
+        -0.5 * t * t * t + 2.5 * t * t - 4.0 * t + 2.0
+    } else {
+        0.0
+    }
+}
+
+/// 按插值方式在像素坐标 (x, y) 处采样（像素中心为整数坐标），
+/// 超出图像范围返回 None，邻域越界部分夹取到边缘
+pub fn pixel_sample_interpolated(img: &image::RgbaImage, x: f32, y: f32, mode: Interpolation) -> Option<[u8; 4]> {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    if x < -0.5 || y < -0.5 || x >= width as f32 - 0.5 || y >= height as f32 - 0.5 {
+        return None;
+    }
+
+    let fetch = |px: i64, py: i64| img.get_pixel(px.clamp(0, width - 1) as u32, py.clamp(0, height - 1) as u32).0;
+
+    match mode {
+        Interpolation::Nearest => Some(fetch(x.round() as i64, y.round() as i64)),
+        Interpolation::Bilinear => {
+            let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+            let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+            let (p00, p10, p01, p11) = (fetch(x0, y0), fetch(x0 + 1, y0), fetch(x0, y0 + 1), fetch(x0 + 1, y0 + 1));
+
+            let mut out = [0u8; 4];
+            for c in 0..4 {
+                let top = p00[c] as f32 * (1.0 - fx) + p10[c] as f32 * fx;
+                let bottom = p01[c] as f32 * (1.0 - fx) + p11[c] as f32 * fx;
+                out[c] = (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8;
+            }
+            Some(out)
+        }
+        Interpolation::Bicubic => {
+            let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+            let mut sums = [0.0f32; 4];
+            for j in -1..=2 {
+                let wy = kernel_calc_cubic(y - (y0 + j) as f32);
+                for i in -1..=2 {
+                    let weight = wy * kernel_calc_cubic(x - (x0 + i) as f32);
+                    let pixel = fetch(x0 + i, y0 + j);
+                    for c in 0..4 {
+                        sums[c] += pixel[c] as f32 * weight;
+                    }
+                }
+            }
+            Some(sums.map(|v| v.round().clamp(0.0, 255.0) as u8))
+        }
+    }
+}
+
+/// Tauri IPC 命令：按任意角度旋转图像，画布自动扩展以容纳完整内容
+///
+/// 扩展出的区域为透明
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `angle` — 旋转角度（度，顺时针为正）
+/// * `interpolation` — 插值方式（"nearest"/"bilinear"/"bicubic"），默认双线性
+///
+/// # 返回值
+/// * `Ok(String)` — 旋转后的 PNG data URL
+///
+/// # 异常
+/// * 角度非法或插值方式非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_rotation_angle(image_data: String, angle: f32, interpolation: Option<String>) -> Result<String, String> {
+    if !angle.is_finite() {
+        return Err(format!("Invalid angle: {}", angle));
+    }
+    let mode = Interpolation::parse(interpolation.as_deref())?;
+
+    let source = image_load_base64(&image_data)?.to_rgba8();
+    let (width, height) = (source.width() as f32, source.height() as f32);
+
+    let radians = angle.to_radians();
+    let (sin, cos) = radians.sin_cos();
+    let out_width = ((width * cos.abs() + height * sin.abs()) - 1e-3).ceil().max(1.0) as u32;
+    let out_height = ((width * sin.abs() + height * cos.abs()) - 1e-3).ceil().max(1.0) as u32;
+
+    let (src_cx, src_cy) = (width / 2.0, height / 2.0);
+    let (dst_cx, dst_cy) = (out_width as f32 / 2.0, out_height as f32 / 2.0);
+
+    let mut output = image::RgbaImage::new(out_width, out_height);
+    output.par_chunks_exact_mut(out_width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let dy = y as f32 + 0.5 - dst_cy;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let dx = x as f32 + 0.5 - dst_cx;
+                // 逆旋转回源图坐标
+                let sx = dx * cos + dy * sin + src_cx - 0.5;
+                let sy = -dx * sin + dy * cos + src_cy - 0.5;
+                if let Some(sample) = pixel_sample_interpolated(&source, sx, sy, mode) {
+                    pixel.copy_from_slice(&sample);
+                }
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 自动增强的分析结果与输出图像
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoEnhanceResult {
+    /// 增强后的 PNG data URL
+    pub image_data: String,
+    /// 对比度倍数，1.0 为不变，与 image_update_adjustments 含义一致
+    pub contrast: f32,
+    /// 亮度偏移（-100..100），与 image_update_adjustments 含义一致
+    pub brightness: i32,
+    /// 饱和度倍数，1.0 为不变
+    pub saturation: f32,
+}
+
+/// 对 RGBA 图像依次应用对比度、亮度（与 image_update_adjustments 含义一致）和饱和度
+///
+/// 对比度和亮度的结果先夹取到有效范围再计算饱和度，避免超出范围的值参与灰度计算导致色相反转
+pub fn pixel_update_enhance(rgba: &mut image::RgbaImage, contrast: f32, brightness: i32, saturation: f32) {
+    let add = brightness as f32 * 255.0 / 100.0;
+    let lut: Vec<f32> = (0..256)
+        .map(|i| (((i as f32 / 255.0 - 0.5) * contrast + 0.5) * 255.0 + add).clamp(0.0, 255.0))
+        .collect();
+
+    rgba.par_chunks_exact_mut(4).for_each(|pixel| {
+        let adjusted = [lut[pixel[0] as usize], lut[pixel[1] as usize], lut[pixel[2] as usize]];
+        let gray = adjusted[0] * 0.299 + adjusted[1] * 0.587 + adjusted[2] * 0.114;
+        for (value, channel) in pixel[..3].iter_mut().zip(adjusted) {
+            *value = (gray + (channel - gray) * saturation).round().clamp(0.0, 255.0) as u8;
+        }
+    });
+}
+
+/// 在线性光空间应用对比度、亮度和饱和度，参数含义与 pixel_update_enhance 相同
+///
+/// 先用精确的 sRGB 传递函数解码为线性光强，计算后再编码回 sRGB；
+/// 饱和度使用线性光下的 Rec. 709 亮度系数
+pub fn pixel_update_enhance_linear(rgba: &mut image::RgbaImage, contrast: f32, brightness: i32, saturation: f32) {
+    let add = brightness as f32 / 100.0;
+    let lut: Vec<f32> = (0..256)
+        .map(|i| ((channel_calc_to_linear(i as u8) - 0.5) * contrast + 0.5 + add).clamp(0.0, 1.0))
+        .collect();
+
+    rgba.par_chunks_exact_mut(4).for_each(|pixel| {
+        let adjusted = [lut[pixel[0] as usize], lut[pixel[1] as usize], lut[pixel[2] as usize]];
+        let luminance = adjusted[0] * 0.2126 + adjusted[1] * 0.7152 + adjusted[2] * 0.0722;
+        for (value, channel) in pixel[..3].iter_mut().zip(adjusted) {
+            *value = channel_calc_from_linear(luminance + (channel - luminance) * saturation);
+        }
+    });
+}
+
+/// 直方图中累计占比达到 fraction 时的亮度值
+fn histogram_calc_percentile(histogram: &[u64; 256], total: u64, fraction: f64) -> u8 {
+    let target = (total as f64 * fraction).ceil() as u64;
+    let mut cumulative = 0u64;
+    for (value, &count) in histogram.iter().enumerate() {
+        cumulative += count;
+        if cumulative >= target.max(1) {
+            return value as u8;
+        }
+    }
+    255
+}
+
+/// Tauri IPC 命令：一键自动增强
+///
+/// 统计亮度直方图（1%/99% 分位、平均亮度、高光裁切比例）和平均饱和度，
+/// 据此推算对比度、亮度、饱和度参数并应用，同时返回所选参数供界面展示和微调
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(AutoEnhanceResult)` — 增强后的图像及选用的参数
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_auto_enhance(image_data: String) -> Result<AutoEnhanceResult, String> {
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+
+    let luma = |p: &[u8]| (p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000;
+    let mut histogram = [0u64; 256];
+    let mut saturation_sum = 0u64;
+    let mut total = 0u64;
+    for pixel in rgba.chunks_exact(4).filter(|p| p[3] > 0) {
+        histogram[luma(pixel) as usize] += 1;
+        let max = pixel[..3].iter().max().copied().unwrap_or(0);
+        let min = pixel[..3].iter().min().copied().unwrap_or(0);
+        saturation_sum += (max - min) as u64;
+        total += 1;
+    }
+
+    if total == 0 {
+        return Ok(AutoEnhanceResult {
+            image_data: image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))?,
+            contrast: 1.0,
+            brightness: 0,
+            saturation: 1.0,
+        });
+    }
+
+    let low = histogram_calc_percentile(&histogram, total, 0.01) as f32;
+    let high = histogram_calc_percentile(&histogram, total, 0.99) as f32;
+    let mean = histogram.iter().enumerate().map(|(v, &c)| v as u64 * c).sum::<u64>() as f32 / total as f32;
+    let highlight_clipped = histogram[250..].iter().sum::<u64>() as f32 / total as f32;
+    let mean_saturation = saturation_sum as f32 / total as f32 / 255.0;
+
+    // 将 1%~99% 的亮度范围拉伸到约 90% 动态范围
+    let contrast = (230.0 / (high - low).max(1.0)).clamp(1.0, 2.0);
+
+    // 对比度以 0.5 为中心拉伸后，再把平均亮度推向中间调；高光已大量裁切时不再提亮
+    let stretched_mean = (mean - 127.5) * contrast + 127.5;
+    let mut brightness = ((118.0 - stretched_mean) / 2.55).clamp(-40.0, 40.0);
+    if highlight_clipped > 0.02 {
+        brightness = brightness.min(0.0);
+    }
+    let brightness = brightness.round() as i32;
+
+    let saturation = (1.0 + (0.25 - mean_saturation) * 1.5).clamp(1.0, 1.4);
+
+    pixel_update_enhance(&mut rgba, contrast, brightness, saturation);
+
+    Ok(AutoEnhanceResult {
+        image_data: image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))?,
+        contrast,
+        brightness,
+        saturation,
+    })
+}
+
+/// HSV（h: 0..360, s/v: 0..1）转 RGB
+fn color_calc_from_hsv(h: f32, s: f32, v: f32) -> [u8; 3] {
+    let c = v * s;
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// RGB 转 HSV（h: 0..360, s/v: 0..1），灰色的色相为 0
+fn color_calc_to_hsv(rgb: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb.map(|channel| channel as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+
+    (h, s, max)
+}
+
+/// 彩条测试图的七种颜色（100% 强度）
+const COLOR_BARS: [[u8; 3]; 7] = [
+    [255, 255, 255],
+    [255, 255, 0],
+    [0, 255, 255],
+    [0, 255, 0],
+    [255, 0, 255],
+    [255, 0, 0],
+    [0, 0, 255],
+];
+
+/// 灰阶测试图的阶数
+const GRAY_RAMP_STEPS: u32 = 16;
+
+/// Tauri IPC 命令：生成已知色值的测试图，用于校准增强参数
+///
+/// # 参数
+/// * `kind` — 图案类型：
+///   "colorbars" 七色彩条；"gradient" 水平色相、竖直饱和度渐变；
+///   "gray_ramp" 16 阶灰阶；"checker" 黑白棋盘格
+/// * `width` / `height` — 输出尺寸
+///
+/// # 返回值
+/// * `Ok(String)` — 测试图 PNG data URL
+///
+/// # 异常
+/// * 尺寸为 0 或单边超过 16384
+/// * 图案类型未知
+#[tauri::command]
+pub fn image_render_test_pattern(kind: String, width: u32, height: u32) -> Result<String, String> {
+    crate::canvas_validate_size(width, height)?;
+
+    let checker_size = (width.max(height) / 16).max(1);
+    let pattern: Box<dyn Fn(u32, u32) -> [u8; 3] + Sync> = match kind.as_str() {
+        "colorbars" => Box::new(move |x, _| COLOR_BARS[(x as usize * COLOR_BARS.len()) / width as usize]),
+        "gradient" => Box::new(move |x, y| {
+            let hue = x as f32 / width as f32 * 360.0;
+            let saturation = 1.0 - y as f32 / (height - 1).max(1) as f32;
+            color_calc_from_hsv(hue, saturation, 1.0)
+        }),
+        "gray_ramp" => Box::new(move |x, _| {
+            let step = x * GRAY_RAMP_STEPS / width;
+            let value = (step * 255 / (GRAY_RAMP_STEPS - 1)) as u8;
+            [value; 3]
+        }),
+        "checker" => Box::new(move |x, y| {
+            if (x / checker_size + y / checker_size).is_multiple_of(2) { [0; 3] } else { [255; 3] }
+        }),
+        other => return Err(format!("Unknown test pattern: {} (expected colorbars, gradient, gray_ramp or checker)", other)),
+    };
+
+    let mut rgba = image::RgbaImage::new(width, height);
+    rgba.par_chunks_exact_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let [r, g, b] = pattern(x as u32, y as u32);
+                pixel.copy_from_slice(&[r, g, b, 255]);
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// 分块结果：按行优先顺序排列的分块及布局信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TiledResult {
+    /// 分块 PNG data URL，下标 = row * cols + col
+    pub tiles: Vec<String>,
+    pub cols: u32,
+    pub rows: u32,
+    pub tile_size: u32,
+    /// 最后一列分块的宽度（可能小于 tile_size）
+    pub last_col_width: u32,
+    /// 最后一行分块的高度（可能小于 tile_size）
+    pub last_row_height: u32,
+}
+
+/// Tauri IPC 命令：将大图（如全景图）切分为固定大小的分块，便于渐进显示
+///
+/// 右侧和底部边缘的分块按实际剩余尺寸裁切，不做填充
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `tile_size` — 分块边长（像素）
+///
+/// # 返回值
+/// * `Ok(TiledResult)` — 分块数据与布局
+///
+/// # 异常
+/// * tile_size 为 0
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_tiles(image_data: String, tile_size: u32) -> Result<TiledResult, String> {
+    if tile_size == 0 {
+        return Err("Tile size must be non-zero".to_string());
+    }
+
+    let img = image_load_base64(&image_data)?;
+    let (width, height) = (img.width(), img.height());
+    let cols = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+
+    let tiles = (0..rows * cols)
+        .into_par_iter()
+        .map(|index| {
+            let (col, row) = (index % cols, index / cols);
+            let (x, y) = (col * tile_size, row * tile_size);
+            let tile = img.crop_imm(x, y, tile_size.min(width - x), tile_size.min(height - y));
+            image_encode_png_base64(&tile)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TiledResult {
+        tiles,
+        cols,
+        rows,
+        tile_size,
+        last_col_width: width - (cols - 1) * tile_size,
+        last_row_height: height - (rows - 1) * tile_size,
+    })
+}
+
+/// Tauri IPC 命令：将分块拼回完整图像，与 image_format_tiles 互逆
+///
+/// 完整尺寸由首行分块宽度之和与首列分块高度之和推算；
+/// 同一列分块宽度、同一行分块高度必须一致
+///
+/// # 参数
+/// * `tiles` — 按行优先顺序排列的 base64 分块
+/// * `cols` / `rows` — 分块列数与行数
+///
+/// # 返回值
+/// * `Ok(String)` — 拼接后的 PNG data URL
+///
+/// # 异常
+/// * 分块数量与 cols * rows 不符
+/// * 分块尺寸不一致
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_untile(tiles: Vec<String>, cols: u32, rows: u32) -> Result<String, String> {
+    if cols == 0 || rows == 0 || tiles.len() != (cols as usize) * (rows as usize) {
+        return Err(format!("Expected {}x{} tiles, got {}", cols, rows, tiles.len()));
+    }
+
+    let decoded = tiles.par_iter()
+        .map(|tile| image_load_base64(tile).map(|img| img.to_rgba8()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let col_widths: Vec<u32> = (0..cols as usize).map(|col| decoded[col].width()).collect();
+    let row_heights: Vec<u32> = (0..rows as usize).map(|row| decoded[row * cols as usize].height()).collect();
+
+    for (index, tile) in decoded.iter().enumerate() {
+        let (col, row) = (index % cols as usize, index / cols as usize);
+        if tile.dimensions() != (col_widths[col], row_heights[row]) {
+            return Err(format!(
+                "Tile ({}, {}) is {}x{}, expected {}x{}",
+                col, row, tile.width(), tile.height(), col_widths[col], row_heights[row]
+            ));
+        }
+    }
+
+    let mut output = image::RgbaImage::new(col_widths.iter().sum(), row_heights.iter().sum());
+    let mut y = 0;
+    for (row, &row_height) in row_heights.iter().enumerate() {
+        let mut x = 0;
+        for (col, &col_width) in col_widths.iter().enumerate() {
+            image::imageops::replace(&mut output, &decoded[row * cols as usize + col], x as i64, y as i64);
+            x += col_width;
+        }
+        y += row_height;
+    }
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 增强参数，含义与 pixel_update_enhance 一致
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EnhanceParams {
+    pub contrast: f32,
+    pub brightness: i32,
+    pub saturation: f32,
+    /// 为 true 时在线性光空间计算（见 pixel_update_enhance_linear），默认在 sRGB 空间计算
+    pub linear_light: Option<bool>,
+}
+
+impl EnhanceParams {
+    /// 校验参数：对比度和饱和度必须为非负有限值，亮度在 -100..100 之间
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.contrast.is_finite() || self.contrast < 0.0 {
+            return Err(format!("Invalid contrast: {}", self.contrast));
+        }
+        if !self.saturation.is_finite() || self.saturation < 0.0 {
+            return Err(format!("Invalid saturation: {}", self.saturation));
+        }
+        if !(-100..=100).contains(&self.brightness) {
+            return Err(format!("Brightness out of range (-100..100): {}", self.brightness));
+        }
+        Ok(())
+    }
+}
+
+/// 按增强参数处理 RGBA 图像，根据 linear_light 选择计算空间
+pub fn pixel_update_enhance_params(rgba: &mut image::RgbaImage, params: &EnhanceParams) {
+    if params.linear_light.unwrap_or(false) {
+        pixel_update_enhance_linear(rgba, params.contrast, params.brightness, params.saturation);
+    } else {
+        pixel_update_enhance(rgba, params.contrast, params.brightness, params.saturation);
+    }
+}
+
+/// 命名的增强预设
+#[derive(Debug, Clone, Copy)]
+struct EnhancePreset {
+    name: &'static str,
+    params: EnhanceParams,
+}
+
+/// 内置增强预设，统一在后端定义以保证各处效果一致
+const ENHANCE_PRESETS: [EnhancePreset; 3] = [
+    // 文档：强对比、提亮纸张、降低饱和度以去除偏色
+    EnhancePreset { name: "document", params: EnhanceParams { contrast: 1.6, brightness: 15, saturation: 0.8, linear_light: None } },
+    // 白板：与默认设置一致，突出笔迹颜色
+    EnhancePreset { name: "whiteboard", params: EnhanceParams { contrast: 1.4, brightness: 10, saturation: 1.2, linear_light: None } },
+    // 照片：轻微增强，保持自然
+    EnhancePreset { name: "photo", params: EnhanceParams { contrast: 1.1, brightness: 0, saturation: 1.15, linear_light: None } },
+];
+
+/// Tauri IPC 命令：列出可用的增强预设名称
+#[tauri::command]
+pub fn image_list_presets() -> Vec<String> {
+    ENHANCE_PRESETS.iter().map(|preset| preset.name.to_string()).collect()
+}
+
+/// Tauri IPC 命令：按名称应用增强预设（"document"/"whiteboard"/"photo"）
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `preset` — 预设名称，可用名称见 image_list_presets
+///
+/// # 返回值
+/// * `Ok(String)` — 增强后的 PNG data URL
+///
+/// # 异常
+/// * 预设名称未知
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_preset(image_data: String, preset: String) -> Result<String, String> {
+    let params = ENHANCE_PRESETS.iter()
+        .find(|p| p.name == preset)
+        .map(|p| p.params)
+        .ok_or_else(|| format!("Unknown preset: {}", preset))?;
+
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+    pixel_update_enhance_params(&mut rgba, &params);
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// Tauri IPC 命令：将多张图片合成为动画 GIF（延时摄影、连拍）
+///
+/// 所有帧缩放到第一帧的尺寸后按顺序编码；帧数多时耗时较长，在后台线程中执行
+///
+/// # 参数
+/// * `images` — base64 编码的帧图片数组
+/// * `delay_ms` — 每帧显示时长（毫秒）
+/// * `loop_count` — 循环次数，0 表示无限循环
+///
+/// # 返回值
+/// * `Ok(String)` — data:image/gif;base64 前缀的动画数据
+///
+/// # 异常
+/// * 帧数组为空
+/// * base64 解析失败或图像格式不支持
+/// * GIF 编码失败
+#[tauri::command]
+pub async fn image_format_gif(images: Vec<String>, delay_ms: u16, loop_count: u16) -> Result<String, String> {
+    if images.is_empty() {
+        return Err("No frames provided".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || image_calc_gif(&images, delay_ms, loop_count))
+        .await
+        .map_err(|e| format!("GIF task failed: {}", e))?
+}
+
+/// GIF 合成的实际处理，images 非空已由 image_format_gif 校验
+fn image_calc_gif(images: &[String], delay_ms: u16, loop_count: u16) -> Result<String, String> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+
+    // 每帧只解码一次，输出尺寸取自第一帧
+    let decoded = images.par_iter()
+        .map(|image_data| image_load_base64(image_data))
+        .collect::<Result<Vec<_>, String>>()?;
+    let first = decoded.first().ok_or_else(|| "No frames provided".to_string())?;
+    let (width, height) = (first.width(), first.height());
+
+    let frames: Vec<image::Frame> = decoded.into_par_iter()
+        .map(|img| {
+            let img = if (img.width(), img.height()) == (width, height) {
+                img
+            } else {
+                img.resize_exact(width, height, image::imageops::FilterType::Triangle)
+            };
+            image::Frame::from_parts(
+                img.to_rgba8(),
+                0,
+                0,
+                image::Delay::from_numer_denom_ms(delay_ms as u32, 1),
+            )
+        })
+        .collect();
+
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buffer, 10);
+        let repeat = if loop_count == 0 { Repeat::Infinite } else { Repeat::Finite(loop_count) };
+        encoder.set_repeat(repeat)
+            .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        encoder.encode_frames(frames)
+            .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+    }
+
+    Ok(format!("data:image/gif;base64,{}", general_purpose::STANDARD.encode(&buffer)))
+}
+
+/// 暗角估计时缩小到的最长边（像素）
+const VIGNETTE_SAMPLE_SIZE: u32 = 256;
+
+/// 暗角校正在角落允许的最大增益，避免过度放大暗部噪点
+const VIGNETTE_MAX_GAIN: f32 = 2.0;
+
+/// 视为中心区域的归一化半径上限（相对中心到角落的距离）
+const VIGNETTE_CENTER_RADIUS: f32 = 0.25;
+
+/// 视为角落区域的归一化半径下限
+const VIGNETTE_CORNER_RADIUS: f32 = 0.85;
+
+/// 像素到图像中心的归一化距离的平方，角落为 1
+fn vignette_calc_radius_sq(x: f32, y: f32, width: u32, height: u32) -> f32 {
+    let (half_w, half_h) = (width as f32 / 2.0, height as f32 / 2.0);
+    let (dx, dy) = (x + 0.5 - half_w, y + 0.5 - half_h);
+    (dx * dx + dy * dy) / (half_w * half_w + half_h * half_h)
+}
+
+/// Tauri IPC 命令：自动检测并校正镜头暗角
+///
+/// 比较中心区域与四角区域的平均亮度估计径向衰减，按 1 + k·r² 的增益曲线提亮边缘，
+/// 使角落亮度回到与中心一致；角落增益不超过 2 倍。角落不比中心暗时图像不变
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(String)` — 校正后的 PNG data URL
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_vignette_correction(image_data: String) -> Result<String, String> {
+    let img = image_load_base64(&image_data)?;
+    let sample = img
+        .resize(VIGNETTE_SAMPLE_SIZE, VIGNETTE_SAMPLE_SIZE, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    // 每个区域累计 [亮度和, r² 和, 像素数]
+    let (mut center, mut corner) = ([0.0f64; 3], [0.0f64; 3]);
+    for (x, y, pixel) in sample.enumerate_pixels() {
+        let r_sq = vignette_calc_radius_sq(x as f32, y as f32, sample.width(), sample.height());
+        let region = if r_sq <= VIGNETTE_CENTER_RADIUS * VIGNETTE_CENTER_RADIUS {
+            &mut center
+        } else if r_sq >= VIGNETTE_CORNER_RADIUS * VIGNETTE_CORNER_RADIUS {
+            &mut corner
+        } else {
+            continue;
+        };
+        region[0] += pixel[0] as f64;
+        region[1] += r_sq as f64;
+        region[2] += 1.0;
+    }
+
+    let mut rgba = img.to_rgba8();
+    if center[2] == 0.0 || corner[2] == 0.0 || corner[0] == 0.0 {
+        return image_encode_png_base64(&DynamicImage::ImageRgba8(rgba));
+    }
+
+    // 亮度按 1 / (1 + k·r²) 衰减时，两区域亮度比 = (1 + k·r²角落) / (1 + k·r²中心)，由各自平均 r² 解出 k
+    let ratio = (center[0] / center[2]) / (corner[0] / corner[2]);
+    let (center_r_sq, corner_r_sq) = (center[1] / center[2], corner[1] / corner[2]);
+    if ratio <= 1.0 {
+        return image_encode_png_base64(&DynamicImage::ImageRgba8(rgba));
+    }
+    // 分母不为正说明衰减超出模型能表示的范围，直接取最大增益
+    let denominator = corner_r_sq - ratio * center_r_sq;
+    let max_k = VIGNETTE_MAX_GAIN - 1.0;
+    let k = if denominator > 0.0 { (((ratio - 1.0) / denominator) as f32).min(max_k) } else { max_k };
+
+    let (width, height) = rgba.dimensions();
+    rgba.par_chunks_exact_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let gain = 1.0 + k * vignette_calc_radius_sq(x as f32, y as f32, width, height);
+                for value in &mut pixel[..3] {
+                    *value = (*value as f32 * gain).round().min(255.0) as u8;
+                }
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// 多帧叠加的合成方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackMode {
+    /// 逐像素取平均值
+    Mean,
+    /// 逐像素取中位数，可剔除个别帧中的离群值（如飞过的物体、热噪点）
+    Median,
+}
+
+impl StackMode {
+    /// 解析 "mean" / "median"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "mean" => Ok(Self::Mean),
+            "median" => Ok(Self::Median),
+            other => Err(format!("Invalid stack mode: {} (expected mean or median)", other)),
+        }
+    }
+}
+
+/// 多帧叠加的最大帧数
+const STACK_MAX_FRAMES: usize = 64;
+
+/// 中位数叠加需要所有帧同时驻留内存，限制各帧像素总数（约 400 MB RGBA）
+const STACK_MEDIAN_MAX_PIXELS: u64 = 100_000_000;
+
+/// 检查帧尺寸与第一帧一致
+fn frame_validate_size(frame: &image::RgbaImage, width: u32, height: u32) -> Result<(), String> {
+    if frame.dimensions() != (width, height) {
+        return Err(format!(
+            "Image size mismatch: {}x{} vs {}x{}",
+            width, height, frame.width(), frame.height()
+        ));
+    }
+    Ok(())
+}
+
+/// Tauri IPC 命令：多帧叠加降噪（星空、弱光连拍）
+///
+/// 将已对齐、尺寸相同的多帧逐像素按通道合成，随机噪声随帧数增加而相互抵消。
+/// 平均模式逐帧解码并累加，内存只占一帧加累加缓冲；中位数模式需要所有帧同时驻留内存，
+/// 像素总数受上限约束。帧数为偶数时中位数取中间两个值的平均
+///
+/// # 参数
+/// * `images` — base64 编码的帧图片数组（1 到 64 帧）
+/// * `mode` — "mean" 平均或 "median" 中位数
+///
+/// # 返回值
+/// * `Ok(String)` — 合成后的 PNG data URL
+///
+/// # 异常
+/// * 帧数为 0 或超过上限，或合成方式非法
+/// * 中位数模式下各帧像素总数超过上限
+/// * 各帧尺寸不一致
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_stack_frames(images: Vec<String>, mode: String) -> Result<String, String> {
+    let mode = StackMode::parse(&mode)?;
+    if images.is_empty() {
+        return Err("No frames provided".to_string());
+    }
+    if images.len() > STACK_MAX_FRAMES {
+        return Err(format!("Too many frames: {} (maximum {})", images.len(), STACK_MAX_FRAMES));
+    }
+
+    let first = image_load_base64(&images[0])?.to_rgba8();
+    let (width, height) = first.dimensions();
+    let count = images.len();
+
+    let output = match mode {
+        StackMode::Mean => {
+            let mut sums = vec![0u32; first.as_raw().len()];
+            let frames = std::iter::once(Ok(first))
+                .chain(images[1..].iter().map(|image_data| image_load_base64(image_data).map(|img| img.to_rgba8())));
+            for frame in frames {
+                let frame = frame?;
+                frame_validate_size(&frame, width, height)?;
+                sums.par_iter_mut()
+                    .zip(frame.as_raw().par_iter())
+                    .for_each(|(sum, &value)| *sum += value as u32);
+            }
+            let averaged = sums.par_iter()
+                .map(|&sum| (sum as f32 / count as f32).round() as u8)
+                .collect();
+            image::RgbaImage::from_raw(width, height, averaged)
+                .ok_or_else(|| "Failed to build stacked image".to_string())?
+        }
+        StackMode::Median => {
+            let total_pixels = width as u64 * height as u64 * count as u64;
+            if total_pixels > STACK_MEDIAN_MAX_PIXELS {
+                return Err(format!(
+                    "Too many pixels for median stacking: {} frames of {}x{} (maximum {} pixels in total)",
+                    count, width, height, STACK_MEDIAN_MAX_PIXELS
+                ));
+            }
+
+            let mut frames = vec![first];
+            frames.extend(images[1..].par_iter()
+                .map(|image_data| Ok(image_load_base64(image_data)?.to_rgba8()))
+                .collect::<Result<Vec<_>, String>>()?);
+            for frame in &frames[1..] {
+                frame_validate_size(frame, width, height)?;
+            }
+
+            let row_len = width as usize * 4;
+            let mut output = image::RgbaImage::new(width, height);
+            output.par_chunks_exact_mut(row_len)
+                .enumerate()
+                .for_each(|(y, row)| {
+                    let offset = y * row_len;
+                    let mut values = Vec::with_capacity(count);
+                    for (i, value) in row.iter_mut().enumerate() {
+                        values.clear();
+                        values.extend(frames.iter().map(|frame| frame.as_raw()[offset + i]));
+                        let mid = count / 2;
+                        let (lower, &mut upper, _) = values.select_nth_unstable(mid);
+                        *value = if count % 2 == 1 {
+                            upper
+                        } else {
+                            let below = lower.iter().copied().max().unwrap_or(upper);
+                            (below as u16 + upper as u16).div_ceil(2) as u8
+                        };
+                    }
+                });
+            output
+        }
+    };
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 中位切分（median cut）生成调色板：反复沿范围最大的通道在加权中位处切分颜色盒
+fn palette_calc_median_cut(colors: Vec<([u8; 4], u32)>, max_colors: usize) -> Vec<[u8; 4]> {
+    fn channel_range(colors: &[([u8; 4], u32)]) -> (usize, u8) {
+        (0..4)
+            .map(|c| {
+                let min = colors.iter().map(|(color, _)| color[c]).min().unwrap_or(0);
+                let max = colors.iter().map(|(color, _)| color[c]).max().unwrap_or(0);
+                (c, max - min)
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap_or((0, 0))
+    }
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        let Some((index, channel)) = boxes.iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| (i, channel_range(b)))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(i, (channel, _))| (i, channel))
+        else {
+            break;
+        };
+
+        let mut target = boxes.swap_remove(index);
+        target.sort_unstable_by_key(|(color, _)| color[channel]);
+        let half = target.iter().map(|(_, count)| *count as u64).sum::<u64>() / 2;
+
+        let mut cumulative = 0u64;
+        let mut split = 1;
+        for (i, (_, count)) in target.iter().enumerate() {
+            cumulative += *count as u64;
+            if cumulative >= half {
+                split = (i + 1).clamp(1, target.len() - 1);
+                break;
+            }
+        }
+
+        let upper = target.split_off(split);
+        boxes.push(target);
+        boxes.push(upper);
+    }
+
+    boxes.iter()
+        .map(|b| {
+            let total: u64 = b.iter().map(|(_, count)| *count as u64).sum();
+            let mut sums = [0u64; 4];
+            for (color, count) in b {
+                for c in 0..4 {
+                    sums[c] += color[c] as u64 * *count as u64;
+                }
+            }
+            sums.map(|sum| ((sum as f64 / total.max(1) as f64).round()) as u8)
+        })
+        .collect()
+}
+
+/// 调色板中与 color 距离最近的下标
+fn palette_find_nearest(palette: &[[u8; 4]], color: [u8; 4]) -> u8 {
+    palette.iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| {
+            (0..4).map(|c| {
+                let d = entry[c] as i32 - color[c] as i32;
+                (d * d) as u32
+            }).sum::<u32>()
+        })
+        .map(|(index, _)| index as u8)
+        .unwrap_or(0)
+}
+
+/// Tauri IPC 命令：将图像量化为调色板索引 PNG，适合体积很小的图形分享
+///
+/// 使用中位切分生成至多 max_colors 色的调色板（含透明度，写入 tRNS），
+/// 每个像素映射到最近的调色板颜色
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `max_colors` — 调色板最大颜色数，取值 2..=256
+///
+/// # 返回值
+/// * `Ok(String)` — 索引色 PNG data URL
+///
+/// # 异常
+/// * 颜色数超出范围
+/// * base64 解析失败或图像格式不支持
+/// * PNG 编码失败
+#[tauri::command]
+pub fn image_format_indexed_png(image_data: String, max_colors: u16) -> Result<String, String> {
+    if !(2..=256).contains(&max_colors) {
+        return Err(format!("Max colors must be between 2 and 256, got {}", max_colors));
+    }
+
+    let rgba = image_load_base64(&image_data)?.to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    let mut histogram: std::collections::HashMap<[u8; 4], u32> = std::collections::HashMap::new();
+    for pixel in rgba.pixels() {
+        *histogram.entry(pixel.0).or_insert(0) += 1;
+    }
+    let palette = palette_calc_median_cut(histogram.into_iter().collect(), max_colors as usize);
+
+    let mut lookup: std::collections::HashMap<[u8; 4], u8> = std::collections::HashMap::new();
+    let indices: Vec<u8> = rgba.pixels()
+        .map(|pixel| *lookup.entry(pixel.0).or_insert_with(|| palette_find_nearest(&palette, pixel.0)))
+        .collect();
+
+    let rgb_palette: Vec<u8> = palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+    let alpha_palette: Vec<u8> = palette.iter().map(|c| c[3]).collect();
+
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(rgb_palette);
+        if alpha_palette.iter().any(|&a| a < 255) {
+            encoder.set_trns(alpha_palette);
+        }
+        let mut writer = encoder.write_header()
+            .map_err(|e| format!("Failed to encode indexed PNG: {}", e))?;
+        writer.write_image_data(&indices)
+            .map_err(|e| format!("Failed to encode indexed PNG: {}", e))?;
+    }
+
+    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&buffer)))
+}
+
+/// Tauri IPC 命令：只增强图像中的矩形区域，并以羽化边缘合成回原图
+///
+/// 相当于一次调用完成「裁剪 → 增强 → 合成」，用于局部提亮/压暗
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `x` / `y` / `width` / `height` — 增强区域
+/// * `params` — 增强参数
+/// * `feather` — 羽化宽度（像素），区域边缘向内该距离内逐渐过渡到原图，0 为硬边
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL
+///
+/// # 异常
+/// * 增强参数非法
+/// * 区域为空或超出图像边界
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_region_enhance(
+    image_data: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    params: EnhanceParams,
+    feather: u32,
+) -> Result<String, String> {
+    params.validate()?;
+    let img = image_load_base64(&image_data)?;
+    rect_validate_bounds(&img, x, y, width, height)?;
+
+    let mut output = img.to_rgba8();
+    let mut region = img.crop_imm(x, y, width, height).to_rgba8();
+    pixel_update_enhance_params(&mut region, &params);
+
+    for (rx, ry, enhanced) in region.enumerate_pixels() {
+        let edge_distance = rx.min(ry).min(width - 1 - rx).min(height - 1 - ry);
+        let weight = if feather == 0 {
+            1.0
+        } else {
+            ((edge_distance as f32 + 0.5) / feather as f32).min(1.0)
+        };
+
+        let pixel = output.get_pixel_mut(x + rx, y + ry);
+        for c in 0..4 {
+            let blended = pixel[c] as f32 * (1.0 - weight) + enhanced[c] as f32 * weight;
+            pixel[c] = blended.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 文档角点检测时的最大处理边长，大图先缩小以加快边缘检测
+const DOCUMENT_DETECT_MAX_SIZE: u32 = 512;
+
+/// 边缘强度阈值下限，避免平坦图片中噪声被当作边缘
+const DOCUMENT_EDGE_MIN_MAGNITUDE: f32 = 24.0;
+
+/// 候选四边形面积占整图的最小比例，低于此值视为未检测到文档
+const DOCUMENT_MIN_AREA_RATIO: f32 = 0.15;
+
+/// 多边形面积（鞋带公式，取绝对值）
+fn polygon_calc_area(points: &[crate::PathPoint]) -> f32 {
+    let n = points.len();
+    let twice_area: f32 = (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    twice_area.abs() / 2.0
+}
+
+/// Sobel 梯度幅值超过阈值的像素构成边缘图
+fn edge_calc_map(gray: &image::GrayImage) -> Vec<bool> {
+    let (w, h) = (gray.width() as usize, gray.height() as usize);
+    let raw = gray.as_raw();
+    let mut magnitude = vec![0.0f32; w * h];
+
+    for y in 1..h.saturating_sub(1) {
+        for x in 1..w.saturating_sub(1) {
+            let p = |dx: usize, dy: usize| raw[(y + dy - 1) * w + (x + dx - 1)] as f32;
+            let gx = (p(2, 0) + 2.0 * p(2, 1) + p(2, 2)) - (p(0, 0) + 2.0 * p(0, 1) + p(0, 2));
+            let gy = (p(0, 2) + 2.0 * p(1, 2) + p(2, 2)) - (p(0, 0) + 2.0 * p(1, 0) + p(2, 0));
+            magnitude[y * w + x] = (gx * gx + gy * gy).sqrt();
+        }
+    }
+
+    let count = magnitude.len().max(1) as f32;
+    let mean = magnitude.iter().sum::<f32>() / count;
+    let variance = magnitude.iter().map(|m| (m - mean) * (m - mean)).sum::<f32>() / count;
+    let threshold = (mean + variance.sqrt()).max(DOCUMENT_EDGE_MIN_MAGNITUDE);
+
+    magnitude.iter().map(|&m| m >= threshold).collect()
+}
+
+/// 在边缘图中寻找凸包面积最大的连通轮廓，返回其凸包
+///
+/// 连通判定使用 5x5 邻域，可跨越 1 像素的边缘断口
+fn contour_find_largest_hull(edges: &[bool], width: usize, height: usize) -> Vec<crate::PathPoint> {
+    let mut visited = vec![false; edges.len()];
+    let mut best_hull = Vec::new();
+    let mut best_area = 0.0f32;
+    let mut stack = Vec::new();
+
+    for start in 0..edges.len() {
+        if !edges[start] || visited[start] {
+            continue;
+        }
+
+        let mut component = Vec::new();
+        visited[start] = true;
+        stack.push(start);
+
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % width, index / width);
+            component.push(crate::PathPoint { x: x as f32, y: y as f32 });
+
+            for ny in y.saturating_sub(2)..(y + 3).min(height) {
+                for nx in x.saturating_sub(2)..(x + 3).min(width) {
+                    let neighbor = ny * width + nx;
+                    if edges[neighbor] && !visited[neighbor] {
+                        visited[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        if component.len() < 3 {
+            continue;
+        }
+        let hull = crate::stroke_processing::point_calc_convex_hull(component);
+        let area = polygon_calc_area(&hull);
+        if area > best_area {
+            best_area = area;
+            best_hull = hull;
+        }
+    }
+
+    best_hull
+}
+
+/// 将凸包近似为面积最大的内接四边形
+///
+/// 以坐标和/差的极值点作为初始角点，再逐个角点在凸包顶点中寻找使面积最大的位置
+fn hull_calc_quadrilateral(hull: &[crate::PathPoint]) -> [crate::PathPoint; 4] {
+    let pick = |key: &dyn Fn(&crate::PathPoint) -> f32| {
+        *hull.iter().max_by(|a, b| key(a).total_cmp(&key(b))).unwrap()
+    };
+    let mut quad = [
+        pick(&|p| -(p.x + p.y)),
+        pick(&|p| p.x - p.y),
+        pick(&|p| p.x + p.y),
+        pick(&|p| p.y - p.x),
+    ];
+
+    for _ in 0..3 {
+        let mut changed = false;
+        for k in 0..4 {
+            let mut best = polygon_calc_area(&quad);
+            for &candidate in hull {
+                let mut trial = quad;
+                trial[k] = candidate;
+                let area = polygon_calc_area(&trial);
+                if area > best {
+                    best = area;
+                    quad[k] = candidate;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // 按绕质心的角度排序，并从左上角（x + y 最小）开始，保证顺时针顺序
+    let cx = quad.iter().map(|p| p.x).sum::<f32>() / 4.0;
+    let cy = quad.iter().map(|p| p.y).sum::<f32>() / 4.0;
+    quad.sort_by(|a, b| (a.y - cy).atan2(a.x - cx).total_cmp(&(b.y - cy).atan2(b.x - cx)));
+    let top_left = (0..4)
+        .min_by(|&a, &b| (quad[a].x + quad[a].y).total_cmp(&(quad[b].x + quad[b].y)))
+        .unwrap_or(0);
+    quad.rotate_left(top_left);
+    quad
+}
+
+/// Tauri IPC 命令：检测照片中文档的四个角点，用于自动裁剪和透视校正
+///
+/// 缩小后做 Sobel 边缘检测，取凸包面积最大的边缘轮廓并近似为四边形；
+/// 未找到足够大的轮廓时返回整图四角
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok([[f32; 2]; 4])` — 原图坐标下的角点，依次为左上、右上、右下、左下
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_calc_document_corners(image_data: String) -> Result<[[f32; 2]; 4], String> {
+    let img = image_load_base64(&image_data)?;
+    let (width, height) = (img.width(), img.height());
+    let full_image = [
+        [0.0, 0.0],
+        [width as f32, 0.0],
+        [width as f32, height as f32],
+        [0.0, height as f32],
+    ];
+
+    let scale = (DOCUMENT_DETECT_MAX_SIZE as f32 / width.max(height) as f32).min(1.0);
+    let small_w = ((width as f32 * scale).round() as u32).max(1);
+    let small_h = ((height as f32 * scale).round() as u32).max(1);
+    let gray = img
+        .resize_exact(small_w, small_h, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let blurred = image::imageops::blur(&gray, 1.5);
+
+    let edges = edge_calc_map(&blurred);
+    let hull = contour_find_largest_hull(&edges, small_w as usize, small_h as usize);
+    if hull.len() < 4 || polygon_calc_area(&hull) < (small_w * small_h) as f32 * DOCUMENT_MIN_AREA_RATIO {
+        log::info!("未检测到文档轮廓，返回整图角点");
+        return Ok(full_image);
+    }
+
+    let quad = hull_calc_quadrilateral(&hull);
+    let (scale_x, scale_y) = (width as f32 / small_w as f32, height as f32 / small_h as f32);
+    Ok(quad.map(|p| [
+        (p.x * scale_x).clamp(0.0, width as f32),
+        (p.y * scale_y).clamp(0.0, height as f32),
+    ]))
+}
+
+/// 由四对对应点求解单应矩阵（h33 固定为 1），将 from 映射到 to
+///
+/// 使用带部分主元的高斯消元求解 8 元线性方程组；点共线等退化情况返回 None
+fn homography_calc_from_points(from: &[[f64; 2]; 4], to: &[[f64; 2]; 4]) -> Option<[f64; 8]> {
+    let mut matrix = [[0.0f64; 9]; 8];
+    for i in 0..4 {
+        let ([u, v], [x, y]) = (from[i], to[i]);
+        matrix[i * 2] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x];
+        matrix[i * 2 + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y];
+    }
+
+    for col in 0..8 {
+        let pivot = (col..8).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-10 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+
+        let pivot_row = matrix[col];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row == col {
+                continue;
+            }
+            let factor = values[col] / pivot_row[col];
+            for (value, pivot_value) in values.iter_mut().zip(pivot_row.iter()).skip(col) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    Some(std::array::from_fn(|i| matrix[i][8] / matrix[i][i]))
+}
+
+/// Tauri IPC 命令：将照片中的文档四边形透视校正为矩形，得到平整的扫描效果
+///
+/// 对输出图每个像素用逆单应变换映射回原图，双线性采样；落在原图外的像素为透明。
+/// 角点通常来自 image_calc_document_corners
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `corners` — 原图中文档的四个角点，依次为左上、右上、右下、左下
+/// * `out_width` / `out_height` — 输出图尺寸
+///
+/// # 返回值
+/// * `Ok(String)` — 校正后的 PNG data URL
+///
+/// # 异常
+/// * 输出尺寸为 0 或超过上限
+/// * 角点非有限值或退化（三点共线等）
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_dewarp(image_data: String, corners: [[f32; 2]; 4], out_width: u32, out_height: u32) -> Result<String, String> {
+    if out_width == 0 || out_height == 0 {
+        return Err("Output size must be non-zero".to_string());
+    }
+    if out_width > crate::MAX_CANVAS_DIMENSION || out_height > crate::MAX_CANVAS_DIMENSION {
+        return Err(format!(
+            "Output size {}x{} exceeds the maximum of {}",
+            out_width, out_height, crate::MAX_CANVAS_DIMENSION
+        ));
+    }
+    if corners.iter().flatten().any(|v| !v.is_finite()) {
+        return Err("Document corners contain non-finite values".to_string());
+    }
+
+    let (w, h) = (out_width as f64, out_height as f64);
+    let target = [[0.0, 0.0], [w, 0.0], [w, h], [0.0, h]];
+    let source = corners.map(|[x, y]| [x as f64, y as f64]);
+    let m = homography_calc_from_points(&target, &source)
+        .ok_or_else(|| "Document corners are degenerate".to_string())?;
+
+    let img = image_load_base64(&image_data)?.to_rgba8();
+    let mut output = image::RgbaImage::new(out_width, out_height);
+    output.par_chunks_exact_mut(out_width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let v = y as f64 + 0.5;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let u = x as f64 + 0.5;
+                let denominator = m[6] * u + m[7] * v + 1.0;
+                if denominator.abs() < 1e-12 {
+                    continue;
+                }
+                let sx = (m[0] * u + m[1] * v + m[2]) / denominator - 0.5;
+                let sy = (m[3] * u + m[4] * v + m[5]) / denominator - 0.5;
+                if let Some(sample) = pixel_sample_interpolated(&img, sx as f32, sy as f32, Interpolation::Bilinear) {
+                    pixel.copy_from_slice(&sample);
+                }
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 预览代理图的最大边长，拖动滑块时只处理缩小后的图
+const PROXY_MAX_SIZE: u32 = 1024;
+
+/// 同时缓存的代理图数量上限，超出时淘汰最久未使用的
+const PROXY_MAX_ENTRIES: usize = 8;
+
+/// 代理图表项：最近使用时间和代理图
+type ProxyEntry = (std::time::Instant, Arc<image::RgbaImage>);
+
+/// 已解码的预览代理图，键为代理 ID
+///
+/// 按最近使用时间淘汰：长期复用的代理（如笔画压缩的底图）每次取用都会刷新时间，不会因加载得早而被先淘汰
+static PROXY_REGISTRY: Lazy<Mutex<HashMap<u64, ProxyEntry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static PROXY_NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Tauri IPC 命令：解码图片并缓存缩小后的预览代理图
+///
+/// 拖动增强滑块时配合 image_update_proxy 使用，避免每次都重新解码原图；
+/// 用完后应调用 image_delete_proxy 释放；缓存超过 8 个时淘汰最久未使用的代理
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `max_size` — 代理图最大边长，默认 1024，原图更小时不放大
+///
+/// # 返回值
+/// * `Ok(u64)` — 代理 ID
+///
+/// # 异常
+/// * 最大边长为 0
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_load_proxy(image_data: String, max_size: Option<u32>) -> Result<u64, String> {
+    let max_size = max_size.unwrap_or(PROXY_MAX_SIZE);
+    if max_size == 0 {
+        return Err("Proxy size must be non-zero".to_string());
+    }
+
+    let img = image_load_base64(&image_data)?;
+    let proxy = if img.width().max(img.height()) > max_size {
+        img.resize(max_size, max_size, image::imageops::FilterType::Triangle).to_rgba8()
+    } else {
+        img.to_rgba8()
+    };
+
+    let id = PROXY_NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut registry = PROXY_REGISTRY.lock().map_err(|e| format!("Failed to lock proxy registry: {}", e))?;
+    while registry.len() >= PROXY_MAX_ENTRIES {
+        let Some(oldest) = registry.iter().min_by_key(|(_, (last_used, _))| *last_used).map(|(id, _)| *id) else {
+            break;
+        };
+        registry.remove(&oldest);
+        log::warn!("预览代理图数量超过上限，已淘汰最久未使用的: {}", oldest);
+    }
+    registry.insert(id, (std::time::Instant::now(), Arc::new(proxy)));
+
+    Ok(id)
+}
+
+/// 取出缓存的代理图并刷新其最近使用时间；返回的引用在代理被释放或淘汰后仍然有效
+///
+/// # 异常
+/// * 代理 ID 不存在（未加载、已释放或已被淘汰）
+pub fn image_fetch_proxy(proxy_id: u64) -> Result<Arc<image::RgbaImage>, String> {
+    let mut registry = PROXY_REGISTRY.lock().map_err(|e| format!("Failed to lock proxy registry: {}", e))?;
+    let (last_used, proxy) = registry.get_mut(&proxy_id)
+        .ok_or_else(|| format!("Proxy not found: {}", proxy_id))?;
+    *last_used = std::time::Instant::now();
+    Ok(proxy.clone())
+}
+
+/// Tauri IPC 命令：对缓存的代理图应用增强参数，不重新解码原图
+///
+/// # 参数
+/// * `proxy_id` — image_load_proxy 返回的代理 ID
+/// * `params` — 增强参数
+///
+/// # 返回值
+/// * `Ok(String)` — 增强后的代理图 PNG data URL
+///
+/// # 异常
+/// * 增强参数非法
+/// * 代理 ID 不存在（未加载、已释放或已被淘汰）
+#[tauri::command]
+pub fn image_update_proxy(proxy_id: u64, params: EnhanceParams) -> Result<String, String> {
+    params.validate()?;
+    let proxy = image_fetch_proxy(proxy_id)?;
+
+    let mut rgba = (*proxy).clone();
+    pixel_update_enhance_params(&mut rgba, &params);
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// Tauri IPC 命令：释放缓存的代理图
+///
+/// # 参数
+/// * `proxy_id` — image_load_proxy 返回的代理 ID
+///
+/// # 返回值
+/// * `Ok(bool)` — 代理图存在并已释放时为 true
+#[tauri::command]
+pub fn image_delete_proxy(proxy_id: u64) -> Result<bool, String> {
+    let mut registry = PROXY_REGISTRY.lock().map_err(|e| format!("Failed to lock proxy registry: {}", e))?;
+    Ok(registry.remove(&proxy_id).is_some())
+}
+
+/// 低于此饱和度的像素色相不可靠，调整力度按饱和度线性减弱
+const HUE_RANGE_MIN_SATURATION: f32 = 0.1;
+
+/// Tauri IPC 命令：只调整某一色相范围内像素的饱和度和明度（可选颜色）
+///
+/// 与目标色相的距离在 hue_width 一半以内时完全生效，之后平滑衰减，
+/// 超过 hue_width 的像素不受影响；接近灰色的像素按饱和度减弱调整
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `target_hue` — 目标色相（度，0 为红色）
+/// * `hue_width` — 色相范围半宽（度，0..180）
+/// * `sat_delta` — 饱和度增量（-1..1）
+/// * `light_delta` — 明度增量（-1..1）
+///
+/// # 返回值
+/// * `Ok(String)` — 调整后的 PNG data URL
+///
+/// # 异常
+/// * 参数非有限值或超出范围
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_hue_range(
+    image_data: String,
+    target_hue: f32,
+    hue_width: f32,
+    sat_delta: f32,
+    light_delta: f32,
+) -> Result<String, String> {
+    if !target_hue.is_finite() || !hue_width.is_finite() || hue_width <= 0.0 || hue_width > 180.0 {
+        return Err(format!("Invalid hue range: {} ± {}", target_hue, hue_width));
+    }
+    if !(-1.0..=1.0).contains(&sat_delta) || !(-1.0..=1.0).contains(&light_delta) {
+        return Err(format!("Adjustment deltas must be within -1..1, got {} and {}", sat_delta, light_delta));
+    }
+
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+    let inner = hue_width / 2.0;
+
+    rgba.par_chunks_exact_mut(4).for_each(|pixel| {
+        let (h, s, v) = color_calc_to_hsv([pixel[0], pixel[1], pixel[2]]);
+        if s == 0.0 {
+            return;
+        }
+
+        let distance = {
+            let d = (h - target_hue).rem_euclid(360.0);
+            d.min(360.0 - d)
+        };
+        if distance >= hue_width {
+            return;
+        }
+        let band = if distance <= inner {
+            1.0
+        } else {
+            let t = (hue_width - distance) / (hue_width - inner);
+            t * t * (3.0 - 2.0 * t)
+        };
+        let weight = band * (s / HUE_RANGE_MIN_SATURATION).min(1.0);
+
+        let rgb = color_calc_from_hsv(
+            h,
+            (s + sat_delta * weight).clamp(0.0, 1.0),
+            (v + light_delta * weight).clamp(0.0, 1.0),
+        );
+        pixel[..3].copy_from_slice(&rgb);
+    });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// 3x3 矩阵（行主序）求逆，行列式接近 0 时返回 None
+fn matrix_calc_inverse(m: &[f64; 9]) -> Option<[f64; 9]> {
+    let cofactor = [
+        m[4] * m[8] - m[5] * m[7],
+        m[5] * m[6] - m[3] * m[8],
+        m[3] * m[7] - m[4] * m[6],
+        m[2] * m[7] - m[1] * m[8],
+        m[0] * m[8] - m[2] * m[6],
+        m[1] * m[6] - m[0] * m[7],
+        m[1] * m[5] - m[2] * m[4],
+        m[2] * m[3] - m[0] * m[5],
+        m[0] * m[4] - m[1] * m[3],
+    ];
+    let determinant = m[0] * cofactor[0] + m[1] * cofactor[1] + m[2] * cofactor[2];
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    // 伴随矩阵为余子式矩阵的转置
+    Some([
+        cofactor[0], cofactor[3], cofactor[6],
+        cofactor[1], cofactor[4], cofactor[7],
+        cofactor[2], cofactor[5], cofactor[8],
+    ].map(|v| v / determinant))
+}
+
+/// Tauri IPC 命令：按 3x3 仿射/透视矩阵变换图像
+///
+/// 对输出图每个像素用逆矩阵映射回原图并按指定插值方式采样，落在原图外的像素为透明。
+/// 用于对渲染后的批注图层做旋转、斜切等变换
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `matrix` — 行主序 3x3 矩阵，将原图像素坐标映射到输出图坐标
+/// * `out_width` / `out_height` — 输出图尺寸
+/// * `interpolation` — 插值方式（"nearest"/"bilinear"/"bicubic"），默认双线性
+///
+/// # 返回值
+/// * `Ok(String)` — 变换后的 PNG data URL
+///
+/// # 异常
+/// * 输出尺寸为 0 或超过上限
+/// * 矩阵含非有限值或不可逆
+/// * 插值方式非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_warp(
+    image_data: String,
+    matrix: [f32; 9],
+    out_width: u32,
+    out_height: u32,
+    interpolation: Option<String>,
+) -> Result<String, String> {
+    if out_width == 0 || out_height == 0 {
+        return Err("Output size must be non-zero".to_string());
+    }
+    if out_width > crate::MAX_CANVAS_DIMENSION || out_height > crate::MAX_CANVAS_DIMENSION {
+        return Err(format!(
+            "Output size {}x{} exceeds the maximum of {}",
+            out_width, out_height, crate::MAX_CANVAS_DIMENSION
+        ));
+    }
+    if matrix.iter().any(|v| !v.is_finite()) {
+        return Err("Transform matrix contains non-finite values".to_string());
+    }
+    let mode = Interpolation::parse(interpolation.as_deref())?;
+    let inverse = matrix_calc_inverse(&matrix.map(|v| v as f64))
+        .ok_or_else(|| "Transform matrix is not invertible".to_string())?;
+
+    let img = image_load_base64(&image_data)?.to_rgba8();
+    let mut output = image::RgbaImage::new(out_width, out_height);
+    output.par_chunks_exact_mut(out_width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let v = y as f64 + 0.5;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let u = x as f64 + 0.5;
+                let w = inverse[6] * u + inverse[7] * v + inverse[8];
+                if w.abs() < 1e-12 {
+                    continue;
+                }
+                let sx = (inverse[0] * u + inverse[1] * v + inverse[2]) / w - 0.5;
+                let sy = (inverse[3] * u + inverse[4] * v + inverse[5]) / w - 0.5;
+                if let Some(sample) = pixel_sample_interpolated(&img, sx as f32, sy as f32, mode) {
+                    pixel.copy_from_slice(&sample);
+                }
+            }
+        });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// Tauri IPC 命令：将接近目标颜色的像素替换为另一颜色或抠为透明（绿幕抠像）
+///
+/// 颜色距离为 RGB 欧氏距离，归一化到 0..1（1 为黑白之间的距离）。距离不超过 tolerance
+/// 的像素完全替换，在 tolerance..tolerance + feather 之间平滑过渡，使边缘自然
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `target` — 要替换的颜色，#RRGGBB 格式
+/// * `replacement` — 替换颜色，#RRGGBB 或 #RRGGBBAA 格式；省略时抠为透明
+/// * `tolerance` — 完全替换的颜色距离（0..1）
+/// * `feather` — 边缘过渡宽度（0..1）
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL
+///
+/// # 异常
+/// * 颜色格式非法或容差超出范围
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_replace_color(
+    image_data: String,
+    target: String,
+    replacement: Option<String>,
+    tolerance: f32,
+    feather: f32,
+) -> Result<String, String> {
+    if !(0.0..=1.0).contains(&tolerance) || !(0.0..=1.0).contains(&feather) {
+        return Err(format!("Tolerance and feather must be within 0..1, got {} and {}", tolerance, feather));
+    }
+    let target = crate::color_calc_from_hex(&target)?;
+    let replacement = replacement.as_deref().map(crate::color_calc_from_hex).transpose()?;
+
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+    let max_distance = (3.0f32 * 255.0 * 255.0).sqrt();
+
+    rgba.par_chunks_exact_mut(4).for_each(|pixel| {
+        let distance = (0..3)
+            .map(|c| {
+                let d = pixel[c] as f32 - target[c] as f32;
+                d * d
+            })
+            .sum::<f32>()
+            .sqrt() / max_distance;
+
+        let weight = if distance <= tolerance {
+            1.0
+        } else if feather > 0.0 && distance < tolerance + feather {
+            let t = 1.0 - (distance - tolerance) / feather;
+            t * t * (3.0 - 2.0 * t)
+        } else {
+            return;
+        };
+
+        match replacement {
+            Some(color) => {
+                for c in 0..4 {
+                    let blended = pixel[c] as f32 * (1.0 - weight) + color[c] as f32 * weight;
+                    pixel[c] = blended.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            None => pixel[3] = (pixel[3] as f32 * (1.0 - weight)).round() as u8,
+        }
+    });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// 加载占位图的最大边长
+const PLACEHOLDER_MAX_SIZE: u32 = 16;
+
+/// Tauri IPC 命令：生成极小的模糊占位图，前端在原图加载完成前先行显示
+///
+/// 缩小到不超过 16px 后再模糊，编码为 PNG，通常只有几百字节；
+/// 像素全部不透明时去掉 alpha 通道以进一步减小体积（画布导出的 RGBA 图片也是如此）
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(String)` — 占位图 PNG data URL，前端按原图尺寸拉伸显示
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_placeholder(image_data: String) -> Result<String, String> {
+    let img = image_load_base64(&image_data)?;
+    let small = img.resize(PLACEHOLDER_MAX_SIZE, PLACEHOLDER_MAX_SIZE, image::imageops::FilterType::Triangle);
+    let blurred = image::imageops::blur(&small.to_rgba8(), 1.0);
+
+    let placeholder = if blurred.pixels().all(|p| p[3] == 255) {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(blurred).to_rgb8())
+    } else {
+        DynamicImage::ImageRgba8(blurred)
+    };
+    image_encode_png_base64(&placeholder)
+}
+
+/// sRGB 通道值（0..255）转线性光强（0..1）
+pub fn channel_calc_to_linear(value: u8) -> f32 {
+    let v = value as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// 线性光强（0..1）转 sRGB 通道值（0..255），超出范围时夹取
+pub fn channel_calc_from_linear(value: f32) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let srgb = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// BlurHash 使用的 base83 字符表
+const BLURHASH_BASE83: &[u8; 83] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// 计算 BlurHash 前先缩小到的最大边长，分量数最多 9，更高分辨率对结果几乎无影响
+const BLURHASH_SAMPLE_SIZE: u32 = 64;
+
+/// 将数值编码为定长 base83 字符串并追加到 hash
+fn blurhash_push_base83(hash: &mut String, value: u32, length: u32) {
+    for i in 1..=length {
+        let digit = (value / 83u32.pow(length - i)) % 83;
+        hash.push(BLURHASH_BASE83[digit as usize] as char);
+    }
+}
+
+/// Tauri IPC 命令：计算图片的 BlurHash 字符串，前端可直接解码为模糊占位图
+///
+/// 与 image_format_placeholder 相比结果更紧凑（通常 20~30 个字符），便于随列表数据一起返回
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `x_components` / `y_components` — 水平/垂直方向的余弦分量数（1..9）
+///
+/// # 返回值
+/// * `Ok(String)` — BlurHash 字符串，长度为 6 + 2 × (x × y − 1)
+///
+/// # 异常
+/// * 分量数超出 1..9
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_blurhash(image_data: String, x_components: u32, y_components: u32) -> Result<String, String> {
+    if !(1..=9).contains(&x_components) || !(1..=9).contains(&y_components) {
+        return Err(format!(
+            "BlurHash components must be within 1..9, got {}x{}",
+            x_components, y_components
+        ));
+    }
+
+    let img = image_load_base64(&image_data)?
+        .resize(BLURHASH_SAMPLE_SIZE, BLURHASH_SAMPLE_SIZE, image::imageops::FilterType::Triangle)
+        .to_rgb8();
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let linear: Vec<[f32; 3]> = img.pixels()
+        .map(|p| [channel_calc_to_linear(p[0]), channel_calc_to_linear(p[1]), channel_calc_to_linear(p[2])])
+        .collect();
+
+    let factors: Vec<[f32; 3]> = (0..y_components)
+        .flat_map(|j| (0..x_components).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut sum = [0.0f32; 3];
+            for y in 0..height {
+                let basis_y = (std::f32::consts::PI * j as f32 * y as f32 / height as f32).cos();
+                for x in 0..width {
+                    let basis = basis_y * (std::f32::consts::PI * i as f32 * x as f32 / width as f32).cos();
+                    let pixel = linear[y * width + x];
+                    for c in 0..3 {
+                        sum[c] += basis * pixel[c];
+                    }
+                }
+            }
+            sum.map(|v| v * normalisation / (width * height) as f32)
+        })
+        .collect();
+
+    let mut hash = String::new();
+    blurhash_push_base83(&mut hash, (x_components - 1) + (y_components - 1) * 9, 1);
+
+    let (dc, ac) = (factors[0], &factors[1..]);
+    let max_value = if ac.is_empty() {
+        blurhash_push_base83(&mut hash, 0, 1);
+        1.0
+    } else {
+        let actual_max = ac.iter().flatten().fold(0.0f32, |max, v| max.max(v.abs()));
+        let quantised = (actual_max * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+        blurhash_push_base83(&mut hash, quantised, 1);
+        (quantised + 1) as f32 / 166.0
+    };
+
+    let [r, g, b] = dc.map(|v| channel_calc_from_linear(v) as u32);
+    blurhash_push_base83(&mut hash, (r << 16) + (g << 8) + b, 4);
+
+    for factor in ac {
+        let [qr, qg, qb] = factor.map(|v| {
+            let normalized = v / max_value;
+            let signed_sqrt = normalized.signum() * normalized.abs().sqrt();
+            (signed_sqrt * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32
+        });
+        blurhash_push_base83(&mut hash, qr * 19 * 19 + qg * 19 + qb, 2);
+    }
+
+    Ok(hash)
+}
+
+/// 计算显著性图前先缩小到的最大边长
+const SALIENCY_SAMPLE_SIZE: u32 = 256;
+
+/// 显著窗口的能量需比居中窗口高出此比例才偏离中心，否则视为显著性平坦、回退居中裁剪
+const SMART_THUMBNAIL_MIN_GAIN: f64 = 0.05;
+
+/// 在一个方向上选择裁剪窗口起点：显著性优势不足时返回居中位置
+fn saliency_calc_window(line_energy: &[u64], window: usize) -> usize {
+    let center = (line_energy.len() - window) / 2;
+    let best = energy_calc_best_window(line_energy, window);
+
+    let window_energy = |start: usize| line_energy[start..start + window].iter().sum::<u64>() as f64;
+    if window_energy(best) > window_energy(center) * (1.0 + SMART_THUMBNAIL_MIN_GAIN) {
+        best
+    } else {
+        center
+    }
+}
+
+/// Tauri IPC 命令：生成避开主体被裁切的正方形缩略图
+///
+/// 显著性 = 梯度能量 + 与全图平均色的差异，再乘以中心权重（画面中央的主体优先）。
+/// 在缩小的分析图上选取显著性最高的正方形窗口，映射回原图后裁剪缩放；
+/// 显著性平坦时回退为居中裁剪
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `size` — 缩略图边长
+///
+/// # 返回值
+/// * `Ok(String)` — size × size 的 PNG data URL
+///
+/// # 异常
+/// * 边长为 0
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_smart_thumbnail(image_data: String, size: u32) -> Result<String, String> {
+    if size == 0 {
+        return Err("Thumbnail size must be non-zero".to_string());
+    }
+
+    let img = image_load_base64(&image_data)?;
+    let (width, height) = (img.width(), img.height());
+    let crop = width.min(height);
+
+    let sample = img.resize(SALIENCY_SAMPLE_SIZE, SALIENCY_SAMPLE_SIZE, image::imageops::FilterType::Triangle).to_rgb8();
+    let (sw, sh) = (sample.width(), sample.height());
+    let pixel_count = (sw * sh) as f32;
+    let mut mean = [0.0f32; 3];
+    for pixel in sample.pixels() {
+        for c in 0..3 {
+            mean[c] += pixel[c] as f32 / pixel_count;
+        }
+    }
+
+    let mut column_energy = vec![0u64; sw as usize];
+    let mut row_energy = vec![0u64; sh as usize];
+    for y in 0..sh {
+        for x in 0..sw {
+            let pixel = sample.get_pixel(x, y);
+            let right = sample.get_pixel((x + 1).min(sw - 1), y);
+            let below = sample.get_pixel(x, (y + 1).min(sh - 1));
+            let (mut gradient, mut contrast) = (0.0f32, 0.0f32);
+            for c in 0..3 {
+                gradient += (right[c] as f32 - pixel[c] as f32).abs() + (below[c] as f32 - pixel[c] as f32).abs();
+                contrast += (pixel[c] as f32 - mean[c]).abs();
+            }
+
+            let dx = (x as f32 + 0.5) / sw as f32 - 0.5;
+            let dy = (y as f32 + 0.5) / sh as f32 - 0.5;
+            let center_weight = 1.0 - (dx * dx + dy * dy);
+            let saliency = ((gradient + contrast) * center_weight) as u64;
+            column_energy[x as usize] += saliency;
+            row_energy[y as usize] += saliency;
+        }
+    }
+
+    // 分析图与原图的比例一致，窗口尺寸按比例换算
+    let scale = sw as f64 / width as f64;
+    let window_w = ((crop as f64 * scale).round() as usize).clamp(1, sw as usize);
+    let window_h = ((crop as f64 * scale).round() as usize).clamp(1, sh as usize);
+    let start_x = saliency_calc_window(&column_energy, window_w) as f64 / scale;
+    let start_y = saliency_calc_window(&row_energy, window_h) as f64 / scale;
+    let crop_x = (start_x.round() as u32).min(width - crop);
+    let crop_y = (start_y.round() as u32).min(height - crop);
+
+    let thumbnail = img.crop_imm(crop_x, crop_y, crop, crop)
+        .resize_exact(size, size, image::imageops::FilterType::Lanczos3);
+
+    image_encode_png_base64(&thumbnail)
+}
+
+/// 图片格式与色彩配置信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    /// 实际编码格式（按文件头识别），如 "png"、"jpeg"
+    pub format: String,
+    /// 解码后的颜色类型，如 "Rgb8"、"Rgba16"
+    pub color_type: String,
+    /// 嵌入 ICC 配置文件的描述名称；未嵌入时为 "none"，无法读取名称时为 "unknown"
+    pub profile: String,
+    /// ICC 配置文件字节数，未嵌入时为 0
+    pub profile_size: usize,
+    /// ICC 头中声明的色彩空间，如 "RGB"、"GRAY"、"CMYK"
+    pub color_space: Option<String>,
+}
+
+/// 读取 ICC 配置文件 desc 标签中的描述名称，支持 v2 textDescriptionType 与 v4 mluc
+fn icc_fetch_description(icc: &[u8]) -> Option<String> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        icc.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    // 标签数量来自文件内容，按实际能容纳的标签表条目数截断，避免伪造的巨大计数导致长时间循环
+    let tag_count = (read_u32(128)? as usize).min(icc.len().saturating_sub(132) / 12);
+    let (offset, size) = (0..tag_count).find_map(|i| {
+        let entry = 132 + i * 12;
+        (icc.get(entry..entry + 4)? == b"desc").then_some(())?;
+        Some((read_u32(entry + 4)? as usize, read_u32(entry + 8)? as usize))
+    })?;
+    let tag = icc.get(offset..offset.checked_add(size)?)?;
+
+    let text = match tag.get(0..4)? {
+        b"desc" => {
+            let length = u32::from_be_bytes(tag.get(8..12)?.try_into().ok()?) as usize;
+            let ascii = tag.get(12..12 + length)?;
+            String::from_utf8_lossy(ascii).trim_end_matches('\0').to_string()
+        }
+        b"mluc" => {
+            // 取第一条本地化记录（UTF-16BE）
+            let length = u32::from_be_bytes(tag.get(20..24)?.try_into().ok()?) as usize;
+            let start = u32::from_be_bytes(tag.get(24..28)?.try_into().ok()?) as usize;
+            let units: Vec<u16> = tag.get(start..start + length)?
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units).trim_end_matches('\0').to_string()
+        }
+        _ => return None,
+    };
+
+    (!text.trim().is_empty()).then(|| text.trim().to_string())
+}
+
+/// 按文件头识别图片格式，读取颜色类型和嵌入的 ICC 配置文件（不解码像素）
+fn image_fetch_icc_profile(bytes: &[u8]) -> Result<(image::ImageFormat, image::ColorType, Option<Vec<u8>>), String> {
+    use image::ImageDecoder;
+
+    let reader = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image header: {}", e))?;
+    let format = reader.format()
+        .ok_or_else(|| "Unrecognized image format".to_string())?;
+
+    let mut decoder = reader.into_decoder()
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+    let color_type = decoder.color_type();
+    let icc = decoder.icc_profile()
+        .map_err(|e| format!("Failed to read color profile: {}", e))?
+        .filter(|icc| !icc.is_empty());
+
+    Ok((format, color_type, icc))
+}
+
+/// Tauri IPC 命令：识别图片实际格式、颜色类型及嵌入的 ICC 色彩配置
+///
+/// 便于界面对广色域（如 Display P3、Adobe RGB）图片给出显示偏差提示
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(ProfileInfo)` — 格式、颜色类型和配置文件信息；未嵌入配置文件时 profile 为 "none"
+///
+/// # 异常
+/// * base64 解析失败
+/// * 图像格式无法识别或文件头损坏
+#[tauri::command]
+pub fn image_fetch_color_profile(image_data: String) -> Result<ProfileInfo, String> {
+    let bytes = image_fetch_base64_data(&image_data)?;
+    let (format, color_type, icc) = image_fetch_icc_profile(&bytes)?;
+    let color_type = format!("{:?}", color_type);
+
+    let (profile, profile_size, color_space) = match icc {
+        Some(icc) => (
+            icc_fetch_description(&icc).unwrap_or_else(|| "unknown".to_string()),
+            icc.len(),
+            icc.get(16..20).map(|space| String::from_utf8_lossy(space).trim().to_string()),
+        ),
+        None => ("none".to_string(), 0, None),
+    };
+
+    Ok(ProfileInfo {
+        format: format.extensions_str().first().copied().unwrap_or("unknown").to_string(),
+        color_type,
+        profile,
+        profile_size,
+        color_space,
+    })
+}
+
+/// Tauri IPC 命令：将带有非 sRGB ICC 配置文件的图片转换到 sRGB，保证各处显示颜色一致
+///
+/// 未嵌入配置文件、配置文件已是 sRGB 或不是 RGB 配置文件（灰度、CMYK 等）时原样返回输入；
+/// 转换后的图片不再嵌入配置文件
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(String)` — 转换后的 PNG data URL，无需转换时为原始输入
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * ICC 配置文件损坏或无法建立色彩转换
+#[tauri::command]
+pub fn image_format_srgb(image_data: String) -> Result<String, String> {
+    let bytes = image_fetch_base64_data(&image_data)?;
+    let (_, _, icc) = image_fetch_icc_profile(&bytes)?;
+    let Some(icc) = icc else {
+        return Ok(image_data);
+    };
+
+    if icc.get(16..20) != Some(b"RGB ".as_slice()) {
+        log::info!("ICC 配置文件不是 RGB 色彩空间，跳过 sRGB 转换");
+        return Ok(image_data);
+    }
+    if icc_fetch_description(&icc).is_some_and(|name| name.to_ascii_lowercase().starts_with("srgb")) {
+        return Ok(image_data);
+    }
+
+    let source = moxcms::ColorProfile::new_from_slice(&icc)
+        .map_err(|e| format!("Failed to parse color profile: {:?}", e))?;
+    let transform = source
+        .create_transform_8bit(
+            moxcms::Layout::Rgba,
+            &moxcms::ColorProfile::new_srgb(),
+            moxcms::Layout::Rgba,
+            moxcms::TransformOptions::default(),
+        )
+        .map_err(|e| format!("Failed to create color transform: {:?}", e))?;
+
+    let input = image_load_base64(&image_data)?.to_rgba8();
+    let mut output = image::RgbaImage::new(input.width(), input.height());
+    transform
+        .transform(input.as_raw(), &mut output)
+        .map_err(|e| format!("Failed to convert colors: {:?}", e))?;
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(output))
+}
+
+/// 从 EXIF（TIFF 结构）中读取拍摄时间：优先 DateTimeOriginal，其次 IFD0 的 DateTime
+fn exif_fetch_capture_time(exif: &[u8]) -> Option<chrono::NaiveDateTime> {
+    let tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(exif);
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+
+    // 在 IFD 中查找标签，返回 (类型, 数量, 值或偏移所在位置)
+    let find_tag = |ifd: usize, tag: u16| -> Option<(u16, u32, usize)> {
+        let count = read_u16(ifd)? as usize;
+        (0..count).find_map(|i| {
+            let entry = ifd + 2 + i * 12;
+            (read_u16(entry)? == tag).then_some(())?;
+            Some((read_u16(entry + 2)?, read_u32(entry + 4)?, entry + 8))
+        })
+    };
+    let read_datetime = |ifd: usize, tag: u16| -> Option<chrono::NaiveDateTime> {
+        let (kind, count, value_at) = find_tag(ifd, tag)?;
+        // 类型 2 为 ASCII，日期时间固定 20 字节（含结尾 0），必然存放在偏移处
+        if kind != 2 || count < 19 {
+            return None;
+        }
+        let offset = read_u32(value_at)? as usize;
+        let text = std::str::from_utf8(tiff.get(offset..offset + 19)?).ok()?;
+        chrono::NaiveDateTime::parse_from_str(text, "%Y:%m:%d %H:%M:%S").ok()
+    };
+
+    let ifd0 = read_u32(4)? as usize;
+    let exif_ifd = find_tag(ifd0, 0x8769)
+        .and_then(|(_, _, value_at)| read_u32(value_at))
+        .map(|offset| offset as usize);
+
+    exif_ifd
+        .and_then(|ifd| read_datetime(ifd, 0x9003))
+        .or_else(|| read_datetime(ifd0, 0x0132))
+}
+
+/// 读取图片文件 EXIF 中的拍摄时间（只解析文件头，不解码像素），没有 EXIF 或解析失败时返回 None
+pub fn image_fetch_capture_time(path: &std::path::Path) -> Option<chrono::NaiveDateTime> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::open(path).ok()?
+        .with_guessed_format().ok()?
+        .into_decoder().ok()?;
+    let exif = decoder.exif_metadata().ok()??;
+    exif_fetch_capture_time(&exif)
+}
+
+/// 一次生成缩略图的最大尺寸数量
+const THUMBNAIL_MAX_SIZES: usize = 8;
+
+/// Tauri IPC 命令：解码一次图片，生成多个尺寸的缩略图（如网格小图和悬停预览）
+///
+/// 按尺寸从大到小依次缩放，较小的缩略图由上一张较大的缩略图缩小得到；
+/// 原图小于目标尺寸时不放大
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `sizes` — 缩略图最长边尺寸列表
+/// * `fixed_ratio` — true 时保持原图比例，false 时先居中裁剪为正方形
+///
+/// # 返回值
+/// * `Ok(Vec<String>)` — 与 sizes 顺序一一对应的 PNG data URL
+///
+/// # 异常
+/// * 尺寸列表为空、超过 8 个或含 0
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_thumbnails(image_data: String, sizes: Vec<u32>, fixed_ratio: bool) -> Result<Vec<String>, String> {
+    if sizes.is_empty() || sizes.len() > THUMBNAIL_MAX_SIZES {
+        return Err(format!("Thumbnail sizes must contain 1 to {} entries", THUMBNAIL_MAX_SIZES));
+    }
+    if sizes.contains(&0) {
+        return Err("Thumbnail size must be greater than zero".to_string());
+    }
+
+    let img = image_load_base64(&image_data)?;
+    let mut current = if fixed_ratio {
+        img
+    } else {
+        let side = img.width().min(img.height());
+        img.crop_imm((img.width() - side) / 2, (img.height() - side) / 2, side, side)
+    };
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+
+    let mut thumbnails = vec![String::new(); sizes.len()];
+    for i in order {
+        let size = sizes[i];
+        if current.width().max(current.height()) > size {
+            current = current.resize(size, size, image::imageops::FilterType::Lanczos3);
+        }
+        thumbnails[i] = image_encode_png_base64(&current)?;
+    }
+
+    Ok(thumbnails)
+}
+
+/// 顺时针旋转 90 度后的 EXIF Orientation 取值，下标为原取值（1..=8）
+const ORIENTATION_ROTATE_CW: [u16; 9] = [0, 6, 7, 8, 5, 2, 3, 4, 1];
+
+/// 逆时针旋转 90 度后的 EXIF Orientation 取值，下标为原取值（1..=8）
+const ORIENTATION_ROTATE_CCW: [u16; 9] = [0, 8, 5, 6, 7, 4, 1, 2, 3];
+
+/// 在 EXIF（TIFF 结构）的 IFD0 中查找 Orientation 标签，返回取值所在偏移和是否小端序
+fn exif_fetch_orientation_at(tiff: &[u8]) -> Option<(usize, bool)> {
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let bytes: [u8; 4] = tiff.get(4..8)?.try_into().ok()?;
+    let ifd0 = if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) } as usize;
+
+    let count = read_u16(ifd0)? as usize;
+    (0..count).find_map(|i| {
+        let entry = ifd0.checked_add(2 + i * 12)?;
+        // Orientation 必须是 SHORT 类型
+        (read_u16(entry)? == 0x0112 && read_u16(entry + 2)? == 3).then_some((entry + 8, little_endian))
+    })
+}
+
+/// 通过改写 EXIF Orientation 标签无损旋转 JPEG，压缩数据原样保留，不产生二次压缩损失
+///
+/// 没有 EXIF 时在 APP0 段之后插入只含 Orientation 的 APP1 段；
+/// EXIF 中没有 Orientation 标签或文件结构无法解析时返回 None，由调用方改为解码后重新编码
+pub fn jpeg_update_orientation(jpeg: &[u8], clockwise: bool) -> Option<Vec<u8>> {
+    let table = if clockwise { &ORIENTATION_ROTATE_CW } else { &ORIENTATION_ROTATE_CCW };
+    if jpeg.get(0..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+
+    let mut pos = 2;
+    let mut insert_at = 2;
+    while *jpeg.get(pos)? == 0xFF {
+        let marker = *jpeg.get(pos + 1)?;
+        // 图像数据（SOS）之前没有 EXIF
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([*jpeg.get(pos + 2)?, *jpeg.get(pos + 3)?]) as usize;
+        let data = jpeg.get(pos + 4..pos + 2 + length)?;
+
+        if marker == 0xE1 && data.starts_with(b"Exif\0\0") {
+            let tiff_start = pos + 4 + 6;
+            let (value_at, little_endian) = exif_fetch_orientation_at(&data[6..])?;
+            let at = tiff_start + value_at;
+            let bytes = [*jpeg.get(at)?, *jpeg.get(at + 1)?];
+            let current = if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) };
+            let rotated = table[if (1..=8).contains(&current) { current as usize } else { 1 }];
+
+            let mut output = jpeg.to_vec();
+            let bytes = if little_endian { rotated.to_le_bytes() } else { rotated.to_be_bytes() };
+            output[at..at + 2].copy_from_slice(&bytes);
+            return Some(output);
+        }
+        if marker == 0xE0 && insert_at == pos {
+            insert_at = pos + 2 + length;
+        }
+        pos += 2 + length;
+    }
+
+    // 大端 TIFF：IFD0 只有一个 Orientation 条目
+    let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+    tiff.extend_from_slice(&table[1].to_be_bytes());
+    tiff.extend_from_slice(&[0; 6]);
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    segment.extend_from_slice(b"Exif\0\0");
+    segment.extend_from_slice(&tiff);
+
+    let mut output = Vec::with_capacity(jpeg.len() + segment.len());
+    output.extend_from_slice(&jpeg[..insert_at]);
+    output.extend_from_slice(&segment);
+    output.extend_from_slice(&jpeg[insert_at..]);
+    Some(output)
+}
+
+/// 缩小导出时 JPEG 的编码质量
+const DOWNSCALE_JPEG_QUALITY: u8 = 90;
+
+/// 整理 EXIF（TIFF 结构）以写入缩小后的副本：断开 IFD1 去掉缩略图，
+/// 并把 Exif IFD 中的 PixelXDimension / PixelYDimension 更新为新尺寸。
+/// 返回带 "Exif\0\0" 前缀的 APP1 内容，结构无法解析时返回 None
+fn exif_format_for_copy(exif: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+    let mut tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(exif).to_vec();
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read_u16 = |tiff: &[u8], offset: usize| -> Option<u16> {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let read_u32 = |tiff: &[u8], offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+    let find_tag = |tiff: &[u8], ifd: usize, tag: u16| -> Option<(u16, usize)> {
+        let count = read_u16(tiff, ifd)? as usize;
+        (0..count).find_map(|i| {
+            let entry = ifd + 2 + i * 12;
+            (read_u16(tiff, entry)? == tag).then_some(())?;
+            Some((read_u16(tiff, entry + 2)?, entry + 8))
+        })
+    };
+
+    let ifd0 = read_u32(&tiff, 4)? as usize;
+    let next_ifd_at = ifd0 + 2 + read_u16(&tiff, ifd0)? as usize * 12;
+    let ifd1 = read_u32(&tiff, next_ifd_at)? as usize;
+
+    // 缩略图一般位于 EXIF 末尾，此时可直接截掉；否则只断开链接
+    let thumbnail = (ifd1 != 0)
+        .then(|| {
+            let (_, offset_at) = find_tag(&tiff, ifd1, 0x0201)?;
+            let (_, length_at) = find_tag(&tiff, ifd1, 0x0202)?;
+            Some((read_u32(&tiff, offset_at)? as usize, read_u32(&tiff, length_at)? as usize))
+        })
+        .flatten();
+
+    let mut writes: Vec<(usize, Vec<u8>)> = vec![(next_ifd_at, vec![0; 4])];
+    if let Some(exif_ifd) = find_tag(&tiff, ifd0, 0x8769).and_then(|(_, at)| read_u32(&tiff, at)) {
+        for (tag, value) in [(0xA002u16, width), (0xA003u16, height)] {
+            let Some((kind, value_at)) = find_tag(&tiff, exif_ifd as usize, tag) else {
+                continue;
+            };
+            let bytes = match (kind, little_endian) {
+                (3, true) => (value.min(u16::MAX as u32) as u16).to_le_bytes().to_vec(),
+                (3, false) => (value.min(u16::MAX as u32) as u16).to_be_bytes().to_vec(),
+                (4, true) => value.to_le_bytes().to_vec(),
+                (4, false) => value.to_be_bytes().to_vec(),
+                _ => continue,
+            };
+            writes.push((value_at, bytes));
+        }
+    }
+    for (at, bytes) in writes {
+        tiff.get_mut(at..at + bytes.len())?.copy_from_slice(&bytes);
+    }
+
+    if let Some((offset, length)) = thumbnail {
+        if offset > next_ifd_at && offset.saturating_add(length) >= tiff.len() {
+            tiff.truncate(offset);
+        }
+    }
+
+    let mut app1 = b"Exif\0\0".to_vec();
+    app1.extend_from_slice(&tiff);
+    Some(app1)
+}
+
+/// Tauri IPC 命令：将图片缩小到最长边不超过指定尺寸并保留 EXIF，用于分享
+///
+/// 按原比例缩小（不会放大），以 JPEG 输出并写回原图 EXIF：去掉内嵌缩略图，
+/// 像素尺寸标签更新为新尺寸；像素不做旋转，方向标签保持有效
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `max_dimension` — 输出最长边（像素）
+///
+/// # 返回值
+/// * `Ok(String)` — JPEG data URL；原图没有 EXIF 时输出不含 EXIF
+///
+/// # 异常
+/// * 最长边为 0
+/// * base64 解析失败或图像格式不支持
+/// * EXIF 超过单个 JPEG APP 段大小上限
+#[tauri::command]
+pub fn image_format_downscale_exif(image_data: String, max_dimension: u32) -> Result<String, String> {
+    use image::ImageDecoder;
+
+    if max_dimension == 0 {
+        return Err("Max dimension must be greater than zero".to_string());
+    }
+
+    let bytes = image_fetch_base64_data(&image_data)?;
+    if bytes.len() > MAX_IMAGE_SIZE {
+        return Err("Image data too large (max 50MB)".to_string());
+    }
+    let mut decoder = image::ImageReader::new(std::io::Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?
+        .into_decoder()
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+    let exif = decoder.exif_metadata().ok().flatten();
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+
+    if img.width().max(img.height()) > max_dimension {
+        img = img.resize(max_dimension, max_dimension, image::imageops::FilterType::Lanczos3);
+    }
+
+    let exif = exif.and_then(|exif| exif_format_for_copy(&exif, img.width(), img.height()));
+    let encoded = image_encode_jpeg_with_exif(&img, DOWNSCALE_JPEG_QUALITY, ChromaSubsampling::default(), exif.as_deref())?;
+    Ok(format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(&encoded)))
+}
+
+/// 亮度分析前缩小到的最长边
+const BRIGHTNESS_SAMPLE_SIZE: u32 = 64;
+
+/// 平均亮度低于该值（0..255）时视为深色图片
+const BRIGHTNESS_DARK_THRESHOLD: f32 = 128.0;
+
+/// 图片整体亮度分析结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrightnessInfo {
+    /// 平均亮度（BT.601 luma，0..255）
+    pub mean_luma: f32,
+    pub is_dark: bool,
+    /// 在图片上显示文字的推荐颜色，"#000000" 或 "#FFFFFF"
+    pub suggested_text_color: String,
+}
+
+/// Tauri IPC 命令：判断图片整体偏暗还是偏亮，用于界面配色随显示内容切换
+///
+/// 先缩小到最长边 64 像素再统计；透明部分按叠加到白色背景计算
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+///
+/// # 返回值
+/// * `Ok(BrightnessInfo)` — 平均亮度、是否偏暗及推荐文字颜色
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_calc_brightness(image_data: String) -> Result<BrightnessInfo, String> {
+    let img = image_load_base64(&image_data)?;
+    let sample = img
+        .resize(BRIGHTNESS_SAMPLE_SIZE, BRIGHTNESS_SAMPLE_SIZE, image::imageops::FilterType::Triangle)
+        .to_rgba8();
+
+    let total: f64 = sample.pixels()
+        .map(|p| {
+            let luma = p[0] as f64 * 0.299 + p[1] as f64 * 0.587 + p[2] as f64 * 0.114;
+            let alpha = p[3] as f64 / 255.0;
+            luma * alpha + 255.0 * (1.0 - alpha)
+        })
+        .sum();
+    let mean_luma = (total / (sample.width() * sample.height()) as f64) as f32;
+    let is_dark = mean_luma < BRIGHTNESS_DARK_THRESHOLD;
+
+    Ok(BrightnessInfo {
+        mean_luma,
+        is_dark,
+        suggested_text_color: if is_dark { "#FFFFFF" } else { "#000000" }.to_string(),
+    })
+}
+
+/// Tauri IPC 命令：计算墨迹覆盖率，用于多页扫描时自动跳过空白页
+///
+/// 统计与背景色相差超过 tolerance 的像素占比，任一 RGB 通道偏差超过即视为墨迹；
+/// 透明部分按叠加到背景色计算，完全透明的像素视为背景
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `background` — #RRGGBB 格式的背景色（纸张颜色），alpha 被忽略
+/// * `tolerance` — 各通道允许的颜色偏差（0..255），扫描件建议 32 左右以忽略纸张纹理和噪点
+///
+/// # 返回值
+/// * `Ok(f32)` — 墨迹像素占比（0 到 1）
+///
+/// # 异常
+/// * 颜色格式非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_calc_ink_coverage(image_data: String, background: String, tolerance: u8) -> Result<f32, String> {
+    let background = crate::color_calc_from_hex(&background)?;
+    let rgba = image_load_base64(&image_data)?.to_rgba8();
+
+    let ink_pixels: usize = rgba.par_chunks_exact(4)
+        .filter(|pixel| {
+            let alpha = pixel[3] as f32 / 255.0;
+            (0..3).any(|channel| {
+                let value = pixel[channel] as f32 * alpha + background[channel] as f32 * (1.0 - alpha);
+                (value.round() as u8).abs_diff(background[channel]) > tolerance
+            })
+        })
+        .count();
+
+    Ok(ink_pixels as f32 / (rgba.width() as u64 * rgba.height() as u64) as f32)
+}
+
+/// CIELAB 的 D65 白点（XYZ）
+const LAB_WHITE_D65: [f32; 3] = [0.95047, 1.0, 1.08883];
+
+/// sRGB 转 CIELAB（D65）
+fn color_calc_to_lab(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(channel_calc_to_linear);
+    let xyz = [
+        0.4124 * r + 0.3576 * g + 0.1805 * b,
+        0.2126 * r + 0.7152 * g + 0.0722 * b,
+        0.0193 * r + 0.1192 * g + 0.9505 * b,
+    ];
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let [fx, fy, fz]: [f32; 3] = std::array::from_fn(|i| f(xyz[i] / LAB_WHITE_D65[i]));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// CIELAB（D65）转 sRGB，超出色域的分量被夹取
+fn color_calc_from_lab(lab: [f32; 3]) -> [u8; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    let f_inv = |t: f32| if t > 0.206893 { t * t * t } else { (t - 16.0 / 116.0) / 7.787 };
+    let [x, y, z]: [f32; 3] = std::array::from_fn(|i| f_inv([fx, fy, fz][i]) * LAB_WHITE_D65[i]);
+    [
+        channel_calc_from_linear(3.2406 * x - 1.5372 * y - 0.4986 * z),
+        channel_calc_from_linear(-0.9689 * x + 1.8758 * y + 0.0415 * z),
+        channel_calc_from_linear(0.0557 * x - 0.2040 * y + 1.0570 * z),
+    ]
+}
+
+/// 计算图像 LAB 各通道的均值和标准差，完全透明的像素不参与统计
+fn image_calc_lab_stats(rgba: &image::RgbaImage) -> Option<([f32; 3], [f32; 3])> {
+    let mut sum = [0f64; 3];
+    let mut sum_sq = [0f64; 3];
+    let mut count = 0u64;
+    for pixel in rgba.pixels().filter(|p| p[3] > 0) {
+        let lab = color_calc_to_lab([pixel[0], pixel[1], pixel[2]]);
+        for (i, &value) in lab.iter().enumerate() {
+            sum[i] += value as f64;
+            sum_sq[i] += (value as f64) * (value as f64);
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+
+    let n = count as f64;
+    let mean: [f32; 3] = std::array::from_fn(|i| (sum[i] / n) as f32);
+    let std: [f32; 3] = std::array::from_fn(|i| (sum_sq[i] / n - (sum[i] / n).powi(2)).max(0.0).sqrt() as f32);
+    Some((mean, std))
+}
+
+/// Tauri IPC 命令：Reinhard 颜色迁移，使 source 具有 reference 的色彩统计特征
+///
+/// 在 CIELAB 空间中，将 source 每个通道按 (v - 均值) × (参考标准差 / 原标准差) + 参考均值 变换，
+/// 用于统一一批拍摄图片的光线和色调。原图某通道近乎单色时只平移均值；透明度保持不变
+///
+/// # 参数
+/// * `source` — 待调整的 base64 图片数据
+/// * `reference` — 提供目标色调的 base64 图片数据
+///
+/// # 返回值
+/// * `Ok(String)` — 调整后的 PNG data URL
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * 任一图片完全透明
+#[tauri::command]
+pub fn image_update_color_transfer(source: String, reference: String) -> Result<String, String> {
+    let mut rgba = image_load_base64(&source)?.to_rgba8();
+    let reference = image_load_base64(&reference)?.to_rgba8();
+
+    let (source_mean, source_std) = image_calc_lab_stats(&rgba)
+        .ok_or("Source image is fully transparent")?;
+    let (reference_mean, reference_std) = image_calc_lab_stats(&reference)
+        .ok_or("Reference image is fully transparent")?;
+    let scale: [f32; 3] = std::array::from_fn(|i| {
+        if source_std[i] > 1e-3 { reference_std[i] / source_std[i] } else { 1.0 }
+    });
+
+    rgba.par_chunks_exact_mut(4).for_each(|chunk| {
+        let lab = color_calc_to_lab([chunk[0], chunk[1], chunk[2]]);
+        let transferred: [f32; 3] = std::array::from_fn(|i| (lab[i] - source_mean[i]) * scale[i] + reference_mean[i]);
+        let rgb = color_calc_from_lab(transferred);
+        chunk[..3].copy_from_slice(&rgb);
+    });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// Tauri IPC 命令：双色调效果，按灰度在暗部颜色与亮部颜色之间渐变着色
+///
+/// 灰度取 BT.601 luma，0 映射为 shadow_color，1 映射为 highlight_color，中间线性插值；
+/// 只使用两种颜色的 RGB，透明度保持原图不变
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `shadow_color` — 暗部颜色，#RRGGBB 或 #RRGGBBAA
+/// * `highlight_color` — 亮部颜色，#RRGGBB 或 #RRGGBBAA
+///
+/// # 返回值
+/// * `Ok(String)` — 处理后的 PNG data URL
+///
+/// # 异常
+/// * 颜色格式非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_duotone(image_data: String, shadow_color: String, highlight_color: String) -> Result<String, String> {
+    let shadow = crate::color_calc_from_hex(&shadow_color)?;
+    let highlight = crate::color_calc_from_hex(&highlight_color)?;
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+
+    rgba.par_chunks_exact_mut(4).for_each(|chunk| {
+        let t = (chunk[0] as f32 * 0.299 + chunk[1] as f32 * 0.587 + chunk[2] as f32 * 0.114) / 255.0;
+        for i in 0..3 {
+            chunk[i] = (shadow[i] as f32 + (highlight[i] as f32 - shadow[i] as f32) * t).round().clamp(0.0, 255.0) as u8;
+        }
+    });
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(rgba))
+}
+
+/// 统计颜色前图像的最大像素数，超过时先以最近邻缩小（不产生新颜色）
+const COLOR_COUNT_MAX_PIXELS: u64 = 4_000_000;
+
+/// 单次最多返回的颜色数
+const COLOR_COUNT_MAX_RESULTS: u32 = 4096;
+
+/// 颜色及其像素数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorCount {
+    pub color: RgbaColor,
+    pub count: u64,
+}
+
+/// Tauri IPC 命令：统计图像中出现最多的精确颜色，用于判断是否适合调色板量化
+///
+/// 颜色按 RGBA 精确匹配；像素数超过 400 万时先以最近邻缩小，计数为缩小后图像中的像素数
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `max` — 最多返回的颜色数（1-4096）
+///
+/// # 返回值
+/// * `Ok(Vec<ColorCount>)` — 按像素数降序排列的颜色，数量相同时按颜色值排序
+///
+/// # 异常
+/// * max 超出范围
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_calc_color_counts(image_data: String, max: u32) -> Result<Vec<ColorCount>, String> {
+    if max == 0 || max > COLOR_COUNT_MAX_RESULTS {
+        return Err(format!("Color count max must be between 1 and {}", COLOR_COUNT_MAX_RESULTS));
+    }
+
+    let mut img = image_load_base64(&image_data)?;
+    let pixels = img.width() as u64 * img.height() as u64;
+    if pixels > COLOR_COUNT_MAX_PIXELS {
+        let scale = (COLOR_COUNT_MAX_PIXELS as f64 / pixels as f64).sqrt();
+        let width = ((img.width() as f64 * scale) as u32).max(1);
+        let height = ((img.height() as f64 * scale) as u32).max(1);
+        img = img.resize_exact(width, height, image::imageops::FilterType::Nearest);
+    }
+    let rgba = img.to_rgba8();
+
+    let counts = rgba.as_raw()
+        .par_chunks_exact(4)
+        .fold(HashMap::new, |mut map: HashMap<[u8; 4], u64>, chunk| {
+            *map.entry([chunk[0], chunk[1], chunk[2], chunk[3]]).or_insert(0) += 1;
+            map
+        })
+        .reduce(HashMap::new, |a, b| {
+            // 把较小的表合并进较大的表
+            let (mut large, small) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+            for (color, count) in small {
+                *large.entry(color).or_insert(0) += count;
+            }
+            large
+        });
+
+    let mut sorted: Vec<([u8; 4], u64)> = counts.into_iter().collect();
+    sorted.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    sorted.truncate(max as usize);
+
+    Ok(sorted.into_iter()
+        .map(|([r, g, b, a], count)| ColorCount { color: RgbaColor { r, g, b, a }, count })
+        .collect())
+}
+
+/// 某个宽高比下的裁剪参考框
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropGuide {
+    /// 宽高比 [宽, 高]
+    pub ratio: [u32; 2],
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Tauri IPC 命令：计算各宽高比下能放入图像的最大居中矩形，供前端叠加显示构图参考框
+///
+/// 纯几何计算，不需要图像数据；矩形尺寸向下取整，保证不超出图像
+///
+/// # 参数
+/// * `width` / `height` — 图像尺寸
+/// * `ratios` — 宽高比列表，如 [[1, 1], [16, 9]]
+///
+/// # 返回值
+/// * `Ok(Vec<CropGuide>)` — 与 ratios 顺序一一对应的参考框
+///
+/// # 异常
+/// * 图像尺寸或宽高比含 0
+#[tauri::command]
+pub fn image_calc_crop_guides(width: u32, height: u32, ratios: Vec<[u32; 2]>) -> Result<Vec<CropGuide>, String> {
+    if width == 0 || height == 0 {
+        return Err("Image width and height must be greater than zero".to_string());
+    }
+
+    ratios.into_iter()
+        .map(|[ratio_w, ratio_h]| {
+            if ratio_w == 0 || ratio_h == 0 {
+                return Err(format!("Invalid aspect ratio: {}:{}", ratio_w, ratio_h));
+            }
+            // 图像比目标比例更宽时以高度为准，否则以宽度为准
+            let (guide_w, guide_h) = if width as u64 * ratio_h as u64 >= height as u64 * ratio_w as u64 {
+                ((height as u64 * ratio_w as u64 / ratio_h as u64) as u32, height)
+            } else {
+                (width, (width as u64 * ratio_h as u64 / ratio_w as u64) as u32)
+            };
+            Ok(CropGuide {
+                ratio: [ratio_w, ratio_h],
+                x: (width - guide_w) / 2,
+                y: (height - guide_h) / 2,
+                width: guide_w,
+                height: guide_h,
+            })
+        })
+        .collect()
+}
+
+/// Tauri IPC 命令：将剪贴板粘贴的图片统一转换为 PNG data URL
+///
+/// 粘贴得到的数据可能是 PNG/JPEG/WebP/BMP/GIF 等格式，声明的 MIME 类型也不一定可靠：
+/// 优先按文件头识别，无法识别时才按 declared_mime 解码；两者不一致时记录日志
+///
+/// # 参数
+/// * `data` — base64 图片数据，可带 data: 前缀
+/// * `declared_mime` — 剪贴板声明的 MIME 类型，如 "image/png"
+///
+/// # 返回值
+/// * `Ok(String)` — PNG data URL
+///
+/// # 异常
+/// * base64 解析失败或数据超过 50MB
+/// * 格式无法识别或图像数据损坏
+#[tauri::command]
+pub fn image_format_pasted(data: String, declared_mime: String) -> Result<String, String> {
+    let base64_data = match data.split_once(',') {
+        Some((prefix, payload)) if prefix.starts_with("data:") => payload,
+        _ => data.as_str(),
+    };
+    if base64_data.len() > MAX_IMAGE_SIZE * 4 / 3 {
+        return Err("Image data too large (max 50MB)".to_string());
+    }
+    let bytes = general_purpose::STANDARD
+        .decode(base64_data.trim())
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+
+    let declared = image::ImageFormat::from_mime_type(declared_mime.trim());
+    if let (Ok(sniffed), Some(declared)) = (image::guess_format(&bytes), declared) {
+        if sniffed != declared {
+            log::info!("粘贴图片声明为 {}，实际为 {:?}", declared_mime, sniffed);
+        }
+    }
+
+    let img = image_load_sniffed(&bytes, declared)?;
+    image_encode_png_base64(&img)
+}
+
+/// 去除黑边的结果：裁剪后的图像及各边检测到的边条宽度（像素）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LetterboxResult {
+    /// 裁剪后的 PNG data URL；没有检测到边条时为原图
+    pub image_data: String,
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+/// 像素与参考色各通道（含 alpha）的差值都不超过 tolerance
+fn pixel_validate_near(pixel: &image::Rgba<u8>, reference: &image::Rgba<u8>, tolerance: u8) -> bool {
+    pixel.0.iter().zip(reference.0.iter()).all(|(&a, &b)| a.abs_diff(b) <= tolerance)
+}
+
+/// Tauri IPC 命令：检测并裁掉截图四周的纯色边条（黑边、窗口化或加边框的内容）
+///
+/// 从每条边向内逐行/列检查，整行（列）像素都接近该边起始像素颜色时视为边条；
+/// 先确定上下边条，再在剩余行范围内确定左右边条。边条占满整张图（如纯色图）时不裁剪
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `tolerance` — 各通道允许的颜色偏差（0..255），JPEG 截图建议 16 左右
+///
+/// # 返回值
+/// * `Ok(LetterboxResult)` — 裁剪后的图像及上下左右边条宽度
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_format_letterbox(image_data: String, tolerance: u8) -> Result<LetterboxResult, String> {
+    let img = image_load_base64(&image_data)?;
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    let row_uniform = |y: u32, reference: &image::Rgba<u8>| {
+        (0..width).all(|x| pixel_validate_near(rgba.get_pixel(x, y), reference, tolerance))
+    };
+    let top_color = *rgba.get_pixel(0, 0);
+    let top = (0..height).take_while(|&y| row_uniform(y, &top_color)).count() as u32;
+    let bottom_color = *rgba.get_pixel(0, height - 1);
+    let bottom = (top..height).rev().take_while(|&y| row_uniform(y, &bottom_color)).count() as u32;
+
+    // 上下边条占满整张图（纯色或只有两种颜色的色带）时不裁剪
+    let unchanged = || -> Result<LetterboxResult, String> {
+        Ok(LetterboxResult { image_data: image_encode_png_base64(&img)?, top: 0, bottom: 0, left: 0, right: 0 })
+    };
+    if top + bottom >= height {
+        return unchanged();
+    }
+
+    let column_uniform = |x: u32, reference: &image::Rgba<u8>| {
+        (top..height - bottom).all(|y| pixel_validate_near(rgba.get_pixel(x, y), reference, tolerance))
+    };
+    let left_color = *rgba.get_pixel(0, top);
+    let left = (0..width).take_while(|&x| column_uniform(x, &left_color)).count() as u32;
+    let right_color = *rgba.get_pixel(width - 1, top);
+    let right = (left..width).rev().take_while(|&x| column_uniform(x, &right_color)).count() as u32;
+    if left + right >= width {
+        return unchanged();
+    }
+
+    let cropped = img.crop_imm(left, top, width - left - right, height - top - bottom);
+    Ok(LetterboxResult {
+        image_data: image_encode_png_base64(&cropped)?,
+        top,
+        bottom,
+        left,
+        right,
+    })
+}
+
+/// 增强结果及其缩略图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnhancedWithThumbnail {
+    /// 增强后的完整图像 PNG data URL
+    pub image_data: String,
+    /// 增强后图像的缩略图 PNG data URL
+    pub thumbnail: String,
+}
+
+/// Tauri IPC 命令：增强图片并同时生成缩略图，只解码一次
+///
+/// 拍摄流程中增强和生成缩略图原本需要分别解码原图，这里直接由增强结果缩小得到缩略图；
+/// 缩略图保持原比例，原图小于目标尺寸时不放大
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `params` — 增强参数
+/// * `thumb_size` — 缩略图最长边
+///
+/// # 返回值
+/// * `Ok(EnhancedWithThumbnail)` — 增强后的完整图像与缩略图
+///
+/// # 异常
+/// * 增强参数非法或缩略图尺寸为 0
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn image_update_enhance_thumbnail(image_data: String, params: EnhanceParams, thumb_size: u32) -> Result<EnhancedWithThumbnail, String> {
+    params.validate()?;
+    if thumb_size == 0 {
+        return Err("Thumbnail size must be greater than zero".to_string());
+    }
+
+    let mut rgba = image_load_base64(&image_data)?.to_rgba8();
+    pixel_update_enhance_params(&mut rgba, &params);
+    let enhanced = DynamicImage::ImageRgba8(rgba);
+
+    let thumbnail = if enhanced.width().max(enhanced.height()) > thumb_size {
+        enhanced.resize(thumb_size, thumb_size, image::imageops::FilterType::Triangle)
+    } else {
+        enhanced.clone()
+    };
+
+    Ok(EnhancedWithThumbnail {
+        image_data: image_encode_png_base64(&enhanced)?,
+        thumbnail: image_encode_png_base64(&thumbnail)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_eviction_keeps_recently_used_entries() {
+        let image_data = image_encode_png_base64(&DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4))).unwrap();
+        let base = image_load_proxy(image_data.clone(), None).unwrap();
+        let mut others = Vec::new();
+        for _ in 0..PROXY_MAX_ENTRIES * 2 {
+            others.push(image_load_proxy(image_data.clone(), None).unwrap());
+            image_fetch_proxy(base).unwrap();
+        }
+
+        assert!(image_fetch_proxy(base).is_ok());
+        assert!(image_fetch_proxy(others[0]).is_err());
+        for id in others.into_iter().chain([base]) {
+            image_delete_proxy(id).unwrap();
+        }
+    }
+
+    fn encode(img: image::RgbaImage) -> String {
+        image_encode_png_base64(&DynamicImage::ImageRgba8(img)).unwrap()
+    }
+
+    fn decode(data: &str) -> image::RgbaImage {
+        image_load_base64(data).unwrap().to_rgba8()
+    }
+
+    fn encode_with_icc(img: &image::RgbaImage, icc: Vec<u8>) -> String {
+        use image::ImageEncoder;
+
+        let mut bytes = Vec::new();
+        let mut encoder = image::codecs::png::PngEncoder::new(&mut bytes);
+        encoder.set_icc_profile(icc).unwrap();
+        encoder.write_image(img.as_raw(), img.width(), img.height(), image::ExtendedColorType::Rgba8).unwrap();
+        format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&bytes))
+    }
+
+    #[test]
+    fn tilt_shift_keeps_focus_band_and_blurs_edges() {
+        // 黑白竖条纹：模糊后趋向灰色，清晰处保持纯黑或纯白
+        let img = image::RgbaImage::from_fn(40, 100, |x, _| {
+            if x % 2 == 0 { image::Rgba([255, 255, 255, 255]) } else { image::Rgba([0, 0, 0, 255]) }
+        });
+        let run = |center: f32| {
+            tauri::async_runtime::block_on(image_update_tilt_shift(encode(img.clone()), center, 0.2, 4.0))
+        };
+        let out = decode(&run(0.5).unwrap());
+
+        for y in 40..60 {
+            for x in 0..40 {
+                assert_eq!(out.get_pixel(x, y), img.get_pixel(x, y));
+            }
+        }
+        for y in [2, 97] {
+            let value = out.get_pixel(20, y)[0];
+            assert!(value > 60 && value < 200, "row {} not blurred: {}", y, value);
+        }
+        assert!(run(1.5).is_err());
+    }
+
+    #[test]
+    fn stack_frames_reduces_noise_below_any_single_frame() {
+        // 均匀灰度 128 叠加 ±30 的伪随机噪声
+        let mut seed = 7u32;
+        let frames: Vec<image::RgbaImage> = (0..9)
+            .map(|_| image::RgbaImage::from_fn(20, 20, |_, _| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                let value = (128 + ((seed >> 16) % 61) as i32 - 30) as u8;
+                image::Rgba([value, value, value, 255])
+            }))
+            .collect();
+        let error = |img: &image::RgbaImage| img.pixels().map(|p| (p[0] as f64 - 128.0).powi(2)).sum::<f64>();
+        let best_single = frames.iter().map(error).fold(f64::MAX, f64::min);
+        let encoded: Vec<String> = frames.into_iter().map(encode).collect();
+
+        for mode in ["mean", "median"] {
+            let stacked = decode(&image_update_stack_frames(encoded.clone(), mode.to_string()).unwrap());
+            assert!(error(&stacked) < best_single, "{} did not reduce noise", mode);
+        }
+
+        let mismatched = vec![encode(image::RgbaImage::new(2, 2)), encode(image::RgbaImage::new(3, 2))];
+        assert!(image_update_stack_frames(mismatched.clone(), "mean".to_string()).is_err());
+        assert!(image_update_stack_frames(mismatched, "median".to_string()).is_err());
+        assert!(image_update_stack_frames(encoded, "max".to_string()).is_err());
+    }
+
+    #[test]
+    fn ink_coverage_counts_pixels_off_background() {
+        let mut page = image::RgbaImage::from_pixel(10, 10, image::Rgba([250, 250, 245, 255]));
+        for x in 0..10 {
+            page.put_pixel(x, 3, image::Rgba([0, 0, 0, 255]));
+        }
+        let coverage = image_calc_ink_coverage(encode(page.clone()), "#FFFFFF".to_string(), 16).unwrap();
+        assert!((coverage - 0.1).abs() < 1e-6, "{}", coverage);
+
+        // 透明像素按背景色计算，不论背景深浅都不算墨迹
+        let transparent = encode(image::RgbaImage::new(4, 4));
+        assert_eq!(image_calc_ink_coverage(transparent.clone(), "#FFFFFF".to_string(), 0).unwrap(), 0.0);
+        assert_eq!(image_calc_ink_coverage(transparent, "#202020".to_string(), 0).unwrap(), 0.0);
+        assert!(image_calc_ink_coverage(encode(page), "zz".to_string(), 0).is_err());
+    }
+
+    #[test]
+    fn vignette_correction_flattens_radial_falloff() {
+        // 中心亮度 160，向角落按 1 / (1 + 0.6 r²) 衰减
+        let (width, height) = (300u32, 200u32);
+        let vignetted = image::RgbaImage::from_fn(width, height, |x, y| {
+            let (half_w, half_h) = (width as f32 / 2.0, height as f32 / 2.0);
+            let (dx, dy) = (x as f32 + 0.5 - half_w, y as f32 + 0.5 - half_h);
+            let r2 = (dx * dx + dy * dy) / (half_w * half_w + half_h * half_h);
+            let value = (160.0 / (1.0 + 0.6 * r2)).round() as u8;
+            image::Rgba([value, value, value, 255])
+        });
+        let corrected = decode(&image_update_vignette_correction(encode(vignetted)).unwrap());
+
+        let center = corrected.get_pixel(150, 100)[0] as i32;
+        for (x, y) in [(0, 0), (299, 0), (0, 199), (299, 199), (10, 100)] {
+            let value = corrected.get_pixel(x, y)[0] as i32;
+            assert!((value - center).abs() <= 6, "({}, {}) = {}, center {}", x, y, value, center);
+        }
+
+        let flat = image::RgbaImage::from_pixel(50, 50, image::Rgba([100, 100, 100, 255]));
+        assert_eq!(decode(&image_update_vignette_correction(encode(flat.clone())).unwrap()), flat);
+    }
+
+    #[test]
+    fn region_patch_only_changes_target_area() {
+        let base = image::RgbaImage::from_pixel(30, 30, image::Rgba([0, 0, 0, 255]));
+        let patch = image::RgbaImage::from_pixel(10, 10, image::Rgba([255, 0, 0, 255]));
+        let patched = decode(&image_update_region(encode(base.clone()), encode(patch.clone()), 10, 10).unwrap());
+
+        for (x, y, pixel) in patched.enumerate_pixels() {
+            let inside = (10..20).contains(&x) && (10..20).contains(&y);
+            let expected = if inside { patch.get_pixel(x - 10, y - 10) } else { base.get_pixel(x, y) };
+            assert_eq!(pixel, expected, "({}, {})", x, y);
+        }
+        assert!(image_update_region(encode(base), encode(patch), 25, 10).is_err());
+    }
+
+    #[test]
+    fn invert_twice_restores_original() {
+        let img = image::RgbaImage::from_fn(8, 8, |x, y| image::Rgba([x as u8 * 30, y as u8 * 30, 200, (x * y) as u8 * 4]));
+        let inverted = decode(&image_update_invert(encode(img.clone())).unwrap());
+        assert_eq!(inverted.get_pixel(1, 2), &image::Rgba([225, 195, 55, 8]));
+        assert_eq!(decode(&image_update_invert(encode(inverted)).unwrap()), img);
+    }
+
+    #[test]
+    fn alpha_threshold_hardens_edges() {
+        let img = image::RgbaImage::from_fn(4, 1, |x, _| image::Rgba([10, 20, 30, [0, 100, 128, 200][x as usize]]));
+        let result = decode(&image_update_alpha_threshold(encode(img), 127).unwrap());
+        let alphas: Vec<u8> = result.pixels().map(|p| p[3]).collect();
+        assert_eq!(alphas, vec![0, 0, 255, 255]);
+        // 完全透明的像素 RGB 在 PNG 中仍然保留
+        assert!(result.pixels().all(|p| p[0] == 10 && p[1] == 20 && p[2] == 30));
+    }
+
+    #[test]
+    fn pattern_fill_tiles_inside_rect_only() {
+        let base = image::RgbaImage::from_pixel(60, 60, image::Rgba([0, 0, 255, 255]));
+        let pattern = image::RgbaImage::from_fn(10, 10, |x, y| image::Rgba([x as u8 * 20, y as u8 * 20, 0, 255]));
+        let filled = decode(&image_update_pattern_fill(encode(base.clone()), encode(pattern.clone()), 5, 5, 40, 40).unwrap());
+
+        for (x, y, pixel) in filled.enumerate_pixels() {
+            if (5..45).contains(&x) && (5..45).contains(&y) {
+                assert_eq!(pixel, pattern.get_pixel((x - 5) % 10, (y - 5) % 10), "({}, {})", x, y);
+            } else {
+                assert_eq!(pixel, base.get_pixel(x, y), "({}, {})", x, y);
+            }
+        }
+        assert!(image_update_pattern_fill(encode(base), encode(pattern), 30, 30, 40, 40).is_err());
+    }
+
+    #[test]
+    fn region_color_averages_opaque_pixels() {
+        // 左半红、右半蓝，第 0 行完全透明
+        let img = image::RgbaImage::from_fn(20, 10, |x, y| match (x < 10, y == 0) {
+            (_, true) => image::Rgba([255, 255, 255, 0]),
+            (true, false) => image::Rgba([200, 0, 0, 255]),
+            (false, false) => image::Rgba([0, 0, 100, 255]),
+        });
+        let color = image_fetch_region_color(encode(img.clone()), 5, 0, 10, 10).unwrap();
+        assert_eq!((color.r, color.g, color.b), (100, 0, 50));
+        assert!(image_fetch_region_color(encode(img), 15, 0, 10, 10).is_err());
+    }
+
+    #[test]
+    fn mipmaps_halve_each_level() {
+        let img = image::RgbaImage::from_pixel(256, 256, image::Rgba([40, 80, 120, 255]));
+        let levels = image_format_mipmaps(encode(img), 3).unwrap();
+        let sizes: Vec<(u32, u32)> = levels.iter().map(|level| decode(level).dimensions()).collect();
+        assert_eq!(sizes, vec![(256, 256), (128, 128), (64, 64), (32, 32)]);
+        assert_eq!(decode(&levels[3]).get_pixel(7, 7), &image::Rgba([40, 80, 120, 255]));
+
+        // 缩到 1 像素后提前停止
+        assert_eq!(image_format_mipmaps(encode(image::RgbaImage::new(4, 2)), 5).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn jpeg_chroma_subsampling_is_applied() {
+        // 一像素宽的红绿交替竖线，色度细节在 4:2:0 下会被抹平
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(64, 64, |x, _| {
+            if x % 2 == 0 { image::Rgba([255, 0, 0, 255]) } else { image::Rgba([0, 255, 0, 255]) }
+        }));
+        let full = image_encode_jpeg(&img, 90, ChromaSubsampling::Yuv444).unwrap();
+        let half = image_encode_jpeg(&img, 90, ChromaSubsampling::Yuv420).unwrap();
+        assert!(full.len() > half.len(), "4:4:4 {} bytes, 4:2:0 {} bytes", full.len(), half.len());
+
+        let decoded_full = image::load_from_memory(&full).unwrap().to_rgb8();
+        let decoded_half = image::load_from_memory(&half).unwrap().to_rgb8();
+        assert_ne!(decoded_full, decoded_half);
+
+        assert_eq!(ChromaSubsampling::parse(None).unwrap(), ChromaSubsampling::Yuv420);
+        assert!(ChromaSubsampling::parse(Some("4:1:1")).is_err());
+    }
+
+    #[test]
+    fn smart_crop_keeps_off_center_detail() {
+        // 白底，右侧 150..190 列为黑白棋盘格；居中裁剪 50..150 会完全错过
+        let img = image::RgbaImage::from_fn(200, 100, |x, y| {
+            if (150..190).contains(&x) && (x / 4 + y / 4) % 2 == 0 {
+                image::Rgba([0, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        });
+        let cropped = decode(&image_format_smart_crop(encode(img), 100, 100).unwrap());
+        assert_eq!(cropped.dimensions(), (100, 100));
+        let dark = cropped.pixels().filter(|p| p[0] < 128).count();
+        assert!(dark > 1500, "only {} dark pixels kept", dark);
+        assert!(image_format_smart_crop(encode(image::RgbaImage::new(4, 4)), 0, 4).is_err());
+    }
+
+    #[test]
+    fn content_aware_removes_seams_from_flat_region() {
+        // 左半部分为均匀灰色背景，右半部分为细密棋盘格主体
+        let img = image::RgbaImage::from_fn(60, 20, |x, y| {
+            if x >= 30 && (x + y) % 2 == 0 { image::Rgba([0, 0, 0, 255]) } else { image::Rgba([128, 128, 128, 255]) }
+        });
+        let carved = decode(&tauri::async_runtime::block_on(image_format_content_aware(encode(img.clone()), 50, 20)).unwrap());
+        assert_eq!(carved.dimensions(), (50, 20));
+        assert!(tauri::async_runtime::block_on(image_format_content_aware(encode(img.clone()), 100_000, 20)).is_err());
+
+        let preserved = (0..20)
+            .flat_map(|y| (0..30).map(move |x| (x, y)))
+            .filter(|&(x, y)| carved.get_pixel(x + 20, y) == img.get_pixel(x + 30, y))
+            .count();
+        assert!(preserved >= 30 * 20 * 9 / 10, "only {} subject pixels preserved", preserved);
+    }
+
+    #[test]
+    fn ssim_is_one_for_identical_and_lower_for_degraded() {
+        let img = image::RgbaImage::from_fn(64, 64, |x, y| {
+            let value = ((x * 7 + y * 13) % 256) as u8;
+            image::Rgba([value, value / 2, 255 - value, 255])
+        });
+        // 叠加 ±60 的伪随机噪声
+        let mut seed = 3u32;
+        let degraded = image::RgbaImage::from_fn(64, 64, |x, y| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let noise = ((seed >> 16) % 121) as i32 - 60;
+            let p = img.get_pixel(x, y);
+            let add = |value: u8| (value as i32 + noise).clamp(0, 255) as u8;
+            image::Rgba([add(p[0]), add(p[1]), add(p[2]), 255])
+        });
+
+        let same = image_calc_ssim(encode(img.clone()), encode(img.clone())).unwrap();
+        assert!((same - 1.0).abs() < 1e-4, "{}", same);
+        let lower = image_calc_ssim(encode(img.clone()), encode(degraded)).unwrap();
+        assert!(lower < 0.9, "{}", lower);
+        assert!(image_calc_ssim(encode(img), encode(image::RgbaImage::new(32, 64))).is_err());
+    }
+
+    #[test]
+    fn chromatic_aberration_offsets_red_and_blue() {
+        // 左黑右白的竖直边缘位于 x = 20
+        let img = image::RgbaImage::from_fn(40, 10, |x, _| {
+            if x < 20 { image::Rgba([0, 0, 0, 255]) } else { image::Rgba([255, 255, 255, 255]) }
+        });
+        let shifted = decode(&image_update_chromatic_aberration(encode(img), 3.0, 0.0).unwrap());
+        let edge = |channel: usize| (0..40).find(|&x| shifted.get_pixel(x, 5)[channel] > 127).unwrap();
+        assert_eq!(edge(1), 20);
+        assert_eq!(edge(0), 23);
+        assert_eq!(edge(2), 17);
+    }
+
+    #[test]
+    fn posterize_limits_channel_values() {
+        let img = image::RgbaImage::from_fn(64, 64, |x, y| image::Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255]));
+        let two = decode(&image_update_posterize(encode(img.clone()), 2, None).unwrap());
+        for channel in 0..3 {
+            let values: std::collections::BTreeSet<u8> = two.pixels().map(|p| p[channel]).collect();
+            assert_eq!(values.len(), 2, "channel {}: {:?}", channel, values);
+        }
+        assert_eq!(decode(&image_update_posterize(encode(img.clone()), 256, None).unwrap()), img);
+        assert!(image_update_posterize(encode(img.clone()), 1, None).is_err());
+        assert!(image_update_posterize(encode(img), 257, None).is_err());
+    }
+
+    #[test]
+    fn dithering_breaks_up_banding() {
+        let gradient = image::RgbaImage::from_fn(128, 16, |x, _| {
+            let value = (x * 2) as u8;
+            image::Rgba([value, value, value, 255])
+        });
+        // 相邻像素取值不同的次数：色带只在级数边界变化，抖动后在过渡区交错
+        let transitions = |img: &image::RgbaImage| {
+            img.rows()
+                .map(|row| row.collect::<Vec<_>>().windows(2).filter(|w| w[0] != w[1]).count())
+                .sum::<usize>()
+        };
+
+        let banded = decode(&image_update_posterize(encode(gradient.clone()), 4, None).unwrap());
+        for mode in ["floyd_steinberg", "ordered"] {
+            let dithered = decode(&image_update_posterize(encode(gradient.clone()), 4, Some(mode.to_string())).unwrap());
+            assert!(transitions(&dithered) > transitions(&banded) * 4, "{}", mode);
+        }
+        assert!(image_update_posterize(encode(gradient), 4, Some("random".to_string())).is_err());
+    }
+
+    #[test]
+    fn rotation_interpolation_modes() {
+        let checker = image::RgbaImage::from_fn(40, 40, |x, y| {
+            if (x / 5 + y / 5) % 2 == 0 { image::Rgba([0, 0, 0, 255]) } else { image::Rgba([255, 255, 255, 255]) }
+        });
+        let rotate = |mode: &str| decode(&image_update_rotation_angle(encode(checker.clone()), 30.0, Some(mode.to_string())).unwrap());
+        let is_intermediate = |p: &image::Rgba<u8>| p[3] == 255 && p[0] != 0 && p[0] != 255;
+
+        let nearest = rotate("nearest");
+        assert!(nearest.width() > 40 && nearest.height() > 40);
+        assert!(nearest.pixels().all(|p| p[3] == 0 || (p[3] == 255 && (p[0] == 0 || p[0] == 255))));
+        assert!(rotate("bilinear").pixels().any(is_intermediate));
+        assert!(image_update_rotation_angle(encode(checker), 30.0, Some("lanczos".to_string())).is_err());
+    }
+
+    #[test]
+    fn auto_enhance_brightens_dark_low_contrast_image() {
+        // 亮度集中在 30..60 的暗淡渐变
+        let dark = image::RgbaImage::from_fn(64, 64, |x, y| {
+            let value = 30 + ((x + y) * 30 / 126) as u8;
+            image::Rgba([value, value, value, 255])
+        });
+        let luma_variance = |img: &image::RgbaImage| {
+            let values: Vec<f64> = img.pixels().map(|p| p[0] as f64 * 0.299 + p[1] as f64 * 0.587 + p[2] as f64 * 0.114).collect();
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+        };
+
+        let result = image_update_auto_enhance(encode(dark.clone())).unwrap();
+        assert!(result.brightness > 0, "brightness {}", result.brightness);
+        assert!(result.contrast > 1.0, "contrast {}", result.contrast);
+        assert!(luma_variance(&decode(&result.image_data)) > luma_variance(&dark));
+    }
+
+    #[test]
+    fn tiles_cover_image_with_partial_edges() {
+        let img = image::RgbaImage::from_fn(300, 200, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let tiled = image_format_tiles(encode(img), 128).unwrap();
+        assert_eq!((tiled.cols, tiled.rows, tiled.tile_size), (3, 2, 128));
+        assert_eq!((tiled.last_col_width, tiled.last_row_height), (44, 72));
+        assert_eq!(tiled.tiles.len(), 6);
+
+        let sizes: Vec<(u32, u32)> = tiled.tiles.iter().map(|tile| decode(tile).dimensions()).collect();
+        assert_eq!(sizes, vec![(128, 128), (128, 128), (44, 128), (128, 72), (128, 72), (44, 72)]);
+        assert_eq!(decode(&tiled.tiles[5]).get_pixel(0, 0), &image::Rgba([0, 128, 0, 255]));
+    }
+
+    #[test]
+    fn untile_reverses_tiling() {
+        let img = image::RgbaImage::from_fn(300, 200, |x, y| image::Rgba([x as u8, y as u8, (x ^ y) as u8, 255]));
+        let original = encode(img);
+        let tiled = image_format_tiles(original.clone(), 128).unwrap();
+
+        let stitched = image_format_untile(tiled.tiles.clone(), tiled.cols, tiled.rows).unwrap();
+        assert_eq!(stitched, original);
+        assert!(image_format_untile(tiled.tiles, 2, 2).is_err());
+    }
+
+    #[test]
+    fn gif_keeps_every_frame_at_first_frame_size() {
+        let frames = vec![
+            encode(image::RgbaImage::from_pixel(8, 6, image::Rgba([255, 0, 0, 255]))),
+            encode(image::RgbaImage::from_pixel(16, 12, image::Rgba([0, 255, 0, 255]))),
+            encode(image::RgbaImage::from_pixel(8, 6, image::Rgba([0, 0, 255, 255]))),
+        ];
+        let gif = tauri::async_runtime::block_on(image_format_gif(frames, 100, 0)).unwrap();
+        let bytes = general_purpose::STANDARD
+            .decode(gif.strip_prefix("data:image/gif;base64,").unwrap())
+            .unwrap();
+
+        use image::AnimationDecoder;
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(bytes)).unwrap();
+        let decoded = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), 3);
+        // 第二帧尺寸不同，应缩放到首帧尺寸
+        assert!(decoded.iter().all(|f| f.buffer().dimensions() == (8, 6)));
+        assert_eq!(decoded[1].buffer().get_pixel(4, 3).0[1], 255);
+
+        assert!(tauri::async_runtime::block_on(image_format_gif(Vec::new(), 100, 0)).is_err());
+    }
+
+    #[test]
+    fn indexed_png_is_smaller_and_keeps_graphic_colors() {
+        let colors = [
+            image::Rgba([255, 0, 0, 255]),
+            image::Rgba([0, 255, 0, 255]),
+            image::Rgba([0, 0, 255, 255]),
+            image::Rgba([255, 255, 255, 255]),
+        ];
+        let img = image::RgbaImage::from_fn(128, 128, |x, y| colors[((x / 16 + y / 16) % 4) as usize]);
+        let truecolor = encode(img.clone());
+        let indexed = image_format_indexed_png(truecolor.clone(), 4).unwrap();
+
+        let truecolor_len = truecolor.strip_prefix("data:image/png;base64,").unwrap().len();
+        let indexed_len = indexed.strip_prefix("data:image/png;base64,").unwrap().len();
+        assert!(indexed_len < truecolor_len, "{} >= {}", indexed_len, truecolor_len);
+        assert_eq!(decode(&indexed), img);
+
+        assert!(image_format_indexed_png(truecolor, 1).is_err());
+    }
+
+    #[test]
+    fn proxy_enhance_is_repeatable_until_freed() {
+        let img = image::RgbaImage::from_fn(256, 128, |x, y| image::Rgba([x as u8, y as u8 * 2, 100, 255]));
+        let proxy_id = image_load_proxy(encode(img), Some(64)).unwrap();
+        let params = || EnhanceParams { contrast: 1.4, brightness: 10, saturation: 1.2, linear_light: None };
+
+        let first = image_update_proxy(proxy_id, params()).unwrap();
+        let second = image_update_proxy(proxy_id, params()).unwrap();
+        assert_eq!(first, second);
+        // 代理图按最大边长缩小
+        let preview = decode(&first);
+        assert_eq!((preview.width(), preview.height()), (64, 32));
+
+        assert!(image_delete_proxy(proxy_id).unwrap());
+        assert!(!image_delete_proxy(proxy_id).unwrap());
+        assert!(image_update_proxy(proxy_id, params()).is_err());
+    }
+
+    #[test]
+    fn hue_range_adjusts_red_and_leaves_blue() {
+        let red = image::Rgba([200, 40, 40, 255]);
+        let blue = image::Rgba([40, 40, 200, 255]);
+        let img = image::RgbaImage::from_fn(16, 8, |x, _| if x < 8 { red } else { blue });
+
+        let adjusted = decode(&image_update_hue_range(encode(img), 0.0, 30.0, -1.0, 0.0).unwrap());
+        // 红色完全去饱和，蓝色保持不变
+        let [r, g, b, _] = adjusted.get_pixel(2, 4).0;
+        assert!(r.abs_diff(g) <= 2 && g.abs_diff(b) <= 2, "{:?}", (r, g, b));
+        assert_eq!(*adjusted.get_pixel(12, 4), blue);
+
+        assert!(image_update_hue_range(String::new(), 0.0, 0.0, 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn replace_color_keys_green_to_transparent() {
+        let green = image::Rgba([0, 255, 0, 255]);
+        let subject = image::Rgba([200, 60, 40, 255]);
+        let img = image::RgbaImage::from_fn(20, 20, |x, y| {
+            if (6..14).contains(&x) && (6..14).contains(&y) { subject } else { green }
+        });
+
+        let keyed = decode(&image_update_replace_color(encode(img), "#00FF00".to_string(), None, 0.1, 0.1).unwrap());
+        assert_eq!(keyed.get_pixel(1, 1)[3], 0);
+        assert_eq!(keyed.get_pixel(18, 10)[3], 0);
+        assert_eq!(*keyed.get_pixel(10, 10), subject);
+        assert_eq!(*keyed.get_pixel(6, 13), subject);
+    }
+
+    #[test]
+    fn placeholder_is_tiny_and_decodable() {
+        let img = image::RgbaImage::from_fn(400, 300, |x, y| image::Rgba([(x / 2) as u8, (y / 2) as u8, 128, 255]));
+        let placeholder = image_format_placeholder(encode(img)).unwrap();
+
+        let bytes = general_purpose::STANDARD
+            .decode(placeholder.strip_prefix("data:image/png;base64,").unwrap())
+            .unwrap();
+        assert!(bytes.len() < 1024, "placeholder is {} bytes", bytes.len());
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (PLACEHOLDER_MAX_SIZE, 12));
+        assert!(!decoded.color().has_alpha());
+    }
+
+    #[test]
+    fn blurhash_of_solid_color_encodes_dc_and_length() {
+        let color = [200u8, 100, 50];
+        let img = image::RgbaImage::from_pixel(32, 24, image::Rgba([color[0], color[1], color[2], 255]));
+        let hash = image_format_blurhash(encode(img), 4, 3).unwrap();
+
+        assert_eq!(hash.len(), 6 + 2 * (4 * 3 - 1));
+        let digits = |s: &str| s.bytes().fold(0u32, |acc, c| {
+            acc * 83 + BLURHASH_BASE83.iter().position(|&d| d == c).unwrap() as u32
+        });
+        assert_eq!(digits(&hash[0..1]), 3 + 2 * 9);
+        // DC 分量（第 3~6 个字符）即纯色本身
+        let dc = digits(&hash[2..6]);
+        assert_eq!([(dc >> 16) as u8, (dc >> 8) as u8, dc as u8], color);
+
+        assert!(image_format_blurhash(String::new(), 0, 3).is_err());
+        assert!(image_format_blurhash(String::new(), 4, 10).is_err());
+    }
+
+    #[test]
+    fn linear_light_enhance_keeps_midtones_brighter() {
+        let img = image::RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 { image::Rgba([255, 0, 0, 255]) } else { image::Rgba([128, 128, 128, 255]) }
+        });
+        let run = |linear_light, contrast, saturation| {
+            let mut out = img.clone();
+            let params = EnhanceParams { contrast, brightness: 0, saturation, linear_light: Some(linear_light) };
+            pixel_update_enhance_params(&mut out, &params);
+            out
+        };
+
+        // 饱和度减半：线性光下按真实亮度混合，红色不会像 sRGB 空间那样发暗发浑
+        let (srgb, linear) = (run(false, 1.0, 0.5), run(true, 1.0, 0.5));
+        assert!(linear.get_pixel(0, 0)[0] > srgb.get_pixel(0, 0)[0] + 20);
+        assert!(linear.get_pixel(0, 0)[1] > srgb.get_pixel(0, 0)[1] + 20);
+
+        // 对比度减半：sRGB 中灰是支点保持不变，线性光下向线性 0.5（更亮）靠拢
+        let (srgb, linear) = (run(false, 0.5, 1.0), run(true, 0.5, 1.0));
+        assert_eq!(srgb.get_pixel(1, 0)[0], 128);
+        assert!(linear.get_pixel(1, 0)[0] > 140);
+    }
+
+    #[test]
+    fn color_profile_reports_embedded_icc_or_none() {
+        let img = image::RgbaImage::from_pixel(4, 4, image::Rgba([10, 20, 30, 255]));
+        let untagged = image_fetch_color_profile(encode(img.clone())).unwrap();
+        assert_eq!(untagged.format, "png");
+        assert_eq!(untagged.profile, "none");
+        assert_eq!(untagged.profile_size, 0);
+        assert!(untagged.color_space.is_none());
+
+        let icc = moxcms::ColorProfile::new_srgb().encode().unwrap();
+        let tagged = encode_with_icc(&img, icc.clone());
+
+        let info = image_fetch_color_profile(tagged).unwrap();
+        assert_ne!(info.profile, "none");
+        assert_eq!(info.profile_size, icc.len());
+        assert_eq!(info.color_space.as_deref(), Some("RGB"));
+    }
+
+    #[test]
+    fn srgb_conversion_changes_wide_gamut_and_keeps_srgb() {
+        let img = image::RgbaImage::from_fn(8, 8, |x, y| image::Rgba([x as u8 * 30, y as u8 * 30, 200, 255]));
+
+        let p3 = encode_with_icc(&img, moxcms::ColorProfile::new_display_p3().encode().unwrap());
+        let converted = image_format_srgb(p3.clone()).unwrap();
+        assert_ne!(converted, p3);
+        assert_ne!(decode(&converted), img);
+        assert_eq!(image_fetch_color_profile(converted).unwrap().profile, "none");
+
+        let srgb = encode_with_icc(&img, moxcms::ColorProfile::new_srgb().encode().unwrap());
+        assert_eq!(image_format_srgb(srgb.clone()).unwrap(), srgb);
+        let untagged = encode(img);
+        assert_eq!(image_format_srgb(untagged.clone()).unwrap(), untagged);
+    }
+
+    #[test]
+    fn downscale_keeps_orientation_and_capture_time() {
+        use image::ImageDecoder;
+
+        // 小端 TIFF：IFD0 含 Orientation = 6 和 Exif IFD 指针，Exif IFD 含 DateTimeOriginal
+        let mut tiff = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        tiff.extend_from_slice(&[0x69, 0x87, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0]);
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&[0x03, 0x90, 2, 0, 20, 0, 0, 0, 56, 0, 0, 0]);
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(b"2024:05:01 10:20:30\0");
+        let app1 = [b"Exif\0\0".as_slice(), &tiff].concat();
+
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2000, 400, |x, y| {
+            image::Rgba([(x / 8) as u8, (y / 2) as u8, 90, 255])
+        }));
+        let jpeg = image_encode_jpeg_with_exif(&img, 90, ChromaSubsampling::default(), Some(&app1)).unwrap();
+        let input = format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(&jpeg));
+
+        let output = image_format_downscale_exif(input, 1000).unwrap();
+        let bytes = image_fetch_base64_data(&output).unwrap();
+        let mut decoder = image::codecs::jpeg::JpegDecoder::new(std::io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(decoder.dimensions(), (1000, 200));
+
+        let exif = decoder.exif_metadata().unwrap().unwrap();
+        let tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(&exif);
+        let orientation = (0..u16::from_le_bytes([tiff[8], tiff[9]]) as usize)
+            .map(|i| &tiff[10 + i * 12..22 + i * 12])
+            .find(|entry| entry[0..2] == [0x12, 0x01])
+            .map(|entry| u16::from_le_bytes([entry[8], entry[9]]));
+        assert_eq!(orientation, Some(6));
+        assert_eq!(
+            exif_fetch_capture_time(&exif),
+            chrono::NaiveDateTime::parse_from_str("2024-05-01 10:20:30", "%Y-%m-%d %H:%M:%S").ok()
+        );
+    }
+
+    #[test]
+    fn thumbnails_match_each_requested_size() {
+        let img = image::RgbaImage::from_fn(400, 300, |x, y| image::Rgba([x as u8, y as u8, 60, 255]));
+        let data = encode(img);
+
+        let thumbs = image_format_thumbnails(data.clone(), vec![64, 256], true).unwrap();
+        let dims: Vec<_> = thumbs.iter().map(|t| decode(t).dimensions()).collect();
+        assert_eq!(dims, vec![(64, 48), (256, 192)]);
+
+        // 非固定比例时先居中裁剪为正方形；原图更小时不放大
+        let squares = image_format_thumbnails(data.clone(), vec![500, 100], false).unwrap();
+        let dims: Vec<_> = squares.iter().map(|t| decode(t).dimensions()).collect();
+        assert_eq!(dims, vec![(300, 300), (100, 100)]);
+
+        assert!(image_format_thumbnails(data.clone(), Vec::new(), true).is_err());
+        assert!(image_format_thumbnails(data, vec![64, 0], true).is_err());
+    }
+
+    #[test]
+    fn brightness_suggests_contrasting_text_color() {
+        let dark = image::RgbaImage::from_fn(100, 100, |x, _| {
+            if x < 90 { image::Rgba([5, 5, 5, 255]) } else { image::Rgba([255, 255, 255, 255]) }
+        });
+        let info = image_calc_brightness(encode(dark)).unwrap();
+        assert!(info.is_dark && info.mean_luma < 64.0, "{:?}", info);
+        assert_eq!(info.suggested_text_color, "#FFFFFF");
+
+        let bright = image::RgbaImage::from_pixel(100, 100, image::Rgba([240, 235, 220, 255]));
+        let info = image_calc_brightness(encode(bright)).unwrap();
+        assert!(!info.is_dark, "{:?}", info);
+        assert_eq!(info.suggested_text_color, "#000000");
+    }
+
+    #[test]
+    fn duotone_maps_black_and_white_to_gradient_ends() {
+        let img = image::RgbaImage::from_fn(3, 1, |x, _| {
+            let v = [0u8, 128, 255][x as usize];
+            image::Rgba([v, v, v, 255])
+        });
+        let toned = decode(&image_update_duotone(encode(img), "#102040".to_string(), "#F0E0A0".to_string()).unwrap());
+
+        assert_eq!(*toned.get_pixel(0, 0), image::Rgba([0x10, 0x20, 0x40, 255]));
+        assert_eq!(*toned.get_pixel(2, 0), image::Rgba([0xF0, 0xE0, 0xA0, 255]));
+        // 中灰落在两色中间
+        let mid = toned.get_pixel(1, 0);
+        for (c, (lo, hi)) in [(0x10, 0xF0), (0x20, 0xE0), (0x40, 0xA0)].into_iter().enumerate() {
+            let expected = (lo + hi) / 2;
+            assert!((mid[c] as i32 - expected).abs() <= 2, "{:?}", mid);
+        }
+
+        assert!(image_update_duotone(String::new(), "red".to_string(), "#FFFFFF".to_string()).is_err());
+    }
+
+    #[test]
+    fn crop_guides_are_largest_centered_rects() {
+        let guides = image_calc_crop_guides(1600, 900, vec![[1, 1], [16, 9], [9, 16]]).unwrap();
+        let rects: Vec<_> = guides.iter().map(|g| (g.ratio, g.x, g.y, g.width, g.height)).collect();
+        assert_eq!(rects[0], ([1, 1], 350, 0, 900, 900));
+        assert_eq!(rects[1], ([16, 9], 0, 0, 1600, 900));
+        let (_, x, y, width, height) = rects[2];
+        assert_eq!((y, height), (0, 900));
+        assert!((width as i32 - 506).abs() <= 1 && x == (1600 - width) / 2);
+
+        assert!(image_calc_crop_guides(0, 900, vec![[1, 1]]).is_err());
+        assert!(image_calc_crop_guides(1600, 900, vec![[0, 1]]).is_err());
+    }
+
+    #[test]
+    fn extreme_contrast_keeps_hue_order() {
+        let colors = [[230u8, 120, 40], [40, 200, 90], [60, 90, 220], [140, 128, 120]];
+        let img = image::RgbaImage::from_fn(colors.len() as u32, 1, |x, _| {
+            let [r, g, b] = colors[x as usize];
+            image::Rgba([r, g, b, 255])
+        });
+
+        for linear_light in [false, true] {
+            let mut out = img.clone();
+            let params = EnhanceParams { contrast: 10.0, brightness: 40, saturation: 1.5, linear_light: Some(linear_light) };
+            params.validate().unwrap();
+            pixel_update_enhance_params(&mut out, &params);
+
+            // 通道大小关系不能反转：原来最亮的通道仍然最亮，最暗的仍然最暗
+            for (x, color) in colors.iter().enumerate() {
+                let pixel = out.get_pixel(x as u32, 0);
+                let max = (0..3).max_by_key(|&c| color[c]).unwrap();
+                let min = (0..3).min_by_key(|&c| color[c]).unwrap();
+                assert!((0..3).all(|c| pixel[max] >= pixel[c] && pixel[min] <= pixel[c]), "{:?} -> {:?}", color, pixel);
+            }
+        }
+
+        let invalid = EnhanceParams { contrast: f32::NAN, brightness: 0, saturation: 1.0, linear_light: None };
+        assert!(invalid.validate().is_err());
+        let invalid = EnhanceParams { contrast: 1.0, brightness: 150, saturation: 1.0, linear_light: None };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn motion_blur_streaks_along_direction_only() {
+        let motion_blur = |img, distance, angle| tauri::async_runtime::block_on(image_update_motion_blur(img, distance, angle));
+        let black = image::Rgba([0, 0, 0, 255]);
+        let mut point = image::RgbaImage::from_pixel(41, 21, black);
+        point.put_pixel(20, 10, image::Rgba([255, 255, 255, 255]));
+
+        let blurred = decode(&motion_blur(encode(point.clone()), 10.0, 0.0).unwrap());
+        // 水平方向拉成一条线，竖直方向不扩散
+        assert!((16..=24).all(|x| blurred.get_pixel(x, 10)[0] > 0));
+        assert!(blurred.get_pixel(20, 10)[0] < 255);
+        assert_eq!(blurred.get_pixel(10, 10)[0], 0);
+        assert!((0..41).all(|x| blurred.get_pixel(x, 9)[0] == 0 && blurred.get_pixel(x, 11)[0] == 0));
+
+        // 水平边界在水平模糊下保持清晰
+        let edge = image::RgbaImage::from_fn(30, 20, |_, y| if y < 10 { image::Rgba([255, 255, 255, 255]) } else { black });
+        let blurred = decode(&motion_blur(encode(edge.clone()), 12.0, 0.0).unwrap());
+        assert_eq!(blurred, edge);
+
+        assert_eq!(decode(&motion_blur(encode(point.clone()), 0.0, 45.0).unwrap()), point);
+        assert!(motion_blur(encode(point), 1000.0, 0.0).is_err());
+
+        // 透明像素的颜色（黑色）不会渗入不透明的白色区域
+        let sprite = image::RgbaImage::from_fn(30, 5, |x, _| {
+            if (10..20).contains(&x) { image::Rgba([255, 255, 255, 255]) } else { image::Rgba([0, 0, 0, 0]) }
+        });
+        let blurred = decode(&motion_blur(encode(sprite), 8.0, 0.0).unwrap());
+        let edge = blurred.get_pixel(10, 2);
+        assert!(edge[3] > 0 && edge[3] < 255);
+        assert_eq!(&edge.0[..3], &[255, 255, 255]);
+        assert_eq!(blurred.get_pixel(0, 2)[3], 0);
+    }
+
+    #[test]
+    fn test_pattern_rejects_unbounded_sizes() {
+        let bars = decode(&image_render_test_pattern("colorbars".to_string(), 70, 10).unwrap());
+        assert_eq!(bars.dimensions(), (70, 10));
+        assert_eq!(bars.get_pixel(0, 0).0[..3], COLOR_BARS[0]);
+
+        assert!(image_render_test_pattern("checker".to_string(), 0, 10).is_err());
+        assert!(image_render_test_pattern("checker".to_string(), 100_000, 100_000).is_err());
+        assert!(image_render_test_pattern("checker".to_string(), u32::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn icc_description_ignores_oversized_tag_count() {
+        // 声明 0xFFFFFFFF 个标签但只有一条有效条目
+        let mut icc = vec![0u8; 132];
+        icc[128..132].copy_from_slice(&u32::MAX.to_be_bytes());
+        let tag_offset = (132 + 12) as u32;
+        let text = b"Test Profile\0";
+        let mut tag = b"desc\0\0\0\0".to_vec();
+        tag.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        tag.extend_from_slice(text);
+        icc.extend_from_slice(b"desc");
+        icc.extend_from_slice(&tag_offset.to_be_bytes());
+        icc.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        icc.extend_from_slice(&tag);
+        assert_eq!(icc_fetch_description(&icc).as_deref(), Some("Test Profile"));
+
+        // 标签表中没有 desc 时应立即返回 None
+        icc[132..136].copy_from_slice(b"wtpt");
+        let start = std::time::Instant::now();
+        assert_eq!(icc_fetch_description(&icc), None);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
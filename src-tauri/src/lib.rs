@@ -5,13 +5,55 @@ use tauri::{Manager, Emitter};
 use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
 use base64::{Engine as _, engine::general_purpose};
 use zip::ZipArchive;
+use rayon::prelude::*;
 use std::io::{Read, Write};
 
 mod image_processing;
+mod stroke_processing;
+mod text_processing;
 
 use image_processing::{
-    image_load_base64, image_fetch_base64_data,
-    image_update_rotation, image_update_adjustments,
+    image_load_base64, image_fetch_base64_data, image_encode_bytes,
+    image_encode_png_base64, image_encode_jpeg_with_metadata, ImageWithMetadata,
+    image_load_with_metadata, jpeg_update_orientation, ChromaSubsampling, pixel_blend_over,
+    image_update_rotation, image_update_adjustments, image_update_region,
+    image_update_invert, image_update_alpha_threshold,
+    image_update_pattern_fill, image_fetch_region_color,
+    image_format_mipmaps, image_fetch_pixel_color, image_format_smart_crop,
+    image_format_content_aware, image_calc_ssim,
+    image_update_chromatic_aberration, image_update_posterize,
+    image_update_rotation_angle, image_update_auto_enhance,
+    image_render_test_pattern, image_format_tiles, image_format_untile,
+    image_list_presets, image_update_preset, image_format_gif,
+    image_format_indexed_png, image_update_region_enhance,
+    image_calc_document_corners, image_format_dewarp, image_load_proxy,
+    image_update_proxy, image_delete_proxy, image_update_hue_range,
+    image_format_warp, image_update_replace_color, image_format_placeholder,
+    image_format_blurhash, image_format_smart_thumbnail,
+    image_fetch_color_profile, image_format_srgb, image_fetch_capture_time,
+    image_calc_background_color, color_calc_contrast_ratio, RgbaColor, image_fetch_proxy,
+    EnhanceParams, pixel_update_enhance_params,
+    image_format_downscale_exif, image_format_thumbnails,
+    image_calc_brightness, image_update_color_transfer,
+    image_update_duotone, image_calc_color_counts, image_calc_crop_guides,
+    image_format_pasted, image_format_letterbox,
+    image_update_enhance_thumbnail, image_update_motion_blur,
+    image_update_tilt_shift, image_update_stack_frames,
+    image_calc_ink_coverage, image_update_vignette_correction,
+};
+
+use stroke_processing::{
+    stroke_calc_bounds, stroke_detect_eraser_collision, stroke_calc_convex_hull,
+    stroke_format_simplify, stroke_calc_pressure_curve,
+    stroke_format_resolve_order, stroke_format_clip, stroke_calc_winding,
+    stroke_update_distance_field, stroke_format_transform_layers,
+    stroke_format_merge, stroke_calc_outline, stroke_detect_duplicates,
+    stroke_calc_stats, stroke_format_normalize, stroke_format_denormalize,
+    stroke_calc_min_area_rect,
+};
+
+use text_processing::{
+    text_render_image, text_calc_metrics,
 };
 
 #[cfg(target_os = "windows")]
@@ -31,6 +73,8 @@ const MEMREDUCT_CLEAN_COOLDOWN_SECS: u64 = 600;
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use once_cell::sync::Lazy;
 
 #[cfg(target_os = "windows")]
 use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
@@ -46,6 +90,48 @@ pub struct ImageSaveResult {
     pub enhanced_data: Option<String>,
 }
 
+/// ZIP 导出进度事件 "zip-export-progress" 的负载
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZipExportProgress {
+    pub current: usize,
+    pub total: usize,
+}
+
+/// 摄像头支持的分辨率
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraResolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 画布上的矩形区域（像素）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DirtyRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 局部重绘结果：区域图像及其在画布中的位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactRegionResult {
+    pub image_data: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 推荐的画布渲染分辨率
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderResolution {
+    pub width: u32,
+    pub height: u32,
+    /// 实际采用的设备像素比（已按 dprLimit 限制）
+    pub dpr: f64,
+}
+
 /// 笔画中的单条线段
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrokePoint {
@@ -64,15 +150,37 @@ pub struct Stroke {
     pub color: Option<String>,
     pub line_width: Option<u32>,
     pub eraser_size: Option<u32>,
+    /// 橡皮形状："round"（默认）或 "square"
+    pub eraser_shape: Option<String>,
+    /// 文字笔画内容（type 为 "text" 时使用），"\n" 分行
+    pub text: Option<String>,
+    /// 文字笔画字号（像素），默认 24
+    pub font_size: Option<f32>,
+    /// 文字笔画左上角位置
+    pub position: Option<PathPoint>,
 }
 
 /// 笔画压缩请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompactStrokesRequest {
     pub base_image: Option<String>,
+    /// 笔画数组；stroke_load_stream 的笔画随后分批送入，此处可省略
+    #[serde(default)]
     pub strokes: Vec<Stroke>,
     pub canvas_width: u32,
     pub canvas_height: u32,
+    /// 擦除语义："transparent"（默认）擦除为透明；"base" 擦除时恢复底图像素
+    pub erase_mode: Option<String>,
+    /// 设备像素比：画布尺寸和笔画按 CSS 像素给出时，渲染前统一乘以该值（受 dprLimit 限制），
+    /// 底图按自身像素尺寸合成，不缩放
+    pub dpr: Option<f32>,
+    /// 已缓存的底图代理 ID，指定时代替 base_image，避免连续压缩反复解码同一张底图
+    ///
+    /// 生命周期：前端用 image_load_proxy 加载底图（max_size 不小于底图最长边，保持原分辨率）得到 ID，
+    /// 之后的压缩请求可并发共用；底图不再使用时调用 image_delete_proxy 释放。
+    /// 代理按最近使用时间淘汰，每次压缩都会刷新底图代理的使用时间，持续使用时不会被其他预览代理挤出。
+    /// 释放或被淘汰时，已开始的压缩仍持有底图引用，不受影响；之后使用该 ID 的请求返回错误
+    pub base_proxy: Option<u64>,
 }
 
 // ==================== 系统目录 ====================
@@ -818,166 +926,998 @@ fn string_format_prefix(prefix: &str) -> String {
     if sanitized.is_empty() { "photo".to_string() } else { sanitized }
 }
 
+/// 保存进度事件的写入分块大小
+const SAVE_PROGRESS_CHUNK_SIZE: usize = 1 << 20;
+
+/// 分块写入文件，每写完一块按已写入比例回调一次，最后一次为 1.0
+fn file_write_with_progress(path: &std::path::Path, data: &[u8], report: impl Fn(f32)) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    let mut written = 0;
+    for chunk in data.chunks(SAVE_PROGRESS_CHUNK_SIZE) {
+        file.write_all(chunk)?;
+        written += chunk.len();
+        report(written as f32 / data.len() as f32);
+    }
+    file.flush()?;
+    if data.is_empty() {
+        report(1.0);
+    }
+    Ok(())
+}
+
+/// JPEG 保存时的重新编码质量
+const SAVE_JPEG_QUALITY: u8 = 95;
+
+/// 确保 JPEG 使用指定的色度抽样：已是该抽样时原样返回，避免二次压缩；
+/// 否则重新编码，并写回原图的 EXIF 和 ICC 配置文件
+fn jpeg_format_subsampling(jpeg: Vec<u8>, subsampling: ChromaSubsampling) -> Result<Vec<u8>, String> {
+    if ChromaSubsampling::detect(&jpeg) == Some(subsampling) {
+        return Ok(jpeg);
+    }
+    let ImageWithMetadata { image: img, exif, icc } = image_load_with_metadata(&jpeg)?;
+    image_encode_jpeg_with_metadata(&img, SAVE_JPEG_QUALITY, subsampling, exif.as_deref(), icc.as_deref())
+}
+
 /// Tauri IPC 命令：将 base64 编码的图片保存到 ~/Pictures/ViewStage
 ///
+/// 在后台线程中执行，并推送 "save-progress" 事件（0.0 ~ 1.0）：开始时为 0.0，
+/// 之后按已写入字节占文件大小的比例分块推送，写入完成为 1.0。
+/// 解码和重新编码无法得知进度，期间不推送事件，前端应显示为处理中
+///
 /// # 参数
 /// * `image_data` — 含 data:image 前缀的 base64 图片数据
 /// * `prefix` — 文件名前缀，为空则使用 "photo"
+/// * `chroma_subsampling` — JPEG 色度抽样（"4:4:4"/"4:2:2"/"4:2:0"），默认 4:2:0；
+///   JPEG 已是目标抽样时原样写入，否则以 95 质量重新编码并保留 EXIF 和 ICC 配置文件
 ///
 /// # 返回值
 /// * `Ok(ImageSaveResult)` — 包含保存路径及成功状态的保存结果
 ///
 /// # 异常
 /// * base64 解码失败
+/// * 色度抽样参数非法或重新编码失败
 /// * 目录创建失败
 /// * 文件写入失败
 #[tauri::command]
-fn image_save_file(image_data: String, prefix: Option<String>) -> Result<ImageSaveResult, String> {
-    let base_dir = dir_fetch_pictures_viewstage()?;
-    let prefix_str = string_format_prefix(&prefix.unwrap_or_else(|| "photo".to_string()));
+async fn image_save_file(
+    app: tauri::AppHandle,
+    image_data: String,
+    prefix: Option<String>,
+    chroma_subsampling: Option<String>,
+) -> Result<ImageSaveResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = |progress: f32| {
+            let _ = app.emit("save-progress", progress);
+        };
+        report(0.0);
 
-    let decoded = image_fetch_base64_data(&image_data)?;
+        let base_dir = dir_fetch_pictures_viewstage()?;
+        let prefix_str = string_format_prefix(&prefix.unwrap_or_else(|| "photo".to_string()));
 
-    let extension = if image_data.contains("image/png") {
-        "png"
-    } else if image_data.contains("image/jpeg") || image_data.contains("image/jpg") {
-        "jpg"
-    } else {
-        "png"
-    };
+        let mut decoded = image_fetch_base64_data(&image_data)?;
 
-    let (file_path, _file_name) = path_calc_save(&base_dir, &prefix_str, extension)?;
-    
-    std::fs::write(&file_path, &decoded)
-        .map_err(|e| format!("Failed to write image file: {}", e))?;
-    
-    Ok(ImageSaveResult {
-        path: file_path.to_string_lossy().to_string(),
-        success: true,
-        error: None,
-        enhanced_data: None,
+        // 只看 data URL 头部的 MIME 类型，不扫描整段 base64
+        let header = image_data.split_once(',').map(|(header, _)| header).unwrap_or("");
+        let extension = if header.contains("image/jpeg") || header.contains("image/jpg") {
+            "jpg"
+        } else {
+            "png"
+        };
+
+        if extension == "jpg" {
+            let subsampling = ChromaSubsampling::parse(chroma_subsampling.as_deref())?;
+            decoded = jpeg_format_subsampling(decoded, subsampling)?;
+        }
+
+        let (file_path, _file_name) = path_calc_save(&base_dir, &prefix_str, extension)?;
+
+        file_write_with_progress(&file_path, &decoded, report)
+            .map_err(|e| format!("Failed to write image file: {}", e))?;
+
+        Ok(ImageSaveResult {
+            path: file_path.to_string_lossy().to_string(),
+            success: true,
+            error: None,
+            enhanced_data: None,
+        })
     })
-}
+    .await
+    .map_err(|e| format!("Save task failed: {}", e))?
+}
 
-// ==================== 笔画压缩 ====================
+/// 整理文件名模板支持的占位符
+const ORGANIZE_PLACEHOLDERS: [&str; 3] = ["{date}", "{time}", "{index}"];
 
-/// 解析 #RRGGBB 或 #RRGGBBAA 格式颜色字符串为 RGBA
-fn color_calc_from_hex(color_str: &str) -> Result<Rgba<u8>, String> {
-    if !color_str.starts_with('#') {
-        return Err(format!("Invalid color format: must start with '#', got: {}", color_str));
-    }
-    
-    match color_str.len() {
-        7 => {
-            let r = u8::from_str_radix(&color_str[1..3], 16)
-                .map_err(|_| format!("Invalid red component in color: {}", color_str))?;
-            let g = u8::from_str_radix(&color_str[3..5], 16)
-                .map_err(|_| format!("Invalid green component in color: {}", color_str))?;
-            let b = u8::from_str_radix(&color_str[5..7], 16)
-                .map_err(|_| format!("Invalid blue component in color: {}", color_str))?;
-            Ok(Rgba([r, g, b, 255]))
-        }
-        9 => {
-            let r = u8::from_str_radix(&color_str[1..3], 16)
-                .map_err(|_| format!("Invalid red component in color: {}", color_str))?;
-            let g = u8::from_str_radix(&color_str[3..5], 16)
-                .map_err(|_| format!("Invalid green component in color: {}", color_str))?;
-            let b = u8::from_str_radix(&color_str[5..7], 16)
-                .map_err(|_| format!("Invalid blue component in color: {}", color_str))?;
-            let a = u8::from_str_radix(&color_str[7..9], 16)
-                .map_err(|_| format!("Invalid alpha component in color: {}", color_str))?;
-            Ok(Rgba([r, g, b, a]))
+/// 批量整理时处理的图片扩展名（小写）
+const SAVED_IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "webp", "bmp", "gif"];
+
+/// 递归收集目录下的图片文件
+///
+/// 按目录项自身类型判断，不跟随符号链接：指向上级目录的链接不会导致无限递归，
+/// 也不会经由链接收集到目录之外的文件
+fn file_collect_images(dir: &std::path::Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            file_collect_images(&path, files)?;
+        } else if file_type.is_file() && path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SAVED_IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        {
+            files.push(path);
         }
-        _ => Err(format!("Invalid color format: expected #RRGGBB or #RRGGBBAA, got: {}", color_str))
     }
+
+    Ok(())
 }
 
-const DEFAULT_COLOR: Rgba<u8> = Rgba([52, 152, 219, 255]);
+/// 按模板重命名一个目录中的图片，返回重命名的文件数
+///
+/// 文件按拍摄时间（EXIF，缺失时取修改时间）排序后编号；
+/// 目标文件名已被占用时追加 _1、_2 等后缀，不会覆盖已有文件。源文件或目标所在目录规范化后不在 root 内时跳过
+fn file_update_names_in_dir(files: Vec<PathBuf>, pattern: &str, root: &std::path::Path) -> usize {
+    let inside_root = |path: &std::path::Path| {
+        std::fs::canonicalize(path).is_ok_and(|canonical| canonical.starts_with(root))
+    };
 
-/// 在画布上用 Bresenham 算法绘制圆形笔触线段
-fn canvas_render_line(canvas: &mut RgbaImage, x1: i32, y1: i32, x2: i32, y2: i32, color: Rgba<u8>, width: u32) {
-    let dx = (x2 - x1).abs();
-    let dy = (y2 - y1).abs();
-    let sx = if x1 < x2 { 1 } else { -1 };
-    let sy = if y1 < y2 { 1 } else { -1 };
-    let mut err = dx - dy;
-    let mut x = x1;
-    let mut y = y1;
-    
-    let half_width = (width / 2) as i32;
-    
-    loop {
-        for wx in -half_width..=half_width {
-            for wy in -half_width..=half_width {
-                let px = x + wx;
-                let py = y + wy;
-                if px >= 0 && py >= 0 && (px as u32) < canvas.width() && (py as u32) < canvas.height() {
-                    let dist = ((wx * wx + wy * wy) as f32).sqrt();
-                    if dist <= half_width as f32 {
-                        let pixel = canvas.get_pixel_mut(px as u32, py as u32);
-                        if color[3] == 255 {
-                            *pixel = color;
-                        } else {
-                            let alpha = color[3] as f32 / 255.0;
-                            let inv_alpha = 1.0 - alpha;
-                            pixel[0] = (color[0] as f32 * alpha + pixel[0] as f32 * inv_alpha) as u8;
-                            pixel[1] = (color[1] as f32 * alpha + pixel[1] as f32 * inv_alpha) as u8;
-                            pixel[2] = (color[2] as f32 * alpha + pixel[2] as f32 * inv_alpha) as u8;
-                        }
-                    }
-                }
-            }
-        }
-        
-        if x == x2 && y == y2 {
-            break;
+    let mut dated: Vec<(chrono::NaiveDateTime, PathBuf)> = files.into_iter()
+        .map(|path| {
+            let time = image_fetch_capture_time(&path).unwrap_or_else(|| {
+                std::fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .map(|modified| chrono::DateTime::<chrono::Local>::from(modified).naive_local())
+                    .unwrap_or_default()
+            });
+            (time, path)
+        })
+        .collect();
+    dated.sort();
+
+    let mut renamed = 0;
+    for (index, (time, path)) in dated.iter().enumerate() {
+        let (Some(dir), Some(extension)) = (path.parent(), path.extension().and_then(|ext| ext.to_str())) else {
+            continue;
+        };
+        let name = pattern
+            .replace("{date}", &time.format("%Y%m%d").to_string())
+            .replace("{time}", &time.format("%H%M%S").to_string())
+            .replace("{index}", &format!("{:03}", index + 1));
+        let stem = string_format_prefix(&name);
+        let extension = extension.to_ascii_lowercase();
+
+        if !inside_root(path) || !inside_root(dir) {
+            log::warn!("跳过保存目录之外的文件: {}", path.display());
+            continue;
         }
-        
-        let e2 = 2 * err;
-        if e2 > -dy {
-            err -= dy;
-            x += sx;
+
+        // 先以 create_new 占用目标文件名，再 rename 覆盖自己创建的占位文件，
+        // 目标名称在检查和重命名之间被其他文件占用时也不会覆盖它
+        let claimed = file_claim_unique_name(dir, &stem, &extension, |target| {
+            if target == path.as_path() {
+                return Ok(false);
+            }
+            std::fs::OpenOptions::new().write(true).create_new(true).open(target).map(|_| true)
+        });
+        match claimed {
+            Ok((_, false)) => {}
+            Ok((target, true)) => match std::fs::rename(path, &target) {
+                Ok(()) => renamed += 1,
+                Err(e) => {
+                    let _ = std::fs::remove_file(&target);
+                    log::warn!("重命名图片失败 {}: {}", path.display(), e);
+                }
+            },
+            Err(e) => log::warn!("重命名图片失败 {}: {}", path.display(), e),
         }
-        if e2 < dx {
-            err += dx;
-            y += sy;
+    }
+
+    renamed
+}
+
+/// Tauri IPC 命令：按模板批量重命名 ~/Pictures/ViewStage 中已保存的图片
+///
+/// 模板占位符：{date}（YYYYMMDD）、{time}（HHMMSS）、{index}（目录内按时间排序的序号，三位）。
+/// 时间取 EXIF 拍摄时间，缺失时取文件修改时间；文件只在所在目录内改名，
+/// 替换后的文件名只保留字母数字下划线和中划线，重名时追加计数后缀。
+/// 符号链接被跳过，改名前确认源文件和目标目录都位于保存目录内
+///
+/// # 参数
+/// * `pattern` — 文件名模板（不含扩展名），如 "{date}_{index}"
+///
+/// # 返回值
+/// * `Ok(usize)` — 实际重命名的文件数
+///
+/// # 异常
+/// * 模板不含任何占位符
+/// * 保存目录读取失败
+#[tauri::command]
+async fn image_update_file_names(pattern: String) -> Result<usize, String> {
+    if !ORGANIZE_PLACEHOLDERS.iter().any(|placeholder| pattern.contains(placeholder)) {
+        return Err(format!(
+            "Pattern must contain at least one of {}",
+            ORGANIZE_PLACEHOLDERS.join(", ")
+        ));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let base_dir = dir_fetch_pictures_viewstage()?;
+        let base_canonical = std::fs::canonicalize(&base_dir)
+            .map_err(|e| format!("Failed to resolve ViewStage dir: {}", e))?;
+        let mut files = Vec::new();
+        file_collect_images(std::path::Path::new(&base_dir), &mut files)?;
+
+        let mut by_dir: std::collections::BTreeMap<PathBuf, Vec<PathBuf>> = std::collections::BTreeMap::new();
+        for file in files {
+            let dir = file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+            by_dir.entry(dir).or_default().push(file);
         }
+
+        let renamed: usize = by_dir.into_values()
+            .map(|files| file_update_names_in_dir(files, &pattern, &base_canonical))
+            .sum();
+        log::info!("已按模板 {} 重命名 {} 张图片", pattern, renamed);
+        Ok(renamed)
+    })
+    .await
+    .map_err(|e| format!("Rename task failed: {}", e))?
+}
+
+/// 某一天保存的图片占用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DateUsage {
+    /// 日期（YYYY-MM-DD）
+    pub date: String,
+    pub bytes: u64,
+    pub count: usize,
+}
+
+/// 图片保存目录的磁盘占用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub total_bytes: u64,
+    pub file_count: usize,
+    /// 按日期升序排列的分日占用
+    pub by_date: Vec<DateUsage>,
+}
+
+/// Tauri IPC 命令：统计 ~/Pictures/ViewStage 中已保存图片的磁盘占用，用于存储空间提示
+///
+/// 递归遍历保存目录；日期取所在日期子目录名（YYYY-MM-DD），
+/// 不在日期子目录中的图片按文件修改日期归类
+///
+/// # 返回值
+/// * `Ok(StorageUsage)` — 总字节数、文件数及按日期的明细
+///
+/// # 异常
+/// * 保存目录读取失败
+#[tauri::command]
+async fn dir_fetch_pictures_usage() -> Result<StorageUsage, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let base_dir = PathBuf::from(dir_fetch_pictures_viewstage()?);
+        dir_calc_usage(&base_dir)
+    })
+    .await
+    .map_err(|e| format!("Storage usage task failed: {}", e))?
+}
+
+/// 递归统计 base_dir 中图片的总占用及按日期的明细，日期归类规则见 dir_fetch_pictures_usage
+fn dir_calc_usage(base_dir: &std::path::Path) -> Result<StorageUsage, String> {
+    let mut files = Vec::new();
+    file_collect_images(base_dir, &mut files)?;
+
+    let mut by_date: std::collections::BTreeMap<String, DateUsage> = std::collections::BTreeMap::new();
+    let mut usage = StorageUsage { total_bytes: 0, file_count: 0, by_date: Vec::new() };
+
+    for file in files {
+        let Ok(metadata) = std::fs::metadata(&file) else {
+            continue;
+        };
+        let folder_date = file.strip_prefix(base_dir).ok()
+            .and_then(|relative| relative.components().next())
+            .and_then(|component| component.as_os_str().to_str())
+            .filter(|name| chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").is_ok())
+            .map(|name| name.to_string());
+        let date = folder_date.unwrap_or_else(|| {
+            metadata.modified()
+                .map(|modified| chrono::DateTime::<chrono::Local>::from(modified).format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|_| "unknown".to_string())
+        });
+
+        let entry = by_date.entry(date.clone())
+            .or_insert_with(|| DateUsage { date, bytes: 0, count: 0 });
+        entry.bytes += metadata.len();
+        entry.count += 1;
+        usage.total_bytes += metadata.len();
+        usage.file_count += 1;
     }
+
+    usage.by_date = by_date.into_values().collect();
+    Ok(usage)
 }
 
-/// 在画布上用 Bresenham 算法擦除圆形区域（设置 alpha=0）
-fn canvas_delete_line(canvas: &mut RgbaImage, x1: i32, y1: i32, x2: i32, y2: i32, width: u32) {
-    let dx = (x2 - x1).abs();
-    let dy = (y2 - y1).abs();
-    let sx = if x1 < x2 { 1 } else { -1 };
-    let sy = if y1 < y2 { 1 } else { -1 };
-    let mut err = dx - dy;
-    let mut x = x1;
-    let mut y = y1;
-    
-    let half_width = (width / 2) as i32;
-    
-    loop {
-        for wx in -half_width..=half_width {
-            for wy in -half_width..=half_width {
-                let px = x + wx;
-                let py = y + wy;
-                if px >= 0 && py >= 0 && (px as u32) < canvas.width() && (py as u32) < canvas.height() {
-                    let dist = ((wx * wx + wy * wy) as f32).sqrt();
-                    if dist <= half_width as f32 {
-                        let pixel = canvas.get_pixel_mut(px as u32, py as u32);
-                        pixel[3] = 0;
-                    }
-                }
+/// 删除或移入系统回收站 base_dir 中日期早于 cutoff 的 YYYY-MM-DD 子目录里的图片，返回处理的图片数
+///
+/// 每个文件都会规范化路径并确认位于 base_dir 内，指向目录外的符号链接会被跳过。
+/// 处理后为空的日期目录一并删除
+fn file_delete_old_images(base_dir: &std::path::Path, cutoff: chrono::NaiveDate, to_trash: bool) -> Result<usize, String> {
+    let base_canonical = std::fs::canonicalize(base_dir)
+        .map_err(|e| format!("Failed to resolve ViewStage dir: {}", e))?;
+
+    let entries = std::fs::read_dir(base_dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", base_dir.display(), e))?;
+    let mut old_dirs: Vec<PathBuf> = entries.flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter(|entry| {
+            entry.file_name().to_str()
+                .and_then(|name| chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").ok())
+                .is_some_and(|date| date < cutoff)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    old_dirs.sort();
+
+    let mut removed = 0;
+    for dir in old_dirs {
+        let mut files = Vec::new();
+        file_collect_images(&dir, &mut files)?;
+
+        for file in files {
+            let inside_root = std::fs::canonicalize(&file)
+                .is_ok_and(|canonical| canonical.starts_with(&base_canonical));
+            if !inside_root {
+                log::warn!("跳过保存目录之外的文件: {}", file.display());
+                continue;
+            }
+
+            let result = if to_trash {
+                trash::delete(&file).map_err(|e| e.to_string())
+            } else {
+                std::fs::remove_file(&file).map_err(|e| e.to_string())
+            };
+
+            match result {
+                Ok(()) => removed += 1,
+                Err(e) => log::warn!("清理图片失败 {}: {}", file.display(), e),
             }
         }
-        
-        if x == x2 && y == y2 {
-            break;
+
+        // 只删除已经清空的目录，里面还有非图片文件时保留
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    Ok(removed)
+}
+
+/// Tauri IPC 命令：清理 ~/Pictures/ViewStage 中早于指定天数的日期子目录里的图片
+///
+/// 只处理名为 YYYY-MM-DD 的一级子目录，日期早于（今天 - older_than_days）的目录中的图片
+/// 被删除或移入系统回收站，同名文件由回收站自行区分，可从回收站原样还原；
+/// 每个文件都会规范化路径并确认位于保存目录内，指向目录外的符号链接会被跳过。
+/// 处理后为空的日期目录一并删除
+///
+/// # 参数
+/// * `older_than_days` — 保留最近多少天的图片，必须大于 0
+/// * `to_trash` — true 时移入系统回收站，false 时直接删除
+///
+/// # 返回值
+/// * `Ok(usize)` — 删除或移入回收站的图片数
+///
+/// # 异常
+/// * 天数为 0
+/// * 保存目录无法访问
+#[tauri::command]
+async fn image_delete_old_files(older_than_days: u32, to_trash: bool) -> Result<usize, String> {
+    if older_than_days == 0 {
+        return Err("older_than_days must be greater than zero".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let base_dir = PathBuf::from(dir_fetch_pictures_viewstage()?);
+        let cutoff = chrono::Local::now().date_naive() - chrono::Duration::days(older_than_days as i64);
+        let removed = file_delete_old_images(&base_dir, cutoff, to_trash)?;
+
+        log::info!("已清理 {} 天前的图片 {} 张（{}）", older_than_days, removed, if to_trash { "移入回收站" } else { "删除" });
+        Ok(removed)
+    })
+    .await
+    .map_err(|e| format!("Purge task failed: {}", e))?
+}
+
+/// 校验文件路径位于 ~/Pictures/ViewStage 目录内，返回规范化后的路径
+fn path_validate_pictures(path: &str) -> Result<PathBuf, String> {
+    let base_dir = dir_fetch_pictures_viewstage()?;
+    let base_canonical = std::fs::canonicalize(&base_dir)
+        .map_err(|e| format!("Failed to resolve ViewStage dir: {}", e))?;
+    let file_canonical = std::fs::canonicalize(path)
+        .map_err(|_| format!("File not found: {}", path))?;
+
+    if !file_canonical.starts_with(&base_canonical) {
+        return Err("Path is outside the ViewStage directory".to_string());
+    }
+    if !file_canonical.is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+
+    Ok(file_canonical)
+}
+
+/// Tauri IPC 命令：原地旋转已保存的图片文件
+///
+/// JPEG 优先改写 EXIF 方向标签实现无损旋转（见 jpeg_update_orientation）；
+/// EXIF 中缺少方向标签的 JPEG 及其他格式解码旋转后按原格式重新编码（JPEG 质量 95），
+/// 并保留原有的 EXIF 和 ICC 配置文件。先写临时文件再 rename 覆盖原文件。
+///
+/// # 参数
+/// * `path` — 图片文件路径，必须位于 ~/Pictures/ViewStage 内
+/// * `direction` — 旋转方向，"left" 为逆时针 90 度，"right" 为顺时针 90 度
+///
+/// # 异常
+/// * 旋转方向不是 "left" 或 "right"
+/// * 路径不在 ViewStage 目录内或文件不存在
+/// * 图像格式不支持或解码失败
+/// * 编码或写入失败
+#[tauri::command]
+async fn image_update_file_rotation(path: String, direction: String) -> Result<(), String> {
+    let clockwise = match direction.as_str() {
+        "left" => false,
+        "right" => true,
+        _ => return Err(format!("Invalid rotation direction: {} (expected left or right)", direction)),
+    };
+    let file_path = path_validate_pictures(&path)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        file_update_rotation(&file_path, clockwise)?;
+        log::info!("已原地旋转图片: {:?} ({})", file_path, direction);
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Rotate task failed: {}", e))?
+}
+
+/// 原地旋转图片文件 90 度，规则见 image_update_file_rotation；调用方负责校验路径
+fn file_update_rotation(file_path: &std::path::Path, clockwise: bool) -> Result<(), String> {
+    let bytes = std::fs::read(file_path)
+        .map_err(|e| format!("Failed to read image file: {}", e))?;
+    let format = image::guess_format(&bytes)
+        .map_err(|e| format!("Unsupported image format: {}", e))?;
+
+    let lossless = (format == image::ImageFormat::Jpeg)
+        .then(|| jpeg_update_orientation(&bytes, clockwise))
+        .flatten();
+    let encoded = match lossless {
+        Some(rotated) => rotated,
+        None => {
+            let ImageWithMetadata { image: img, exif, icc } = image_load_with_metadata(&bytes)?;
+            let rotated = if clockwise { img.rotate90() } else { img.rotate270() };
+
+            if format == image::ImageFormat::Jpeg {
+                image_encode_jpeg_with_metadata(&rotated, 95, ChromaSubsampling::default(), exif.as_deref(), icc.as_deref())?
+            } else {
+                image_encode_bytes(&rotated, format, 95)?
+            }
         }
-        
-        let e2 = 2 * err;
-        if e2 > -dy {
+    };
+
+    let temp_path = file_path.with_extension("rotating.tmp");
+    std::fs::write(&temp_path, &encoded)
+        .map_err(|e| format!("Failed to write image file: {}", e))?;
+    std::fs::rename(&temp_path, file_path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        format!("Failed to replace image file: {}", e)
+    })?;
+    Ok(())
+}
+
+/// 将图片依次重新编码并以 Stored 方式写入 ZIP，每写入一张回调一次 (已写入数, 总数)
+fn zip_write_images(
+    file: std::fs::File,
+    images: &[String],
+    image_format: image::ImageFormat,
+    extension: &str,
+    quality: u8,
+    report: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored);
+    let total = images.len();
+
+    for (index, image_data) in images.iter().enumerate() {
+        let img = image_load_base64(image_data)?;
+        let encoded = image_encode_bytes(&img, image_format, quality)?;
+
+        writer.start_file(format!("image_{:03}.{}", index + 1, extension), options)
+            .map_err(|e| format!("Failed to write zip entry: {}", e))?;
+        writer.write_all(&encoded)
+            .map_err(|e| format!("Failed to write zip entry: {}", e))?;
+
+        report(index + 1, total);
+    }
+
+    writer.finish()
+        .map_err(|e| format!("Failed to finish zip file: {}", e))?;
+    Ok(total)
+}
+
+/// Tauri IPC 命令：将多张图片重新编码后打包导出为 ZIP
+///
+/// 条目按顺序命名为 image_001.{ext}、image_002.{ext}……；图片本身已压缩，
+/// 条目以 Stored 方式写入。每写入一张推送一次 "zip-export-progress" 事件。
+/// 先写入输出路径旁的临时文件，全部成功后才替换为 out_path；
+/// 任一图片失败时只删除临时文件，out_path 处已有的文件保持不变。
+///
+/// # 参数
+/// * `images` — base64 编码的图片数据数组
+/// * `out_path` — ZIP 文件输出路径，必须为绝对路径且所在目录已存在
+/// * `format` — 条目图片格式（扩展名，如 "png"/"jpg"/"webp"）
+/// * `quality` — JPEG 质量，默认 90
+///
+/// # 返回值
+/// * `Ok(usize)` — 写入的图片数量
+///
+/// # 异常
+/// * 格式不支持
+/// * 输出路径不是绝对路径或所在目录不存在
+/// * 图片解码或编码失败
+/// * ZIP 文件创建或写入失败
+#[tauri::command]
+async fn image_export_zip(
+    app: tauri::AppHandle,
+    images: Vec<String>,
+    out_path: String,
+    format: String,
+    quality: Option<u8>,
+) -> Result<usize, String> {
+    let image_format = image::ImageFormat::from_extension(format.trim_start_matches('.'))
+        .filter(|f| f.can_write())
+        .ok_or_else(|| format!("Unsupported export format: {}", format))?;
+    let extension = image_format.extensions_str().first().copied().unwrap_or("png");
+    let quality = quality.unwrap_or(90).clamp(1, 100);
+    let out_path = path_validate_output(&out_path)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = |current: usize, total: usize| {
+            let _ = app.emit("zip-export-progress", ZipExportProgress { current, total });
+        };
+
+        match zip_export_file(&out_path, &images, image_format, extension, quality, report) {
+            Ok(count) => {
+                log::info!("已导出 {} 张图片到 ZIP: {:?}", count, out_path);
+                Ok(count)
+            }
+            Err(e) => {
+                log::warn!("导出 ZIP 失败: {}", e);
+                Err(e)
+            }
+        }
+    })
+    .await
+    .map_err(|e| format!("Zip export task failed: {}", e))?
+}
+
+/// 校验前端传入的输出文件路径：必须为绝对路径，且所在目录已存在
+fn path_validate_output(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(format!("Output path must be absolute: {}", path.display()));
+    }
+    if path.file_name().is_none() {
+        return Err(format!("Output path has no file name: {}", path.display()));
+    }
+    if !path.parent().is_some_and(|parent| parent.is_dir()) {
+        return Err(format!("Output directory does not exist: {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// 将图片写入 out_path 旁的临时 ZIP，成功后重命名为 out_path；失败时只删除本次创建的临时文件
+fn zip_export_file(
+    out_path: &std::path::Path,
+    images: &[String],
+    image_format: image::ImageFormat,
+    extension: &str,
+    quality: u8,
+    report: impl Fn(usize, usize),
+) -> Result<usize, String> {
+    let mut temp_name = out_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".exporting.tmp");
+    let temp_path = out_path.with_file_name(temp_name);
+
+    // create_new：同名临时文件已存在（如另一次导出正在进行）时放弃，不覆盖
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(|e| format!("Failed to create zip file: {}", e))?;
+
+    let count = zip_write_images(file, images, image_format, extension, quality, report)
+        .and_then(|count| {
+            std::fs::rename(&temp_path, out_path)
+                .map_err(|e| format!("Failed to move zip file into place: {}", e))?;
+            Ok(count)
+        });
+    if count.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    count
+}
+
+/// 校验路径位于 ViewStage 目录内后转换单个图片文件，返回输出路径
+fn image_convert_single_file(path: &str, format: image::ImageFormat, extension: &str, quality: u8, delete_original: bool) -> Result<PathBuf, String> {
+    let source_path = path_validate_pictures(path)?;
+    file_convert_format(&source_path, format, extension, quality, delete_original)
+}
+
+/// 将图片文件转换为目标格式并写在原文件旁，返回输出路径；调用方负责校验路径
+fn file_convert_format(
+    source_path: &std::path::Path,
+    format: image::ImageFormat,
+    extension: &str,
+    quality: u8,
+    delete_original: bool,
+) -> Result<PathBuf, String> {
+    let output_path = source_path.with_extension(extension);
+    if output_path == source_path {
+        return Err(format!("Already in target format: {}", source_path.display()));
+    }
+
+    let img = image::open(source_path)
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+    let encoded = image_encode_bytes(&img, format, quality)?;
+
+    // 临时文件名包含源扩展名，a.png 与 a.webp 并行转换时不会互相覆盖
+    let source_extension = source_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let temp_path = output_path.with_extension(format!("{}.{}.converting.tmp", source_extension, extension));
+    std::fs::write(&temp_path, &encoded)
+        .map_err(|e| format!("Failed to write image file: {}", e))?;
+
+    // 以 create_new 原子地占用目标文件名：目标已存在（或被并行转换的同名文件抢先占用）时放弃，不覆盖
+    if let Err(e) = std::fs::OpenOptions::new().write(true).create_new(true).open(&output_path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(if e.kind() == std::io::ErrorKind::AlreadyExists {
+            format!("Target file already exists: {}", output_path.display())
+        } else {
+            format!("Failed to write image file: {}", e)
+        });
+    }
+    std::fs::rename(&temp_path, &output_path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        let _ = std::fs::remove_file(&output_path);
+        format!("Failed to write image file: {}", e)
+    })?;
+
+    if delete_original {
+        std::fs::remove_file(source_path)
+            .map_err(|e| format!("Converted but failed to delete original: {}", e))?;
+    }
+
+    Ok(output_path)
+}
+
+/// Tauri IPC 命令：批量转换图片文件格式（如整个文件夹迁移到 WebP）
+///
+/// 并行处理，转换结果写在原文件旁（同名不同扩展名），
+/// 仅在新文件写入成功后才删除原文件；目标文件已存在时该文件转换失败，不会覆盖已有文件，原文件也保留；
+/// 单个文件失败不影响其他文件
+///
+/// # 参数
+/// * `paths` — 图片文件路径，必须位于 ~/Pictures/ViewStage 内
+/// * `format` — 目标格式（扩展名，如 "webp"/"png"/"jpg"）
+/// * `quality` — JPEG 质量，默认 90
+/// * `delete_originals` — 转换成功后是否删除原文件
+///
+/// # 返回值
+/// * `Ok(Vec<ImageSaveResult>)` — 与 paths 顺序一致的逐文件结果
+///
+/// # 异常
+/// * 目标格式不支持
+#[tauri::command]
+async fn image_convert_files(
+    paths: Vec<String>,
+    format: String,
+    quality: Option<u8>,
+    delete_originals: bool,
+) -> Result<Vec<ImageSaveResult>, String> {
+    let image_format = image::ImageFormat::from_extension(format.trim_start_matches('.'))
+        .filter(|f| f.can_write())
+        .ok_or_else(|| format!("Unsupported target format: {}", format))?;
+    let extension = image_format.extensions_str().first().copied().unwrap_or("png");
+    let quality = quality.unwrap_or(90).clamp(1, 100);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let results: Vec<ImageSaveResult> = paths.par_iter()
+            .map(|path| match image_convert_single_file(path, image_format, extension, quality, delete_originals) {
+                Ok(output_path) => ImageSaveResult {
+                    path: output_path.to_string_lossy().to_string(),
+                    success: true,
+                    error: None,
+                    enhanced_data: None,
+                },
+                Err(e) => {
+                    log::warn!("转换图片失败 {}: {}", path, e);
+                    ImageSaveResult {
+                        path: path.clone(),
+                        success: false,
+                        error: Some(e),
+                        enhanced_data: None,
+                    }
+                }
+            })
+            .collect();
+
+        let converted = results.iter().filter(|r| r.success).count();
+        log::info!("批量转换完成: {}/{} 成功，目标格式 {}", converted, results.len(), extension);
+        results
+    })
+    .await
+    .map_err(|e| format!("Convert task failed: {}", e))
+}
+
+/// 依次尝试 "stem.ext"、"stem_1.ext"、"stem_2.ext"…… 作为目标文件名，由 claim 原子地占用
+/// （如以 create_new 创建文件）；名称已被占用（AlreadyExists）时换下一个计数，其他错误直接返回
+fn file_claim_unique_name<T>(
+    dir: &std::path::Path,
+    stem: &str,
+    extension: &str,
+    mut claim: impl FnMut(&std::path::Path) -> std::io::Result<T>,
+) -> std::io::Result<(PathBuf, T)> {
+    let mut counter = 0;
+    loop {
+        let target = if counter == 0 {
+            dir.join(format!("{}.{}", stem, extension))
+        } else {
+            dir.join(format!("{}_{}.{}", stem, counter, extension))
+        };
+        match claim(&target) {
+            Ok(value) => return Ok((target, value)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Tauri IPC 命令：增强已保存的图片，结果另存为原文件旁的副本，原文件保持不变
+///
+/// 副本命名为 "原文件名_后缀.扩展名"，按原格式编码（JPEG 使用 95 质量）；
+/// 同名文件已存在时追加 _1、_2 等计数后缀。文件名以 create_new 原子占用，不会覆盖任何已有文件
+///
+/// # 参数
+/// * `path` — 图片文件路径，必须位于 ~/Pictures/ViewStage 内
+/// * `params` — 增强参数
+/// * `suffix` — 文件名后缀，只保留字母数字下划线和中划线，为空时使用 "enhanced"
+///
+/// # 返回值
+/// * `Ok(ImageSaveResult)` — 副本路径及成功状态
+///
+/// # 异常
+/// * 增强参数非法
+/// * 路径不在 ViewStage 目录内或文件不存在
+/// * 图像格式不支持或解码失败
+/// * 编码或写入失败
+#[tauri::command]
+async fn image_save_enhanced_copy(path: String, params: EnhanceParams, suffix: String) -> Result<ImageSaveResult, String> {
+    params.validate()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let source_path = path_validate_pictures(&path)?;
+        let suffix = match suffix.trim_start_matches('_') {
+            "" => "enhanced".to_string(),
+            suffix => string_format_prefix(suffix),
+        };
+
+        let bytes = std::fs::read(&source_path)
+            .map_err(|e| format!("Failed to read image file: {}", e))?;
+        let format = image::guess_format(&bytes)
+            .map_err(|e| format!("Unsupported image format: {}", e))?;
+        let img = image::load_from_memory_with_format(&bytes, format)
+            .map_err(|e| format!("Failed to load image: {}", e))?;
+
+        let mut rgba = img.to_rgba8();
+        pixel_update_enhance_params(&mut rgba, &params);
+        let encoded = image_encode_bytes(&DynamicImage::ImageRgba8(rgba), format, 95)?;
+
+        let dir = source_path.parent().ok_or("Failed to resolve image directory")?;
+        let stem = source_path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+        let extension = source_path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+        let (target, mut file) = file_claim_unique_name(dir, &format!("{}_{}", stem, suffix), extension, |target| {
+            std::fs::OpenOptions::new().write(true).create_new(true).open(target)
+        })
+        .map_err(|e| format!("Failed to create image file: {}", e))?;
+
+        if let Err(e) = file.write_all(&encoded) {
+            drop(file);
+            let _ = std::fs::remove_file(&target);
+            return Err(format!("Failed to write image file: {}", e));
+        }
+
+        log::info!("已保存增强副本: {:?}", target);
+        Ok(ImageSaveResult {
+            path: target.to_string_lossy().to_string(),
+            success: true,
+            error: None,
+            enhanced_data: None,
+        })
+    })
+    .await
+    .map_err(|e| format!("Enhance task failed: {}", e))?
+}
+
+// ==================== 摄像头 ====================
+
+/// 判定宽高比匹配的相对误差
+const CAMERA_ASPECT_TOLERANCE: f32 = 0.02;
+
+/// Tauri IPC 命令：从摄像头支持的分辨率中选出最佳采集分辨率
+///
+/// 摄像头枚举在前端通过 mediaDevices 完成，这里只负责挑选：
+/// 在宽高比与目标相差不超过 2% 的分辨率中取面积最大者；
+/// 没有匹配项时退回面积最大的分辨率
+///
+/// # 参数
+/// * `resolutions` — 摄像头支持的分辨率列表
+/// * `target_aspect` — 目标宽高比（宽 / 高），如 16/9
+///
+/// # 返回值
+/// * `Ok((u32, u32))` — 选中的 (宽, 高)
+///
+/// # 异常
+/// * 分辨率列表为空
+/// * 目标宽高比非法
+#[tauri::command]
+fn camera_calc_best_resolution(resolutions: Vec<CameraResolution>, target_aspect: f32) -> Result<(u32, u32), String> {
+    if !target_aspect.is_finite() || target_aspect <= 0.0 {
+        return Err(format!("Invalid target aspect: {}", target_aspect));
+    }
+
+    let valid = || resolutions.iter().filter(|r| r.width > 0 && r.height > 0);
+    let area = |r: &&CameraResolution| r.width as u64 * r.height as u64;
+
+    let best = valid()
+        .filter(|r| {
+            let aspect = r.width as f32 / r.height as f32;
+            (aspect - target_aspect).abs() / target_aspect <= CAMERA_ASPECT_TOLERANCE
+        })
+        .max_by_key(area)
+        .or_else(|| valid().max_by_key(area))
+        .ok_or_else(|| "No camera resolutions available".to_string())?;
+
+    Ok((best.width, best.height))
+}
+
+// ==================== 笔画压缩 ====================
+
+/// 解析 #RRGGBB 或 #RRGGBBAA 格式颜色字符串为 RGBA
+fn color_calc_from_hex(color_str: &str) -> Result<Rgba<u8>, String> {
+    if !color_str.starts_with('#') {
+        return Err(format!("Invalid color format: must start with '#', got: {}", color_str));
+    }
+    
+    match color_str.len() {
+        7 => {
+            let r = u8::from_str_radix(&color_str[1..3], 16)
+                .map_err(|_| format!("Invalid red component in color: {}", color_str))?;
+            let g = u8::from_str_radix(&color_str[3..5], 16)
+                .map_err(|_| format!("Invalid green component in color: {}", color_str))?;
+            let b = u8::from_str_radix(&color_str[5..7], 16)
+                .map_err(|_| format!("Invalid blue component in color: {}", color_str))?;
+            Ok(Rgba([r, g, b, 255]))
+        }
+        9 => {
+            let r = u8::from_str_radix(&color_str[1..3], 16)
+                .map_err(|_| format!("Invalid red component in color: {}", color_str))?;
+            let g = u8::from_str_radix(&color_str[3..5], 16)
+                .map_err(|_| format!("Invalid green component in color: {}", color_str))?;
+            let b = u8::from_str_radix(&color_str[5..7], 16)
+                .map_err(|_| format!("Invalid blue component in color: {}", color_str))?;
+            let a = u8::from_str_radix(&color_str[7..9], 16)
+                .map_err(|_| format!("Invalid alpha component in color: {}", color_str))?;
+            Ok(Rgba([r, g, b, a]))
+        }
+        _ => Err(format!("Invalid color format: expected #RRGGBB or #RRGGBBAA, got: {}", color_str))
+    }
+}
+
+const DEFAULT_COLOR: Rgba<u8> = Rgba([52, 152, 219, 255]);
+
+/// 在画布上用 Bresenham 算法绘制圆形笔触线段
+fn canvas_render_line(canvas: &mut RgbaImage, x1: i32, y1: i32, x2: i32, y2: i32, color: Rgba<u8>, width: u32) {
+    let dx = (x2 - x1).abs();
+    let dy = (y2 - y1).abs();
+    let sx = if x1 < x2 { 1 } else { -1 };
+    let sy = if y1 < y2 { 1 } else { -1 };
+    let mut err = dx - dy;
+    let mut x = x1;
+    let mut y = y1;
+    
+    let half_width = (width / 2) as i32;
+    
+    loop {
+        for wx in -half_width..=half_width {
+            for wy in -half_width..=half_width {
+                let px = x + wx;
+                let py = y + wy;
+                if px >= 0 && py >= 0 && (px as u32) < canvas.width() && (py as u32) < canvas.height() {
+                    let dist = ((wx * wx + wy * wy) as f32).sqrt();
+                    if dist <= half_width as f32 {
+                        let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                        if color[3] == 255 {
+                            *pixel = color;
+                        } else {
+                            let alpha = color[3] as f32 / 255.0;
+                            let inv_alpha = 1.0 - alpha;
+                            pixel[0] = (color[0] as f32 * alpha + pixel[0] as f32 * inv_alpha) as u8;
+                            pixel[1] = (color[1] as f32 * alpha + pixel[1] as f32 * inv_alpha) as u8;
+                            pixel[2] = (color[2] as f32 * alpha + pixel[2] as f32 * inv_alpha) as u8;
+                        }
+                    }
+                }
+            }
+        }
+        
+        if x == x2 && y == y2 {
+            break;
+        }
+        
+        let e2 = 2 * err;
+        if e2 > -dy {
+            err -= dy;
+            x += sx;
+        }
+        if e2 < dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// 在画布上用 Bresenham 算法擦除圆形或方形区域（设置 alpha=0）
+///
+/// `square` 为 true 时跳过距离判断，擦除整个方形笔刷范围
+///
+/// 提供 restore 时被擦除的像素恢复为 restore 中对应位置的像素（超出范围则为透明）
+#[allow(clippy::too_many_arguments)]
+fn canvas_delete_line(canvas: &mut RgbaImage, x1: i32, y1: i32, x2: i32, y2: i32, width: u32, square: bool, restore: Option<&RgbaImage>) {
+    let dx = (x2 - x1).abs();
+    let dy = (y2 - y1).abs();
+    let sx = if x1 < x2 { 1 } else { -1 };
+    let sy = if y1 < y2 { 1 } else { -1 };
+    let mut err = dx - dy;
+    let mut x = x1;
+    let mut y = y1;
+    
+    let half_width = (width / 2) as i32;
+    
+    loop {
+        for wx in -half_width..=half_width {
+            for wy in -half_width..=half_width {
+                let px = x + wx;
+                let py = y + wy;
+                if px >= 0 && py >= 0 && (px as u32) < canvas.width() && (py as u32) < canvas.height() {
+                    let dist = ((wx * wx + wy * wy) as f32).sqrt();
+                    if square || dist <= half_width as f32 {
+                        let restored = restore.and_then(|base| base.get_pixel_checked(px as u32, py as u32));
+                        let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                        match restored {
+                            Some(base_pixel) => *pixel = *base_pixel,
+                            None => pixel[3] = 0,
+                        }
+                    }
+                }
+            }
+        }
+        
+        if x == x2 && y == y2 {
+            break;
+        }
+        
+        let e2 = 2 * err;
+        if e2 > -dy {
             err -= dy;
             x += sx;
         }
@@ -988,80 +1928,974 @@ fn canvas_delete_line(canvas: &mut RgbaImage, x1: i32, y1: i32, x2: i32, y2: i32
     }
 }
 
-/// Tauri IPC 命令：将笔画数据渲染到画布并返回 base64 PNG
+const DEFAULT_FONT_SIZE: f32 = 24.0;
+
+/// 将文字笔画绘制到画布，完全位于画布外的文字直接跳过
+fn canvas_render_text_stroke(canvas: &mut RgbaImage, stroke: &Stroke) {
+    let (Some(text), Some(position)) = (stroke.text.as_deref(), stroke.position) else {
+        return;
+    };
+    if text.is_empty() {
+        return;
+    }
+
+    let font_size = stroke.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+    let color = color_calc_from_hex(stroke.color.as_deref().unwrap_or("#3498db"))
+        .unwrap_or(DEFAULT_COLOR);
+
+    match text_processing::text_calc_block_size(text, font_size) {
+        Ok((width, height)) => {
+            let outside = position.x + width < 0.0
+                || position.y + height < 0.0
+                || position.x > canvas.width() as f32
+                || position.y > canvas.height() as f32;
+            if outside {
+                return;
+            }
+        }
+        Err(e) => {
+            log::warn!("文字笔画测量失败: {}", e);
+            return;
+        }
+    }
+
+    if let Err(e) = text_processing::text_render_on_canvas(canvas, text, position.x, position.y, font_size, color) {
+        log::warn!("文字笔画渲染失败: {}", e);
+    }
+}
+
+/// 画布单边最大尺寸，防止异常请求分配数 GB 内存
+const MAX_CANVAS_DIMENSION: u32 = 16384;
+
+/// 校验画布尺寸：不能为 0，单边不超过 MAX_CANVAS_DIMENSION
+fn canvas_validate_size(width: u32, height: u32) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err(format!("Invalid canvas size {}x{}: dimensions must be non-zero", width, height));
+    }
+    if width > MAX_CANVAS_DIMENSION || height > MAX_CANVAS_DIMENSION {
+        return Err(format!(
+            "Canvas size {}x{} exceeds the maximum of {}x{}",
+            width, height, MAX_CANVAS_DIMENSION, MAX_CANVAS_DIMENSION
+        ));
+    }
+    Ok(())
+}
+
+/// 解析 erase_mode，返回是否擦除为底图
+fn compact_parse_erase_to_base(erase_mode: Option<&str>) -> Result<bool, String> {
+    match erase_mode {
+        None | Some("transparent") => Ok(false),
+        Some("base") => Ok(true),
+        Some(other) => Err(format!("Invalid erase mode: {} (expected transparent or base)", other)),
+    }
+}
+
+/// 按请求中的 dpr 将画布尺寸和笔画从 CSS 像素换算到设备像素，返回换算后的请求和实际采用的 dpr
+///
+/// dpr 超过配置中的 dprLimit 时按上限处理；未指定 dpr 时原样返回，dpr 为 1.0
+fn compact_apply_dpr(app: &tauri::AppHandle, request: CompactStrokesRequest) -> Result<(CompactStrokesRequest, f32), String> {
+    if request.dpr.is_none() {
+        return Ok((request, 1.0));
+    }
+    compact_calc_dpr(request, config_fetch_dpr_limit(app) as f32)
+}
+
+/// compact_apply_dpr 的换算部分，limit 为 0 表示不限制
+fn compact_calc_dpr(mut request: CompactStrokesRequest, limit: f32) -> Result<(CompactStrokesRequest, f32), String> {
+    let Some(dpr) = request.dpr.take() else {
+        return Ok((request, 1.0));
+    };
+    if !dpr.is_finite() || dpr <= 0.0 {
+        return Err(format!("Invalid device pixel ratio: {}", dpr));
+    }
+    let dpr = if limit > 0.0 { dpr.min(limit) } else { dpr };
+    if dpr == 1.0 {
+        return Ok((request, dpr));
+    }
+
+    request.canvas_width = (request.canvas_width as f32 * dpr).round() as u32;
+    request.canvas_height = (request.canvas_height as f32 * dpr).round() as u32;
+    request.strokes = stroke_format_transform_layers(vec![request.strokes], [dpr, 0.0, 0.0, dpr, 0.0, 0.0])?
+        .pop()
+        .unwrap_or_default();
+
+    Ok((request, dpr))
+}
+
+/// 获取压缩请求的底图：优先使用缓存的底图代理，否则解码 base_image（解码失败视为无底图）
+///
+/// # 异常
+/// * base_proxy 指定的代理不存在
+fn compact_fetch_base(request: &CompactStrokesRequest) -> Result<Option<Arc<RgbaImage>>, String> {
+    if let Some(proxy_id) = request.base_proxy {
+        return image_fetch_proxy(proxy_id).map(Some);
+    }
+    Ok(request.base_image.as_deref()
+        .and_then(|data| image_load_base64(data).ok())
+        .map(|img| Arc::new(img.to_rgba8())))
+}
+
+/// 按顺序将笔画（绘制/擦除/清空/文字）渲染到画布上
+///
+/// 提供 restore 时擦除和清空恢复为该图像的像素，否则变为透明
+fn canvas_render_strokes(canvas: &mut RgbaImage, strokes: &[Stroke], restore: Option<&RgbaImage>) {
+    for stroke in strokes {
+        let points = &stroke.points;
+        
+        if stroke.stroke_type == "clear" {
+            for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+                *pixel = restore
+                    .and_then(|base| base.get_pixel_checked(x, y))
+                    .copied()
+                    .unwrap_or(Rgba([0, 0, 0, 0]));
+            }
+            continue;
+        }
+        
+        if stroke.stroke_type == "text" {
+            canvas_render_text_stroke(canvas, stroke);
+            continue;
+        }
+        
+        if points.is_empty() {
+            continue;
+        }
+        
+        if stroke.stroke_type == "draw" {
+            let color = color_calc_from_hex(stroke.color.as_deref().unwrap_or("#3498db"))
+                .unwrap_or(DEFAULT_COLOR);
+            let line_width = stroke.line_width.unwrap_or(2);
+            
+            for point in points {
+                canvas_render_line(
+                    canvas,
+                    point.from_x as i32,
+                    point.from_y as i32,
+                    point.to_x as i32,
+                    point.to_y as i32,
+                    color,
+                    line_width,
+                );
+            }
+        } else if stroke.stroke_type == "erase" {
+            let eraser_size = stroke.eraser_size.unwrap_or(15);
+            let square = stroke.eraser_shape.as_deref() == Some("square");
+            
+            for point in points {
+                canvas_delete_line(
+                    canvas,
+                    point.from_x as i32,
+                    point.from_y as i32,
+                    point.to_x as i32,
+                    point.to_y as i32,
+                    eraser_size,
+                    square,
+                    restore,
+                );
+            }
+        }
+    }
+}
+
+/// Tauri IPC 命令：将笔画数据渲染到画布并返回 base64 PNG
+///
+/// 接收笔画数组（绘制/擦除/清空/文字），在空白或给定底图上逐笔渲染，用于撤销缩略图生成。
+/// erase_mode 为 "base" 时擦除恢复底图像素，避免导出 JPEG 时被擦除区域变成黑色。
+/// 指定 dpr 时按设备像素渲染（见 CompactStrokesRequest::dpr），
+/// 画布尺寸为 0 或单边超过 16384 时返回错误
+#[tauri::command]
+fn stroke_format_compact(app: tauri::AppHandle, request: CompactStrokesRequest) -> Result<String, String> {
+    let (request, _) = compact_apply_dpr(&app, request)?;
+    canvas_validate_size(request.canvas_width, request.canvas_height)?;
+    let erase_to_base = compact_parse_erase_to_base(request.erase_mode.as_deref())?;
+
+    let mut canvas: RgbaImage = ImageBuffer::new(request.canvas_width, request.canvas_height);
+    
+    for pixel in canvas.pixels_mut() {
+        *pixel = Rgba([0, 0, 0, 0]);
+    }
+    
+    let base_rgba = compact_fetch_base(&request)?;
+    if let Some(ref base_rgba) = base_rgba {
+        for (x, y, pixel) in base_rgba.enumerate_pixels() {
+            if x < canvas.width() && y < canvas.height() {
+                canvas.put_pixel(x, y, *pixel);
+            }
+        }
+    }
+    
+    let restore = if erase_to_base { base_rgba.as_deref() } else { None };
+    canvas_render_strokes(&mut canvas, &request.strokes, restore);
+    
+    let mut buffer = Vec::new();
+    DynamicImage::ImageRgba8(canvas)
+        .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode compacted image: {}", e))?;
+    
+    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&buffer)))
+}
+
+/// Tauri IPC 命令：仅渲染笔画到透明画布，不合成底图
+///
+/// 与 stroke_format_compact 使用相同的渲染规则，但忽略 base_image，
+/// 擦除只作用于笔画本身，便于前端将照片层与批注层分开保存、独立显示
+///
+/// # 参数
+/// * `request` — 笔画压缩请求，base_image 与 erase_mode 字段被忽略
+///
+/// # 返回值
+/// * `Ok(String)` — 透明背景的笔画层 PNG data URL
+///
+/// # 异常
+/// * 画布尺寸为 0 或超过上限
+/// * dpr 非法
+/// * PNG 编码失败
+#[tauri::command]
+fn stroke_render_layer(app: tauri::AppHandle, request: CompactStrokesRequest) -> Result<String, String> {
+    let (request, _) = compact_apply_dpr(&app, request)?;
+    canvas_validate_size(request.canvas_width, request.canvas_height)?;
+    let mut canvas: RgbaImage = ImageBuffer::from_pixel(request.canvas_width, request.canvas_height, Rgba([0, 0, 0, 0]));
+    canvas_render_strokes(&mut canvas, &request.strokes, None);
+    image_encode_png_base64(&DynamicImage::ImageRgba8(canvas))
+}
+
+/// 笔画阴影的最大模糊半径（高斯 sigma，像素）
+const SHADOW_MAX_BLUR: f32 = 100.0;
+
+/// Tauri IPC 命令：渲染带投影的笔画并合成到底图上，呈现"浮起"的批注效果
+///
+/// 笔画先单独渲染到透明层（擦除只作用于笔画本身，与 stroke_render_layer 一致），
+/// 以其 alpha 作为阴影形状，偏移并模糊后垫在笔画下方，再依次合成到底图上。
+/// 模糊使用 fast_blur（多次盒式模糊近似高斯，耗时与半径无关），在后台线程中执行。
+/// 请求指定 dpr 时，模糊程度和偏移与笔画一样按 dpr 换算
+///
+/// # 参数
+/// * `request` — 笔画压缩请求，erase_mode 字段被忽略
+/// * `blur` — 阴影模糊程度（高斯 sigma，0..100），0 为硬阴影
+/// * `offset_x` / `offset_y` — 阴影偏移（CSS 像素）
+/// * `shadow_color` — 阴影颜色，#RRGGBB 或 #RRGGBBAA 格式，alpha 决定阴影浓度
+///
+/// # 返回值
+/// * `Ok(String)` — 合成后的 PNG data URL
+///
+/// # 异常
+/// * 画布尺寸或 dpr 非法
+/// * 模糊程度超出范围或颜色格式非法
+/// * PNG 编码失败
+#[tauri::command]
+async fn stroke_render_shadow(
+    app: tauri::AppHandle,
+    request: CompactStrokesRequest,
+    blur: f32,
+    offset_x: i32,
+    offset_y: i32,
+    shadow_color: String,
+) -> Result<String, String> {
+    if !(0.0..=SHADOW_MAX_BLUR).contains(&blur) {
+        return Err(format!("Shadow blur must be within 0..{}, got {}", SHADOW_MAX_BLUR, blur));
+    }
+    let (request, dpr) = compact_apply_dpr(&app, request)?;
+    canvas_validate_size(request.canvas_width, request.canvas_height)?;
+    let blur = blur * dpr;
+    let (offset_x, offset_y) = ((offset_x as f32 * dpr).round() as i32, (offset_y as f32 * dpr).round() as i32);
+    let color = color_calc_from_hex(&shadow_color)?;
+
+    tauri::async_runtime::spawn_blocking(move || stroke_calc_shadow(&request, blur, offset_x, offset_y, color))
+        .await
+        .map_err(|e| format!("Shadow render task failed: {}", e))?
+}
+
+/// 阴影渲染的实际处理，参数已由 stroke_render_shadow 校验并按 dpr 换算
+fn stroke_calc_shadow(request: &CompactStrokesRequest, blur: f32, offset_x: i32, offset_y: i32, color: Rgba<u8>) -> Result<String, String> {
+    let (width, height) = (request.canvas_width, request.canvas_height);
+
+    let mut layer: RgbaImage = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+    canvas_render_strokes(&mut layer, &request.strokes, None);
+
+    let mut shadow: RgbaImage = ImageBuffer::from_pixel(width, height, Rgba([color[0], color[1], color[2], 0]));
+    for (x, y, pixel) in layer.enumerate_pixels() {
+        let (sx, sy) = (x as i64 + offset_x as i64, y as i64 + offset_y as i64);
+        if pixel[3] == 0 || sx < 0 || sy < 0 || sx >= width as i64 || sy >= height as i64 {
+            continue;
+        }
+        shadow.get_pixel_mut(sx as u32, sy as u32)[3] = (pixel[3] as u32 * color[3] as u32 / 255) as u8;
+    }
+    if blur > 0.0 {
+        shadow = image::imageops::fast_blur(&shadow, blur);
+    }
+
+    let mut canvas: RgbaImage = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+    if let Some(base) = compact_fetch_base(request)? {
+        image::imageops::replace(&mut canvas, base.as_ref(), 0, 0);
+    }
+    for ((target, shadow_pixel), layer_pixel) in canvas.pixels_mut().zip(shadow.pixels()).zip(layer.pixels()) {
+        pixel_blend_over(&mut target.0, &shadow_pixel.0);
+        pixel_blend_over(&mut target.0, &layer_pixel.0);
+    }
+
+    image_encode_png_base64(&DynamicImage::ImageRgba8(canvas))
+}
+
+/// 将笔画平移 (-dx, -dy)；线段坐标先按渲染时的规则取整再平移，保证与整幅渲染逐像素一致
+fn stroke_calc_translated(stroke: &Stroke, dx: i32, dy: i32) -> Stroke {
+    let mut translated = stroke.clone();
+    for segment in &mut translated.points {
+        segment.from_x = (segment.from_x as i32 - dx) as f32;
+        segment.from_y = (segment.from_y as i32 - dy) as f32;
+        segment.to_x = (segment.to_x as i32 - dx) as f32;
+        segment.to_y = (segment.to_y as i32 - dy) as f32;
+    }
+    if let Some(position) = translated.position.as_mut() {
+        position.x -= dx as f32;
+        position.y -= dy as f32;
+    }
+    translated
+}
+
+/// Tauri IPC 命令：只重绘画布中的脏矩形区域
+///
+/// 仅渲染与区域相交的笔画（清空笔画始终生效），返回区域图像及偏移，
+/// 前端只需贴回变化区域，避免大画布的小改动触发整幅 PNG 编码
+///
+/// # 参数
+/// * `request` — 笔画压缩请求，规则与 stroke_format_compact 一致
+/// * `dirty_rect` — 需要重绘的区域，超出画布部分被裁掉；指定 dpr 时与画布一样按 CSS 像素给出，
+///   返回的区域位置和尺寸为设备像素
+///
+/// # 返回值
+/// * `Ok(CompactRegionResult)` — 区域 PNG data URL 及其在画布中的位置和尺寸
+///
+/// # 异常
+/// * 画布尺寸或 dpr 非法，或区域与画布没有交集
+/// * erase_mode 非法
+/// * PNG 编码失败
+#[tauri::command]
+fn stroke_format_compact_region(
+    app: tauri::AppHandle,
+    request: CompactStrokesRequest,
+    dirty_rect: DirtyRect,
+) -> Result<CompactRegionResult, String> {
+    let (request, dpr) = compact_apply_dpr(&app, request)?;
+    // 向外取整，保证换算后的区域完整覆盖原区域
+    let left = (dirty_rect.x as f32 * dpr).floor() as u32;
+    let top = (dirty_rect.y as f32 * dpr).floor() as u32;
+    let right = (dirty_rect.x.saturating_add(dirty_rect.width) as f32 * dpr).ceil() as u32;
+    let bottom = (dirty_rect.y.saturating_add(dirty_rect.height) as f32 * dpr).ceil() as u32;
+    let dirty_rect = DirtyRect { x: left, y: top, width: right - left, height: bottom - top };
+    canvas_validate_size(request.canvas_width, request.canvas_height)?;
+    let erase_to_base = compact_parse_erase_to_base(request.erase_mode.as_deref())?;
+
+    let x = dirty_rect.x.min(request.canvas_width);
+    let y = dirty_rect.y.min(request.canvas_height);
+    let width = dirty_rect.width.min(request.canvas_width - x);
+    let height = dirty_rect.height.min(request.canvas_height - y);
+    if width == 0 || height == 0 {
+        return Err("Dirty rect does not intersect the canvas".to_string());
+    }
+
+    let mut base_region: Option<RgbaImage> = None;
+    if let Some(base_rgba) = compact_fetch_base(&request)? {
+        base_region = Some(ImageBuffer::from_fn(width, height, |px, py| {
+            base_rgba.get_pixel_checked(x + px, y + py).copied().unwrap_or(Rgba([0, 0, 0, 0]))
+        }));
+    }
+
+    let mut canvas = base_region.clone()
+        .unwrap_or_else(|| ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0])));
+
+    let (left, top, right, bottom) = (x as f32, y as f32, (x + width) as f32, (y + height) as f32);
+    let strokes: Vec<Stroke> = request.strokes.iter()
+        .filter(|stroke| {
+            stroke.stroke_type == "clear"
+                || stroke_calc_bounds(stroke).is_some_and(|[min_x, min_y, max_x, max_y]| {
+                    max_x >= left && min_x <= right && max_y >= top && min_y <= bottom
+                })
+        })
+        .map(|stroke| stroke_calc_translated(stroke, x as i32, y as i32))
+        .collect();
+
+    let restore = if erase_to_base { base_region.as_ref() } else { None };
+    canvas_render_strokes(&mut canvas, &strokes, restore);
+
+    Ok(CompactRegionResult {
+        image_data: image_encode_png_base64(&DynamicImage::ImageRgba8(canvas))?,
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// 同时打开的分段渲染会话上限，超出时淘汰最久未使用的会话
+const STROKE_STREAM_MAX_SESSIONS: usize = 4;
+
+/// 分段渲染会话：笔画分批送入后立即渲染到画布，不保留笔画数据
+struct StrokeStreamSession {
+    canvas: RgbaImage,
+    /// erase_mode 为 "base" 时擦除恢复的底图
+    restore: Option<Arc<RgbaImage>>,
+    /// 画布采用的设备像素比，送入的笔画按此缩放
+    dpr: f32,
+    stroke_count: usize,
+}
+
+/// 会话表项：最近使用时间和会话
+type StrokeStreamEntry = (std::time::Instant, Arc<Mutex<StrokeStreamSession>>);
+
+/// 打开的分段渲染会话，键为会话 ID
+static STROKE_STREAMS: Lazy<Mutex<std::collections::HashMap<u64, StrokeStreamEntry>>> =
+    Lazy::new(|| Mutex::new(std::collections::HashMap::new()));
+
+static STROKE_STREAM_NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+/// 取出会话并刷新其最近使用时间
+///
+/// # 异常
+/// * 会话不存在（未创建、已结束或已被淘汰）
+fn stroke_fetch_stream(stream_id: u64) -> Result<Arc<Mutex<StrokeStreamSession>>, String> {
+    let mut streams = STROKE_STREAMS.lock().map_err(|e| format!("Failed to lock stroke streams: {}", e))?;
+    let (last_used, session) = streams.get_mut(&stream_id)
+        .ok_or_else(|| format!("Stroke stream not found: {}", stream_id))?;
+    *last_used = std::time::Instant::now();
+    Ok(session.clone())
+}
+
+/// 逐个反序列化 JSON 数组元素并交给回调处理，不在内存中保留整个数组
+struct StrokeSeqVisitor<F>(F);
+
+impl<'de, F: FnMut(Stroke)> serde::de::Visitor<'de> for StrokeSeqVisitor<F> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of strokes")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(mut self, mut seq: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some(stroke) = seq.next_element::<Stroke>()? {
+            (self.0)(stroke);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// 逐条解析笔画 JSON，每解析出一条笔画即调用 on_stroke，返回笔画数
+///
+/// 支持两种格式：笔画数组（以 "[" 开头），或连续排列的笔画对象（如每行一个的 JSON Lines）
+fn stroke_parse_stream(json: &str, mut on_stroke: impl FnMut(Stroke)) -> Result<usize, String> {
+    if json.trim_start().starts_with('[') {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let count = serde::Deserializer::deserialize_seq(&mut deserializer, StrokeSeqVisitor(on_stroke))
+            .map_err(|e| format!("Failed to parse strokes: {}", e))?;
+        deserializer.end().map_err(|e| format!("Failed to parse strokes: {}", e))?;
+        return Ok(count);
+    }
+
+    let mut count = 0;
+    for stroke in serde_json::Deserializer::from_str(json).into_iter::<Stroke>() {
+        on_stroke(stroke.map_err(|e| format!("Failed to parse stroke {}: {}", count, e))?);
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Tauri IPC 命令：创建分段渲染会话，用于超大批量笔画的压缩
+///
+/// 百万级点数的白板一次性传入并反序列化可能耗尽内存。分段渲染时前端先创建会话，
+/// 再用 stroke_update_stream 分批送入笔画（每批渲染后即丢弃），最后用 stroke_format_stream 取出结果，
+/// 后端峰值内存只有画布和一批笔画。笔画量较小时仍可直接使用 stroke_format_compact。
+/// 同时最多保留 4 个会话，超出时淘汰最久未使用的；放弃渲染时调用 stroke_delete_stream 释放
+///
+/// # 参数
+/// * `request` — 笔画压缩请求，规则与 stroke_format_compact 一致；其中的 strokes 立即渲染，通常省略
+///
+/// # 返回值
+/// * `Ok(u64)` — 会话 ID
+///
+/// # 异常
+/// * 画布尺寸、dpr 或 erase_mode 非法
+/// * base_proxy 指定的代理不存在
+#[tauri::command]
+async fn stroke_load_stream(app: tauri::AppHandle, request: CompactStrokesRequest) -> Result<u64, String> {
+    let session = tauri::async_runtime::spawn_blocking(move || {
+        let (request, dpr) = compact_apply_dpr(&app, request)?;
+        canvas_validate_size(request.canvas_width, request.canvas_height)?;
+        let erase_to_base = compact_parse_erase_to_base(request.erase_mode.as_deref())?;
+
+        let base_rgba = compact_fetch_base(&request)?;
+        let mut canvas: RgbaImage = ImageBuffer::from_fn(request.canvas_width, request.canvas_height, |x, y| {
+            base_rgba.as_ref()
+                .and_then(|base| base.get_pixel_checked(x, y))
+                .copied()
+                .unwrap_or(Rgba([0, 0, 0, 0]))
+        });
+        let restore = if erase_to_base { base_rgba } else { None };
+        canvas_render_strokes(&mut canvas, &request.strokes, restore.as_deref());
+
+        Ok::<_, String>(StrokeStreamSession { canvas, restore, dpr, stroke_count: request.strokes.len() })
+    })
+    .await
+    .map_err(|e| format!("Stroke stream task failed: {}", e))??;
+
+    let id = STROKE_STREAM_NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let mut streams = STROKE_STREAMS.lock().map_err(|e| format!("Failed to lock stroke streams: {}", e))?;
+    while streams.len() >= STROKE_STREAM_MAX_SESSIONS {
+        let Some(oldest) = streams.iter().min_by_key(|(_, (last_used, _))| *last_used).map(|(id, _)| *id) else {
+            break;
+        };
+        streams.remove(&oldest);
+        log::warn!("笔画分段渲染会话数量超过上限，已淘汰: {}", oldest);
+    }
+    streams.insert(id, (std::time::Instant::now(), Arc::new(Mutex::new(session))));
+
+    Ok(id)
+}
+
+/// Tauri IPC 命令：向分段渲染会话送入一批笔画并立即渲染
+///
+/// 每批笔画逐条解析、逐条渲染，不构建完整的笔画数组；批次按调用顺序叠加
+///
+/// # 参数
+/// * `stream_id` — stroke_load_stream 返回的会话 ID
+/// * `strokes_json` — 本批笔画：笔画数组 JSON，或连续排列的笔画 JSON 对象（JSON Lines）
+///
+/// # 返回值
+/// * `Ok(usize)` — 会话累计渲染的笔画数
+///
+/// # 异常
+/// * 会话不存在（未创建、已结束或已被淘汰）
+/// * 笔画 JSON 格式错误（错误之前已解析的笔画仍会保留在画布上）
+#[tauri::command]
+async fn stroke_update_stream(stream_id: u64, strokes_json: String) -> Result<usize, String> {
+    let session = stroke_fetch_stream(stream_id)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = session.lock().map_err(|e| format!("Failed to lock stroke stream: {}", e))?;
+        let StrokeStreamSession { canvas, restore, dpr, stroke_count } = &mut *session;
+        let dpr = *dpr;
+
+        let result = stroke_parse_stream(&strokes_json, |stroke| {
+            // dpr 已校验为有限正数，矩阵合法，变换不会失败
+            let stroke = if dpr == 1.0 {
+                Some(stroke)
+            } else {
+                stroke_format_transform_layers(vec![vec![stroke]], [dpr, 0.0, 0.0, dpr, 0.0, 0.0])
+                    .ok()
+                    .and_then(|mut layers| layers.pop())
+                    .and_then(|mut layer| layer.pop())
+            };
+            if let Some(stroke) = stroke {
+                canvas_render_strokes(canvas, std::slice::from_ref(&stroke), restore.as_deref());
+            }
+            *stroke_count += 1;
+        });
+        result.map(|_| *stroke_count)
+    })
+    .await
+    .map_err(|e| format!("Stroke stream task failed: {}", e))?
+}
+
+/// Tauri IPC 命令：结束分段渲染会话，返回渲染结果
+///
+/// 会话随之释放，之后使用该 ID 的调用返回错误
+///
+/// # 参数
+/// * `stream_id` — stroke_load_stream 返回的会话 ID
+///
+/// # 返回值
+/// * `Ok(String)` — 渲染后的 PNG data URL
+///
+/// # 异常
+/// * 会话不存在（未创建、已结束或已被淘汰）
+/// * PNG 编码失败
+#[tauri::command]
+async fn stroke_format_stream(stream_id: u64) -> Result<String, String> {
+    let (_, session) = STROKE_STREAMS.lock()
+        .map_err(|e| format!("Failed to lock stroke streams: {}", e))?
+        .remove(&stream_id)
+        .ok_or_else(|| format!("Stroke stream not found: {}", stream_id))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let session = session.lock().map_err(|e| format!("Failed to lock stroke stream: {}", e))?;
+        log::info!("分段渲染笔画 {} 条", session.stroke_count);
+        image_encode_png_base64(&DynamicImage::ImageRgba8(session.canvas.clone()))
+    })
+    .await
+    .map_err(|e| format!("Stroke stream task failed: {}", e))?
+}
+
+/// Tauri IPC 命令：放弃并释放分段渲染会话
+///
+/// # 参数
+/// * `stream_id` — stroke_load_stream 返回的会话 ID
+///
+/// # 返回值
+/// * `Ok(bool)` — 会话存在并已释放时为 true
+#[tauri::command]
+fn stroke_delete_stream(stream_id: u64) -> Result<bool, String> {
+    let mut streams = STROKE_STREAMS.lock().map_err(|e| format!("Failed to lock stroke streams: {}", e))?;
+    Ok(streams.remove(&stream_id).is_some())
+}
+
+/// 路径平滑使用的二维点
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PathPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Catmull-Rom 样条平滑路径，与前端 `_build_smooth_points` 保持一致
+///
+/// 每段插值步数为 max(3, round(smoothness * 10))
+fn stroke_calc_smooth_points(points: &[PathPoint], smoothness: f32) -> Vec<PathPoint> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+
+    let steps = ((smoothness * 10.0).round() as i32).max(3) as usize;
+    let last = points.len() - 1;
+    let mut result = Vec::with_capacity(last * steps + 1);
+
+    for i in 0..last {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[(i + 1).min(last)];
+        let p3 = points[(i + 2).min(last)];
+
+        for j in 0..=steps {
+            if i > 0 && j == 0 {
+                continue;
+            }
+            let t = j as f32 / steps as f32;
+            let t2 = t * t;
+            let t3 = t2 * t;
+            let x = 0.5 * ((2.0 * p1.x)
+                + (-p0.x + p2.x) * t
+                + (2.0 * p0.x - 5.0 * p1.x + 4.0 * p2.x - p3.x) * t2
+                + (-p0.x + 3.0 * p1.x - 3.0 * p2.x + p3.x) * t3);
+            let y = 0.5 * ((2.0 * p1.y)
+                + (-p0.y + p2.y) * t
+                + (2.0 * p0.y - 5.0 * p1.y + 4.0 * p2.y - p3.y) * t2
+                + (-p0.y + 3.0 * p1.y - 3.0 * p2.y + p3.y) * t3);
+            result.push(PathPoint { x, y });
+        }
+    }
+
+    result
+}
+
+/// 平滑前允许的最大路径点数，超出时先均匀降采样，避免粘贴的超长路径占用大量内存和时间
+const SMOOTH_MAX_POINTS: usize = 10_000;
+
+/// 均匀降采样路径点到不超过 max_points 个，保留首尾点
+fn point_calc_downsample(points: &[PathPoint], max_points: usize) -> Vec<PathPoint> {
+    if points.len() <= max_points || max_points < 2 {
+        return points.to_vec();
+    }
+
+    let last = points.len() - 1;
+    (0..max_points)
+        .map(|i| points[i * last / (max_points - 1)])
+        .collect()
+}
+
+/// 读取配置中的数值项并限制在 range 内，读取失败或非有限值时回退 default
+fn config_fetch_number(
+    app: &tauri::AppHandle,
+    key: &str,
+    default: f64,
+    range: std::ops::RangeInclusive<f64>,
+) -> f64 {
+    config_fetch_current(app)
+        .ok()
+        .and_then(|config| config.get(key)?.as_f64())
+        .filter(|value| value.is_finite())
+        .unwrap_or(default)
+        .clamp(*range.start(), *range.end())
+}
+
+/// 读取配置中的平滑强度 smoothStrength，读取失败时回退默认值
+fn config_fetch_smooth_strength(app: &tauri::AppHandle) -> f32 {
+    let default_strength = config_fetch_default()["smoothStrength"].as_f64().unwrap_or(0.8);
+    config_fetch_number(app, "smoothStrength", default_strength, 0.0..=1.0) as f32
+}
+
+/// Tauri IPC 命令：按配置中的 smoothStrength 平滑路径
+///
+/// 平滑强度取自 config.json，前端无需重复维护该设置。
+/// 点数超过上限时先均匀降采样再平滑，并记录警告
+///
+/// # 参数
+/// * `points` — 原始路径点
+/// * `max_points` — 平滑前的点数上限，默认 10000
+///
+/// # 返回值
+/// * `Ok(Vec<PathPoint>)` — 平滑后的路径点
+///
+/// # 异常
+/// * 点数上限小于 2
+#[tauri::command]
+fn stroke_format_smooth_default(app: tauri::AppHandle, points: Vec<PathPoint>, max_points: Option<usize>) -> Result<Vec<PathPoint>, String> {
+    let max_points = max_points.unwrap_or(SMOOTH_MAX_POINTS);
+    if max_points < 2 {
+        return Err(format!("Invalid max points: {} (must be at least 2)", max_points));
+    }
+
+    let smoothness = config_fetch_smooth_strength(&app);
+    if points.len() > max_points {
+        log::warn!("路径点数 {} 超过上限 {}，平滑前已降采样", points.len(), max_points);
+        return Ok(stroke_calc_smooth_points(&point_calc_downsample(&points, max_points), smoothness));
+    }
+
+    Ok(stroke_calc_smooth_points(&points, smoothness))
+}
+
+/// 笔画后处理默认的坐标量化步长（像素）
+const FINALIZE_DEFAULT_QUANTIZE: f32 = 0.5;
+
+/// 笔画后处理默认的重采样间距（像素）
+const FINALIZE_DEFAULT_SPACING: f32 = 4.0;
+
+/// 笔画后处理默认的简化容差（像素）
+const FINALIZE_DEFAULT_TOLERANCE: f32 = 0.5;
+
+/// 笔画后处理请求，未指定的参数使用默认值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalizeStrokeRequest {
+    /// 原始采样点
+    pub points: Vec<PathPoint>,
+    /// 坐标量化步长，0 表示不量化，默认 0.5
+    pub quantize: Option<f32>,
+    /// 重采样间距，默认 4
+    pub spacing: Option<f32>,
+    /// Catmull-Rom 平滑强度（0..1），默认取配置中的 smoothStrength
+    pub smoothness: Option<f32>,
+    /// 简化容差，默认 0.5
+    pub tolerance: Option<f32>,
+}
+
+/// 按步长将坐标量化到网格，并去掉量化后相邻重复的点
+fn point_calc_quantize(points: &[PathPoint], step: f32) -> Vec<PathPoint> {
+    let mut result: Vec<PathPoint> = Vec::with_capacity(points.len());
+    for point in points {
+        let point = if step > 0.0 {
+            PathPoint { x: (point.x / step).round() * step, y: (point.y / step).round() * step }
+        } else {
+            *point
+        };
+        if result.last().is_none_or(|last| last.x != point.x || last.y != point.y) {
+            result.push(point);
+        }
+    }
+    result
+}
+
+/// 按弧长等间距重采样路径，保留首尾点
+fn point_calc_resample(points: &[PathPoint], spacing: f32) -> Vec<PathPoint> {
+    let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+
+    let mut result = vec![first];
+    let mut carried = 0.0f32;
+    for pair in points.windows(2) {
+        let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        let length = (dx * dx + dy * dy).sqrt();
+        let mut distance = spacing - carried;
+        while distance <= length {
+            let t = distance / length;
+            result.push(PathPoint { x: pair[0].x + dx * t, y: pair[0].y + dy * t });
+            distance += spacing;
+        }
+        carried = length - (distance - spacing);
+    }
+
+    if result.len() == 1 || carried > 0.0 {
+        result.push(last);
+    }
+    result
+}
+
+/// Tauri IPC 命令：手写笔画后处理，一次完成 量化 → 重采样 → Catmull-Rom 平滑 → 简化
+///
+/// 量化和按固定间距重采样去除采样抖动，平滑后再用 Douglas-Peucker 简化，
+/// 得到点数适中、曲线顺滑的最终路径
+///
+/// # 参数
+/// * `request` — 原始点及各步骤参数
+///
+/// # 返回值
+/// * `Ok(Vec<PathPoint>)` — 处理后的路径点；只有一个点时原样返回
+///
+/// # 异常
+/// * 路径为空或含非有限坐标
+/// * 参数为负数或非有限值，或重采样间距为 0
+#[tauri::command]
+fn stroke_format_finalize(app: tauri::AppHandle, request: FinalizeStrokeRequest) -> Result<Vec<PathPoint>, String> {
+    let smoothness = match request.smoothness {
+        Some(value) => value,
+        None => config_fetch_smooth_strength(&app),
+    };
+    stroke_calc_finalize(&request, smoothness)
+}
+
+/// stroke_format_finalize 的处理部分，smoothness 已从请求或配置中取得
+fn stroke_calc_finalize(request: &FinalizeStrokeRequest, smoothness: f32) -> Result<Vec<PathPoint>, String> {
+    if request.points.is_empty() {
+        return Err("Stroke has no points".to_string());
+    }
+    if request.points.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        return Err("Stroke contains non-finite coordinates".to_string());
+    }
+
+    let quantize = request.quantize.unwrap_or(FINALIZE_DEFAULT_QUANTIZE);
+    let spacing = request.spacing.unwrap_or(FINALIZE_DEFAULT_SPACING);
+    let tolerance = request.tolerance.unwrap_or(FINALIZE_DEFAULT_TOLERANCE);
+    if !quantize.is_finite() || quantize < 0.0 {
+        return Err(format!("Invalid quantize step: {}", quantize));
+    }
+    if !spacing.is_finite() || spacing <= 0.0 {
+        return Err(format!("Invalid resample spacing: {}", spacing));
+    }
+    if !smoothness.is_finite() {
+        return Err(format!("Invalid smoothness: {}", smoothness));
+    }
+    let smoothness = smoothness.clamp(0.0, 1.0);
+    if let [point] = request.points[..] {
+        return Ok(vec![point]);
+    }
+
+    let quantized = point_calc_quantize(&request.points, quantize);
+    let mut resampled = point_calc_resample(&quantized, spacing);
+    if resampled.len() > SMOOTH_MAX_POINTS {
+        log::warn!("重采样后点数 {} 超过上限 {}，平滑前已降采样", resampled.len(), SMOOTH_MAX_POINTS);
+        resampled = point_calc_downsample(&resampled, SMOOTH_MAX_POINTS);
+    }
+    let smoothed = stroke_calc_smooth_points(&resampled, smoothness);
+
+    Ok(stroke_format_simplify(smoothed, None, tolerance)?.points)
+}
+
+// ==================== 渲染分辨率 ====================
+
+/// 读取配置中的设备像素比上限 dprLimit，0 表示不限制
+fn config_fetch_dpr_limit(app: &tauri::AppHandle) -> f64 {
+    let default_limit = config_fetch_default()["dprLimit"].as_f64().unwrap_or(2.0);
+    config_fetch_number(app, "dprLimit", default_limit, 0.0..=8.0)
+}
+
+/// Tauri IPC 命令：按窗口大小、显示器缩放比例及 dprLimit 推荐渲染分辨率
+///
+/// 高 DPI 屏幕（如 4K 笔记本 250% 缩放）按原生像素渲染开销很大，
+/// 这里将缩放比例限制在 dprLimit 以内，与前端 main_calc_capped_dpr 规则一致
+///
+/// # 参数
+/// * `label` — 目标窗口标签，默认 "main"
+///
+/// # 返回值
+/// * `Ok(RenderResolution)` — 推荐的物理像素宽高及采用的 dpr
+///
+/// # 异常
+/// * 窗口不存在
+/// * 获取窗口尺寸或缩放比例失败
+#[tauri::command]
+fn window_calc_render_resolution(app: tauri::AppHandle, label: Option<String>) -> Result<RenderResolution, String> {
+    let label = label.unwrap_or_else(|| "main".to_string());
+    let window = app.get_webview_window(&label)
+        .ok_or_else(|| format!("Window not found: {}", label))?;
+
+    let scale_factor = window.scale_factor()
+        .map_err(|e| format!("Failed to get scale factor: {}", e))?;
+    let logical = window.inner_size()
+        .map_err(|e| format!("Failed to get window size: {}", e))?
+        .to_logical::<f64>(scale_factor);
+
+    let limit = config_fetch_dpr_limit(&app);
+    let dpr = if limit > 0.0 { scale_factor.min(limit) } else { scale_factor };
+
+    Ok(RenderResolution {
+        width: (logical.width * dpr).round().max(1.0) as u32,
+        height: (logical.height * dpr).round().max(1.0) as u32,
+        dpr,
+    })
+}
+
+/// 显示器不可用时（无头或远程会话）使用的默认分辨率上限
+const DISPLAY_FALLBACK_MAX: (u32, u32) = (1920, 1080);
+
+/// 常见显示分辨率，按显示器尺寸筛选后作为候选项
+const COMMON_DISPLAY_RESOLUTIONS: [(u32, u32); 8] = [
+    (640, 480),
+    (800, 600),
+    (1280, 720),
+    (1280, 960),
+    (1600, 1200),
+    (1920, 1080),
+    (2560, 1440),
+    (3840, 2160),
+];
+
+/// 分辨率选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayResolution {
+    pub width: u32,
+    pub height: u32,
+    /// 是否为检测到的显示器原生分辨率
+    pub native: bool,
+}
+
+/// 生成分辨率候选列表（按面积从大到小，去重）
+///
+/// monitor_size 为 None 时表示无法获取显示器，回退到不超过 1920×1080 的常见分辨率，
+/// 并加入配置中的尺寸，保证列表非空且包含当前设置
+fn resolution_list_calc(monitor_size: Option<(u32, u32)>, configured: (u32, u32)) -> Vec<DisplayResolution> {
+    let (max_width, max_height) = monitor_size.unwrap_or(DISPLAY_FALLBACK_MAX);
+    let mut resolutions: Vec<DisplayResolution> = COMMON_DISPLAY_RESOLUTIONS.iter()
+        .filter(|&&(w, h)| w <= max_width && h <= max_height)
+        .map(|&(width, height)| DisplayResolution { width, height, native: false })
+        .collect();
+
+    match monitor_size {
+        Some((width, height)) => resolutions.push(DisplayResolution { width, height, native: true }),
+        None if configured.0 > 0 && configured.1 > 0 => {
+            resolutions.push(DisplayResolution { width: configured.0, height: configured.1, native: false });
+        }
+        None => {}
+    }
+
+    resolutions.sort_by(|a, b| (b.width as u64 * b.height as u64).cmp(&(a.width as u64 * a.height as u64))
+        .then(b.native.cmp(&a.native)));
+    resolutions.dedup_by(|a, b| a.width == b.width && a.height == b.height);
+    resolutions
+}
+
+/// Tauri IPC 命令：获取可选的显示分辨率列表
 ///
-/// 接收笔画数组（绘制/擦除/清空），在空白或给定底图上逐笔渲染，用于撤销缩略图生成
+/// 以主显示器的物理尺寸为上限筛选常见分辨率，并附上原生分辨率。
+/// 无法获取主显示器（无头或远程会话）时不报错，记录警告后回退到默认列表及配置中的 cameraWidth × cameraHeight
+///
+/// # 返回值
+/// * `Ok(Vec<DisplayResolution>)` — 按面积从大到小排列的分辨率，不会为空
 #[tauri::command]
-fn stroke_format_compact(request: CompactStrokesRequest) -> Result<String, String> {
-    let mut canvas: RgbaImage = ImageBuffer::new(request.canvas_width, request.canvas_height);
-    
-    for pixel in canvas.pixels_mut() {
-        *pixel = Rgba([0, 0, 0, 0]);
-    }
-    
-    if let Some(base_image_data) = request.base_image {
-        if let Ok(base_img) = image_load_base64(&base_image_data) {
-            let base_rgba = base_img.to_rgba8();
-            for (x, y, pixel) in base_rgba.enumerate_pixels() {
-                if x < canvas.width() && y < canvas.height() {
-                    canvas.put_pixel(x, y, *pixel);
-                }
-            }
-        }
-    }
-    
-    for stroke in &request.strokes {
-        let points = &stroke.points;
-        
-        if stroke.stroke_type == "clear" {
-            for pixel in canvas.pixels_mut() {
-                *pixel = Rgba([0, 0, 0, 0]);
-            }
-            continue;
-        }
-        
-        if points.is_empty() {
-            continue;
+fn display_fetch_resolutions(app: tauri::AppHandle) -> Result<Vec<DisplayResolution>, String> {
+    let monitor_size = match app.primary_monitor() {
+        Ok(Some(monitor)) => Some((monitor.size().width, monitor.size().height)),
+        Ok(None) => {
+            log::warn!("未检测到主显示器，使用默认分辨率列表");
+            None
         }
-        
-        if stroke.stroke_type == "draw" {
-            let color = color_calc_from_hex(stroke.color.as_deref().unwrap_or("#3498db"))
-                .unwrap_or(DEFAULT_COLOR);
-            let line_width = stroke.line_width.unwrap_or(2);
-            
-            for point in points {
-                canvas_render_line(
-                    &mut canvas,
-                    point.from_x as i32,
-                    point.from_y as i32,
-                    point.to_x as i32,
-                    point.to_y as i32,
-                    color,
-                    line_width,
-                );
-            }
-        } else if stroke.stroke_type == "erase" {
-            let eraser_size = stroke.eraser_size.unwrap_or(15);
-            
-            for point in points {
-                canvas_delete_line(
-                    &mut canvas,
-                    point.from_x as i32,
-                    point.from_y as i32,
-                    point.to_x as i32,
-                    point.to_y as i32,
-                    eraser_size,
-                );
-            }
+        Err(e) => {
+            log::warn!("获取主显示器失败，使用默认分辨率列表: {}", e);
+            None
         }
-    }
-    
-    let mut buffer = Vec::new();
-    DynamicImage::ImageRgba8(canvas)
-        .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
-        .map_err(|e| format!("Failed to encode compacted image: {}", e))?;
-    
-    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&buffer)))
+    };
+
+    let config = config_fetch_current(&app).unwrap_or_else(|_| config_fetch_default());
+    let configured_size = |key: &str| config.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let configured = (configured_size("cameraWidth"), configured_size("cameraHeight"));
+
+    Ok(resolution_list_calc(monitor_size, configured))
 }
 
 // ==================== 全局状态 ====================
@@ -1124,6 +2958,73 @@ fn app_fetch_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
+/// 实际编译进来的 image 版本，由 build.rs 从 Cargo.lock 读取
+const IMAGE_CRATE_VERSION: &str = env!("IMAGE_CRATE_VERSION");
+
+/// 构建与运行环境信息，供用户反馈格式支持或性能问题时附带
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// 构建配置："debug" 或 "release"
+    pub profile: String,
+    /// image 的实际版本（如 "0.25.9"），不是 Cargo.toml 中的版本要求
+    pub image_crate_version: String,
+    /// 可解码的图片格式（扩展名）。本 crate 未定义 cargo features，
+    /// 各图片格式由 image 的默认 features 决定，以下两个格式列表即代表实际启用的功能
+    pub decode_formats: Vec<String>,
+    /// 可编码的图片格式（扩展名）
+    pub encode_formats: Vec<String>,
+    pub os: String,
+    pub arch: String,
+    pub cpu_cores: usize,
+}
+
+/// Tauri IPC 命令：获取构建信息（版本、构建配置、支持的图片格式、系统与 CPU 核数）
+#[tauri::command]
+fn app_fetch_build_info() -> BuildInfo {
+    let formats = |enabled: fn(&image::ImageFormat) -> bool| -> Vec<String> {
+        image::ImageFormat::all()
+            .filter(enabled)
+            .filter_map(|format| format.extensions_str().first().map(|ext| ext.to_string()))
+            .collect()
+    };
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+        image_crate_version: IMAGE_CRATE_VERSION.to_string(),
+        decode_formats: formats(|format| format.reading_enabled()),
+        encode_formats: formats(|format| format.writing_enabled()),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpu_cores: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+    }
+}
+
+/// Tauri IPC 命令：预热耗时子系统，避免启动后首次图像处理卡顿
+///
+/// 初始化 rayon 全局线程池、解析打包字体，并完成一次极小图片的编解码以加载解码器，
+/// 在后台线程执行，不阻塞启动画面
+#[tauri::command]
+async fn app_init_warmup() -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(|| -> Result<(), String> {
+        let start = std::time::Instant::now();
+
+        let threads = rayon::current_num_threads();
+
+        text_processing::font_fetch_bundled()?;
+
+        let probe = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(2, 2, Rgba([0, 0, 0, 255])));
+        let encoded = image_encode_png_base64(&probe)?;
+        image_load_base64(&encoded)?;
+
+        log::info!("子系统预热完成: {} 个线程，耗时 {:?}", threads, start.elapsed());
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Warmup task failed: {}", e))?
+}
+
 /// Tauri IPC 命令：获取当前操作系统平台标识
 #[tauri::command]
 fn app_fetch_platform() -> String {
@@ -1290,6 +3191,44 @@ fn config_backup_corrupted(config_path: &std::path::Path) {
     }
 }
 
+/// 上一次可正常解析的配置备份路径（config.json.bak）
+fn config_calc_backup_path(config_path: &std::path::Path) -> PathBuf {
+    config_path.with_extension("json.bak")
+}
+
+/// 覆盖配置前，若当前配置可正常解析则复制为 .bak，作为断电等导致写坏时的恢复来源
+fn config_backup_last_good(config_path: &std::path::Path) {
+    let is_valid = std::fs::read_to_string(config_path)
+        .ok()
+        .is_some_and(|content| serde_json::from_str::<serde_json::Value>(&content).is_ok_and(|v| v.is_object()));
+    if !is_valid {
+        return;
+    }
+    if let Err(e) = std::fs::copy(config_path, config_calc_backup_path(config_path)) {
+        log::warn!("备份配置文件失败: {}", e);
+    }
+}
+
+/// 用 .bak 备份覆盖主配置文件，备份不存在或无法解析时返回 None
+fn config_restore_from_backup(config_path: &std::path::Path) -> Option<serde_json::Value> {
+    let backup_path = config_calc_backup_path(config_path);
+    let backup = std::fs::read_to_string(&backup_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .filter(|value| value.is_object())?;
+
+    let temp_path = config_path.with_extension("json.tmp");
+    let config_str = serde_json::to_string_pretty(&backup).ok()?;
+    if let Err(e) = std::fs::write(&temp_path, config_str).and_then(|_| std::fs::rename(&temp_path, config_path)) {
+        log::warn!("从备份恢复配置失败: {}", e);
+        let _ = std::fs::remove_file(&temp_path);
+        return None;
+    }
+
+    log::info!("已从备份恢复配置: {:?}", backup_path);
+    Some(backup)
+}
+
 /// 生成默认配置（各字段均设初始值）
 fn config_fetch_default() -> serde_json::Value {
     serde_json::json!({
@@ -1312,7 +3251,7 @@ fn config_fetch_default() -> serde_json::Value {
         "dprMax": 4,
         "dprStep": 0.5,
         "highFrameRate": false,
-        "smoothStrength": 0.5,
+        "smoothStrength": 0.8,
         "blurEffect": true,
         "penSizePresets": [2, 5, 10, 15, 21],
         "penColors": [
@@ -1340,10 +3279,34 @@ fn config_fetch_default() -> serde_json::Value {
         "denoiseFrameCount": 3,
         "denoiseStrength": "medium",
         "penEffectMode": "limited",
-        "memreductCleanEnabled": true
+        "memreductCleanEnabled": true,
+        "configVersion": CONFIG_VERSION
     })
 }
 
+/// 当前配置格式版本，写入 configVersion；缺少该字段的是 1 之前的旧配置
+const CONFIG_VERSION: u64 = 1;
+
+/// 旧版本写入配置的 smoothStrength 默认值
+const LEGACY_SMOOTH_STRENGTH: f64 = 0.5;
+
+/// 迁移旧版本配置，需在与默认配置合并之前调用
+///
+/// 旧配置的 smoothStrength 默认为 0.5，而前端画笔平滑一直使用 0.8；
+/// 设置界面没有该选项，0.5 只可能是写入的旧默认值，改为当前默认值使前后端平滑强度一致
+fn config_update_legacy(config: &mut serde_json::Value) {
+    let Some(obj) = config.as_object_mut() else {
+        return;
+    };
+    if obj.contains_key("configVersion") {
+        return;
+    }
+    if obj.get("smoothStrength").and_then(|v| v.as_f64()) == Some(LEGACY_SMOOTH_STRENGTH) {
+        obj.insert("smoothStrength".to_string(), config_fetch_default()["smoothStrength"].clone());
+        log::info!("已将旧配置的平滑强度迁移为新默认值");
+    }
+}
+
 /// JSON 值的类型名称（用于类型校验）
 fn json_type_name(v: &serde_json::Value) -> &'static str {
     match v {
@@ -1391,18 +3354,105 @@ fn config_validate_and_merge(
     defaults.clone()
 }
 
+/// 数值配置项的合法范围（含边界），超出范围的值恢复为默认值
+const CONFIG_NUMERIC_RANGES: &[(&str, f64, f64)] = &[
+    ("cameraWidth", 1.0, 16384.0),
+    ("cameraHeight", 1.0, 16384.0),
+    ("moveFps", 1.0, 240.0),
+    ("drawFps", 1.0, 240.0),
+    ("defaultRotation", 0.0, 359.0),
+    ("contrast", 0.1, 5.0),
+    ("brightness", -100.0, 100.0),
+    ("saturation", 0.0, 5.0),
+    ("sharpen", 0.0, 10.0),
+    ("canvasScale", 0.5, 8.0),
+    ("dprLimit", 0.0, 8.0),
+    ("dprMin", 0.5, 8.0),
+    ("dprMax", 0.5, 8.0),
+    ("dprStep", 0.1, 4.0),
+    ("smoothStrength", 0.0, 1.0),
+    ("autoClearCacheDays", 0.0, 3650.0),
+    ("denoiseFrameCount", 1.0, 10.0),
+];
+
+/// 校验数值配置项范围：超出范围或非有限值的字段恢复为默认值，并将字段名加入 recovered
+fn config_validate_ranges(
+    config: &mut serde_json::Value,
+    defaults: &serde_json::Value,
+    recovered: &mut Vec<String>,
+) {
+    let Some(obj) = config.as_object_mut() else {
+        return;
+    };
+
+    for &(key, min, max) in CONFIG_NUMERIC_RANGES {
+        let Some(value) = obj.get(key).and_then(|v| v.as_f64()) else {
+            continue;
+        };
+        if value.is_finite() && (min..=max).contains(&value) {
+            continue;
+        }
+        if let Some(default_val) = defaults.get(key) {
+            log::warn!("配置项 '{}' 超出范围 ({} 不在 {}..={})，已恢复默认值", key, value, min, max);
+            obj.insert(key.to_string(), default_val.clone());
+            recovered.push(key.to_string());
+        }
+    }
+}
+
+/// 启动时校验并修复配置文件
+///
+/// 仅处理能解析但字段类型错误或数值越界的配置（如 cameraWidth 为 0、saturation 为 "high"），
+/// 只替换问题字段，其余字段保持不变，修复后原子写回。
+/// 无法解析的文件留给 settings_fetch_all 备份并回退默认值。
+fn config_repair_at_startup(config_path: &std::path::Path) {
+    let Ok(content) = std::fs::read_to_string(config_path) else {
+        return;
+    };
+    let Ok(mut existing) = serde_json::from_str::<serde_json::Value>(&content) else {
+        log::warn!("配置文件无法解析，跳过启动修复");
+        return;
+    };
+    if !existing.is_object() {
+        return;
+    }
+    config_update_legacy(&mut existing);
+
+    let defaults = config_fetch_default();
+    let mut recovered: Vec<String> = Vec::new();
+    let mut repaired = config_validate_and_merge(&existing, &defaults, &mut recovered);
+    config_validate_ranges(&mut repaired, &defaults, &mut recovered);
+
+    if recovered.is_empty() {
+        return;
+    }
+
+    let temp_path = config_path.with_extension("json.tmp");
+    match write_atomic(&temp_path, config_path, &repaired) {
+        Ok(()) => log::info!("启动时已修复配置项: {}", recovered.join(", ")),
+        Err(e) => log::warn!("写回修复后的配置失败: {}", e),
+    }
+}
+
 /// settings_fetch_all 命令的返回结构
 #[derive(Serialize)]
 struct SettingsResult {
+    /// 合并默认值并校验后的完整配置
     settings: serde_json::Value,
+    /// 类型异常或数值越界、已恢复为默认值的字段名，为空时不序列化
     #[serde(skip_serializing_if = "Vec::is_empty")]
     recovered: Vec<String>,
 }
 
 /// Tauri IPC 命令：读取配置文件，校验并合并后返回完整配置。
 ///
-/// 配置文件不存在时返回默认配置；读取/解析失败时备份损坏文件并返回默认配置；
-/// 字段类型异常时自动恢复为默认值并记录到 recovered 列表。
+/// 配置文件不存在时返回默认配置；读取失败时备份损坏文件并返回默认配置；
+/// 无法解析时备份损坏文件并尝试从 config.json.bak 恢复，没有可用备份才返回默认配置；
+/// 字段类型异常或数值越界时自动恢复为默认值并记录到 recovered 列表。
+///
+/// # 返回值
+/// * `Ok(SettingsResult)` — settings 为完整配置；recovered 为恢复为默认值的字段名，
+///   没有时省略该字段。从备份恢复的配置同样经过校验，但不单独标记
 #[tauri::command]
 async fn settings_fetch_all(app: tauri::AppHandle) -> Result<SettingsResult, String> {
     let paths = AppPaths::new(&app)?;
@@ -1424,17 +3474,26 @@ async fn settings_fetch_all(app: tauri::AppHandle) -> Result<SettingsResult, Str
         }
     };
     
-    let existing_config = match serde_json::from_str::<serde_json::Value>(&config_content) {
+    let mut existing_config = match serde_json::from_str::<serde_json::Value>(&config_content) {
         Ok(v) => v,
         Err(e) => {
-            log::warn!("解析配置文件失败: {}，使用默认配置", e);
+            log::warn!("解析配置文件失败: {}，尝试从备份恢复", e);
             config_backup_corrupted(&config_path);
-            return Ok(SettingsResult { settings: default_config, recovered: Vec::new() });
+            match config_restore_from_backup(config_path) {
+                Some(restored) => restored,
+                None => {
+                    log::warn!("没有可用的配置备份，使用默认配置");
+                    return Ok(SettingsResult { settings: default_config, recovered: Vec::new() });
+                }
+            }
         }
     };
     
+    config_update_legacy(&mut existing_config);
+
     let mut recovered: Vec<String> = Vec::new();
-    let merged_config = config_validate_and_merge(&existing_config, &default_config, &mut recovered);
+    let mut merged_config = config_validate_and_merge(&existing_config, &default_config, &mut recovered);
+    config_validate_ranges(&mut merged_config, &default_config, &mut recovered);
     
     if merged_config != existing_config {
         let merged_str = serde_json::to_string_pretty(&merged_config)
@@ -1528,10 +3587,228 @@ async fn settings_save_all(app: tauri::AppHandle, settings: serde_json::Value) -
     write_atomic(&temp_path, &config_path, &existing_settings)
 }
 
+/// Tauri IPC 命令：检查配置文件，无法解析时从 config.json.bak 恢复
+///
+/// 每次保存配置前都会把上一份可解析的配置复制为 .bak，
+/// 因此断电等原因导致 config.json 被截断时可以找回用户设置
+///
+/// # 返回值
+/// * `Ok(true)` — 配置已从备份恢复
+/// * `Ok(false)` — 配置正常或不存在，无需修复
+///
+/// # 异常
+/// * 配置损坏且没有可用的备份
+#[tauri::command]
+async fn settings_repair_all(app: tauri::AppHandle) -> Result<bool, String> {
+    let paths = AppPaths::new(&app)?;
+    let config_path = &paths.config_path;
+
+    let Ok(content) = std::fs::read_to_string(config_path) else {
+        return Ok(false);
+    };
+    if serde_json::from_str::<serde_json::Value>(&content).is_ok_and(|v| v.is_object()) {
+        return Ok(false);
+    }
+
+    config_backup_corrupted(config_path);
+    config_restore_from_backup(config_path)
+        .map(|_| true)
+        .ok_or_else(|| "Config is corrupted and no valid backup is available".to_string())
+}
+
+/// 分享码中不包含的配置项：与本机设备或系统注册状态相关，不应随分享码迁移
+const CONFIG_SHARE_EXCLUDED_KEYS: &[&str] = &[
+    "defaultCamera",
+    "lastCacheClearDate",
+    "fileAssociations",
+    "wordAssociations",
+    "configVersion",
+];
+
+/// 分享码解压后允许的最大字节数，防止恶意构造的压缩数据占用大量内存
+const CONFIG_SHARE_MAX_SIZE: u64 = 64 * 1024;
+
+/// 读取当前配置（校验合并后），文件不存在或损坏时返回默认配置
+fn config_fetch_current(app: &tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let paths = AppPaths::new(app)?;
+    let defaults = config_fetch_default();
+
+    let existing = std::fs::read_to_string(&paths.config_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+
+    Ok(match existing {
+        Some(mut existing) => {
+            config_update_legacy(&mut existing);
+            let mut recovered = Vec::new();
+            let mut merged = config_validate_and_merge(&existing, &defaults, &mut recovered);
+            config_validate_ranges(&mut merged, &defaults, &mut recovered);
+            merged
+        }
+        None => defaults,
+    })
+}
+
+/// Tauri IPC 命令：从配置的画笔颜色中挑选与图像背景对比度最高的颜色
+///
+/// 以图像（或区域）叠加到白色背景后的平均颜色为背景，按 WCAG 对比度比较 penColors，
+/// 使批注在深色和浅色背景上都清晰可见；调色板为空时在黑白之间选择
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `region` — 可选采样区域 [x, y, width, height]，缺省为整张图片
+///
+/// # 返回值
+/// * `Ok(RgbaColor)` — 推荐的画笔颜色（alpha 固定为 255）
+///
+/// # 异常
+/// * base64 解析失败或图像格式不支持
+/// * 区域为空或超出图像边界
+#[tauri::command]
+fn image_calc_pen_color(app: tauri::AppHandle, image_data: String, region: Option<[u32; 4]>) -> Result<RgbaColor, String> {
+    let background = image_calc_background_color(&image_data, region)?;
+    let config = config_fetch_current(&app)?;
+
+    let channel = |color: &serde_json::Value, key: &str| {
+        color.get(key).and_then(|v| v.as_u64()).map(|v| v.min(255) as u8)
+    };
+    let mut palette: Vec<[u8; 3]> = config.get("penColors")
+        .and_then(|v| v.as_array())
+        .map(|colors| {
+            colors.iter()
+                .filter_map(|c| Some([channel(c, "r")?, channel(c, "g")?, channel(c, "b")?]))
+                .collect()
+        })
+        .unwrap_or_default();
+    if palette.is_empty() {
+        palette = vec![[0, 0, 0], [255, 255, 255]];
+    }
+
+    let mut best = palette[0];
+    let mut best_ratio = color_calc_contrast_ratio(best, background);
+    for &color in &palette[1..] {
+        let ratio = color_calc_contrast_ratio(color, background);
+        if ratio > best_ratio {
+            best = color;
+            best_ratio = ratio;
+        }
+    }
+
+    Ok(RgbaColor { r: best[0], g: best[1], b: best[2], a: 255 })
+}
+
+/// Tauri IPC 命令：将当前配置导出为分享码
+///
+/// 只包含与默认值不同的配置项（不含本机相关项），JSON 经 deflate 压缩后
+/// 以 URL 安全的 base64 编码，便于反馈问题或分享配置
+///
+/// # 返回值
+/// * `Ok(String)` — 分享码
+///
+/// # 异常
+/// * 配置目录获取失败
+/// * 序列化或压缩失败
+#[tauri::command]
+fn settings_format_code(app: tauri::AppHandle) -> Result<String, String> {
+    config_format_share_code(&config_fetch_current(&app)?)
+}
+
+/// 将配置中与默认值不同的项（不含本机相关项）压缩编码为分享码
+fn config_format_share_code(current: &serde_json::Value) -> Result<String, String> {
+    let defaults = config_fetch_default();
+
+    let mut changed = serde_json::Map::new();
+    if let (Some(current_obj), Some(defaults_obj)) = (current.as_object(), defaults.as_object()) {
+        for (key, default_val) in defaults_obj {
+            if CONFIG_SHARE_EXCLUDED_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            if let Some(value) = current_obj.get(key).filter(|value| *value != default_val) {
+                changed.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    let json = serde_json::to_vec(&serde_json::Value::Object(changed))
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&json)
+        .map_err(|e| format!("Failed to compress settings: {}", e))?;
+    let compressed = encoder.finish()
+        .map_err(|e| format!("Failed to compress settings: {}", e))?;
+
+    Ok(general_purpose::URL_SAFE_NO_PAD.encode(compressed))
+}
+
+/// Tauri IPC 命令：应用分享码中的配置
+///
+/// 分享码中未出现的配置项恢复为默认值（本机相关项保持不变），
+/// 应用后的配置与导出方一致。所有字段先做类型和范围校验，任一字段非法则整体拒绝。
+///
+/// # 参数
+/// * `code` — settings_format_code 生成的分享码
+///
+/// # 异常
+/// * 分享码解码、解压或解析失败
+/// * 包含未知字段、类型错误或数值越界的字段
+/// * 配置写入失败
+#[tauri::command]
+async fn settings_apply_code(app: tauri::AppHandle, code: String) -> Result<(), String> {
+    let validated = config_parse_share_code(&code)?;
+    settings_save_all(app, validated).await?;
+    log::info!("已应用分享码配置");
+    Ok(())
+}
+
+/// 解码并校验分享码，返回合并默认值后的完整配置（已移除本机相关项）
+fn config_parse_share_code(code: &str) -> Result<serde_json::Value, String> {
+    let compressed = general_purpose::URL_SAFE_NO_PAD.decode(code.trim())
+        .map_err(|e| format!("Invalid settings code: {}", e))?;
+
+    let mut json = Vec::new();
+    flate2::read::DeflateDecoder::new(compressed.as_slice())
+        .take(CONFIG_SHARE_MAX_SIZE + 1)
+        .read_to_end(&mut json)
+        .map_err(|e| format!("Invalid settings code: {}", e))?;
+    if json.len() as u64 > CONFIG_SHARE_MAX_SIZE {
+        return Err("Settings code is too large".to_string());
+    }
+
+    let shared: serde_json::Value = serde_json::from_slice(&json)
+        .map_err(|e| format!("Invalid settings code: {}", e))?;
+    let shared_obj = shared.as_object()
+        .ok_or_else(|| "Invalid settings code: expected an object".to_string())?;
+
+    let defaults = config_fetch_default();
+    for key in shared_obj.keys() {
+        if defaults.get(key).is_none() || CONFIG_SHARE_EXCLUDED_KEYS.contains(&key.as_str()) {
+            return Err(format!("Settings code contains unsupported key: {}", key));
+        }
+    }
+
+    let mut recovered = Vec::new();
+    let mut validated = config_validate_and_merge(&shared, &defaults, &mut recovered);
+    config_validate_ranges(&mut validated, &defaults, &mut recovered);
+    if !recovered.is_empty() {
+        return Err(format!("Settings code contains invalid values: {}", recovered.join(", ")));
+    }
+
+    if let Some(obj) = validated.as_object_mut() {
+        for key in CONFIG_SHARE_EXCLUDED_KEYS {
+            obj.remove(*key);
+        }
+    }
+
+    log::info!("分享码包含 {} 项配置", shared_obj.len());
+    Ok(validated)
+}
+
 /// 原子写入 JSON 到文件（临时文件 + rename）
 fn write_atomic(temp_path: &std::path::Path, config_path: &std::path::Path, value: &serde_json::Value) -> Result<(), String> {
     let config_str = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
     std::fs::write(&temp_path, &config_str).map_err(|e| e.to_string())?;
+    config_backup_last_good(config_path);
     std::fs::rename(&temp_path, &config_path).map_err(|e| {
         let _ = std::fs::remove_file(&temp_path);
         format!("Failed to rename config file: {}", e)
@@ -3419,6 +5696,10 @@ pub fn app_init_run() {
             
             let is_first_run = !config_path.exists();
             
+            if !is_first_run {
+                config_repair_at_startup(&config_path);
+            }
+            
             if is_first_run {
                 println!("首次运行，打开 OOBE 界面");
                 
@@ -3499,19 +5780,112 @@ pub fn app_init_run() {
             theme_get_preview,
             image_update_rotation,
             image_update_adjustments,
+            image_update_region,
+            image_update_invert,
+            image_update_alpha_threshold,
+            image_update_pattern_fill,
+            image_fetch_region_color,
+            image_format_mipmaps,
+            image_fetch_pixel_color,
+            image_format_smart_crop,
+            image_format_content_aware,
+            image_calc_ssim,
+            image_update_chromatic_aberration,
+            image_update_posterize,
+            image_update_rotation_angle,
+            image_update_auto_enhance,
+            image_render_test_pattern,
+            image_format_tiles,
+            image_format_untile,
+            image_list_presets,
+            image_update_preset,
+            image_format_gif,
+            image_format_indexed_png,
+            image_update_region_enhance,
+            image_calc_document_corners,
+            image_format_dewarp,
+            image_load_proxy,
+            image_update_proxy,
+            image_delete_proxy,
+            image_update_hue_range,
+            image_format_warp,
+            image_update_replace_color,
+            image_format_placeholder,
+            image_format_blurhash,
+            image_format_smart_thumbnail,
+            image_fetch_color_profile,
+            image_format_srgb,
+            image_calc_pen_color,
+            image_format_downscale_exif,
+            image_format_thumbnails,
+            image_calc_brightness,
+            image_update_color_transfer,
+            image_update_duotone,
+            image_calc_color_counts,
+            image_calc_crop_guides,
+            image_format_pasted,
+            image_format_letterbox,
+            image_update_enhance_thumbnail,
+            image_update_motion_blur,
+            image_update_tilt_shift,
+            image_update_stack_frames,
+            image_calc_ink_coverage,
+            image_update_vignette_correction,
             image_save_file,
+            image_update_file_rotation,
+            image_export_zip,
+            image_convert_files,
+            image_save_enhanced_copy,
+            image_update_file_names,
+            dir_fetch_pictures_usage,
+            image_delete_old_files,
+            camera_calc_best_resolution,
             stroke_format_compact,
+            stroke_render_layer,
+            stroke_render_shadow,
+            stroke_format_compact_region,
+            stroke_load_stream,
+            stroke_update_stream,
+            stroke_format_stream,
+            stroke_delete_stream,
+            stroke_format_smooth_default,
+            stroke_format_finalize,
+            stroke_detect_eraser_collision,
+            stroke_calc_convex_hull,
+            stroke_calc_min_area_rect,
+            stroke_format_simplify,
+            stroke_calc_pressure_curve,
+            stroke_format_resolve_order,
+            stroke_format_clip,
+            stroke_calc_winding,
+            stroke_update_distance_field,
+            stroke_format_transform_layers,
+            stroke_format_merge,
+            stroke_calc_outline,
+            stroke_detect_duplicates,
+            stroke_calc_stats,
+            stroke_format_normalize,
+            stroke_format_denormalize,
+            text_render_image,
+            text_calc_metrics,
+            window_calc_render_resolution,
+            display_fetch_resolutions,
             window_show_settings,
             mirror_update_state,
             mirror_fetch_state,
             app_fetch_version,
+            app_fetch_build_info,
             app_fetch_platform,
+            app_init_warmup,
             update_fetch_check,
             update_download_file,
             update_download_cancel,
             update_install_release,
             settings_fetch_all,
             settings_save_all,
+            settings_repair_all,
+            settings_format_code,
+            settings_apply_code,
             settings_delete_all,
             app_restart_process,
             filetype_validate_pdf_default,
@@ -3532,3 +5906,601 @@ pub fn app_init_run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_list_falls_back_without_monitor() {
+        let resolutions = resolution_list_calc(None, (1024, 768));
+        assert!(!resolutions.is_empty());
+        assert!(resolutions.iter().all(|r| r.width <= 1920 && r.height <= 1080 && !r.native));
+        assert!(resolutions.iter().any(|r| (r.width, r.height) == (1024, 768)));
+        assert!(resolutions.windows(2).all(|w| w[0].width * w[0].height >= w[1].width * w[1].height));
+    }
+
+    /// 在系统临时目录下创建测试专用的空目录
+    fn test_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("viewstage_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn file_convert_format_refuses_to_overwrite() {
+        let dir = test_temp_dir("convert");
+        let source = dir.join("a.png");
+        RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255])).save(&source).unwrap();
+        std::fs::write(dir.join("a.jpg"), b"existing").unwrap();
+
+        let result = file_convert_format(&source, image::ImageFormat::Jpeg, "jpg", 90, true);
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(dir.join("a.jpg")).unwrap(), b"existing");
+        assert!(source.exists());
+
+        std::fs::remove_file(dir.join("a.jpg")).unwrap();
+        let output = file_convert_format(&source, image::ImageFormat::Jpeg, "jpg", 90, true).unwrap();
+        assert_eq!(output, dir.join("a.jpg"));
+        assert!(image::open(&output).is_ok());
+        assert!(!source.exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_update_names_follows_pattern_without_collisions() {
+        let dir = test_temp_dir("rename");
+        for name in ["b.png", "a.PNG", "c.jpg", "20240101_001.png"] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+        let root = std::fs::canonicalize(&dir).unwrap();
+        let mut files = Vec::new();
+        file_collect_images(&dir, &mut files).unwrap();
+        assert_eq!(files.len(), 4);
+
+        let renamed = file_update_names_in_dir(files, "shot_{index}", &root);
+        assert_eq!(renamed, 4);
+        let mut names: Vec<String> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 4);
+        assert!(names.iter().all(|name| name.starts_with("shot_00")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_collect_images_skips_symlinks() {
+        let dir = test_temp_dir("symlink");
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub").join("a.png"), b"x").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("sub").join("a.png"), dir.join("link.png")).unwrap();
+
+        let mut files = Vec::new();
+        file_collect_images(&dir, &mut files).unwrap();
+        assert_eq!(files, vec![dir.join("sub").join("a.png")]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stroke_parse_stream_accepts_array_and_json_lines() {
+        let stroke = r##"{"type":"draw","points":[{"from_x":0,"from_y":0,"to_x":1,"to_y":1}],"color":"#ff0000","line_width":2}"##;
+        let mut types = Vec::new();
+        let count = stroke_parse_stream(&format!("[{0},{0}]", stroke), |s| types.push(s.stroke_type)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(stroke_parse_stream(&format!("{0}\n{0}\n{0}\n", stroke), |_| {}).unwrap(), 3);
+        assert!(stroke_parse_stream("[{\"type\":1}]", |_| {}).is_err());
+    }
+
+    #[test]
+    fn stroke_stream_renders_chunks_in_order() {
+        let session = StrokeStreamSession {
+            canvas: RgbaImage::new(20, 20),
+            restore: None,
+            dpr: 1.0,
+            stroke_count: 0,
+        };
+        let id = STROKE_STREAM_NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        STROKE_STREAMS.lock().unwrap().insert(id, (std::time::Instant::now(), Arc::new(Mutex::new(session))));
+
+        let draw = r##"[{"type":"draw","points":[{"from_x":2,"from_y":10,"to_x":18,"to_y":10}],"color":"#ff0000","line_width":4}]"##;
+        let erase = r##"{"type":"erase","points":[{"from_x":2,"from_y":10,"to_x":6,"to_y":10}],"eraser_size":6}"##;
+        assert_eq!(tauri::async_runtime::block_on(stroke_update_stream(id, draw.to_string())).unwrap(), 1);
+        assert_eq!(tauri::async_runtime::block_on(stroke_update_stream(id, erase.to_string())).unwrap(), 2);
+
+        let data = tauri::async_runtime::block_on(stroke_format_stream(id)).unwrap();
+        let canvas = image_load_base64(&data).unwrap().to_rgba8();
+        assert_eq!(canvas.get_pixel(14, 10)[0], 255);
+        assert_eq!(canvas.get_pixel(3, 10)[3], 0);
+        assert!(stroke_fetch_stream(id).is_err());
+    }
+
+    #[test]
+    fn file_write_with_progress_reports_increasing_fractions() {
+        let dir = test_temp_dir("write_progress");
+        let path = dir.join("out.bin");
+        let data = vec![7u8; SAVE_PROGRESS_CHUNK_SIZE * 2 + 10];
+        let reported = std::cell::RefCell::new(Vec::new());
+        file_write_with_progress(&path, &data, |p| reported.borrow_mut().push(p)).unwrap();
+
+        let reported = reported.into_inner();
+        assert_eq!(reported.len(), 3);
+        assert!(reported.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(*reported.last().unwrap(), 1.0);
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn config_update_legacy_migrates_old_smooth_default_once() {
+        let mut legacy = serde_json::json!({ "smoothStrength": 0.5 });
+        config_update_legacy(&mut legacy);
+        assert_eq!(legacy["smoothStrength"], config_fetch_default()["smoothStrength"]);
+
+        let mut chosen = serde_json::json!({ "smoothStrength": 0.5, "configVersion": CONFIG_VERSION });
+        config_update_legacy(&mut chosen);
+        assert_eq!(chosen["smoothStrength"], 0.5);
+
+        let mut custom = serde_json::json!({ "smoothStrength": 0.3 });
+        config_update_legacy(&mut custom);
+        assert_eq!(custom["smoothStrength"], 0.3);
+    }
+
+    #[test]
+    fn compact_dpr_scales_canvas_and_strokes() {
+        let request: CompactStrokesRequest = serde_json::from_value(serde_json::json!({
+            "canvas_width": 100,
+            "canvas_height": 50,
+            "dpr": 2.0,
+            "strokes": [{
+                "type": "draw",
+                "points": [{ "from_x": 10.0, "from_y": 5.0, "to_x": 30.0, "to_y": 20.0 }],
+            }],
+        })).unwrap();
+
+        let (scaled, dpr) = compact_calc_dpr(request.clone(), 0.0).unwrap();
+        assert_eq!(dpr, 2.0);
+        assert_eq!((scaled.canvas_width, scaled.canvas_height), (200, 100));
+        let segment = &scaled.strokes[0].points[0];
+        assert_eq!((segment.from_x, segment.from_y, segment.to_x, segment.to_y), (20.0, 10.0, 60.0, 40.0));
+
+        let (limited, dpr) = compact_calc_dpr(request, 1.5).unwrap();
+        assert_eq!(dpr, 1.5);
+        assert_eq!((limited.canvas_width, limited.canvas_height), (150, 75));
+    }
+
+    #[test]
+    fn config_restore_from_backup_recovers_truncated_config() {
+        let dir = test_temp_dir("config_backup");
+        let config_path = dir.join("config.json");
+        let temp_path = config_path.with_extension("json.tmp");
+        let good = serde_json::json!({ "language": "en-US", "moveFps": 60 });
+
+        write_atomic(&temp_path, &config_path, &good).unwrap();
+        write_atomic(&temp_path, &config_path, &serde_json::json!({ "language": "en-US", "moveFps": 90 })).unwrap();
+        std::fs::write(&config_path, "{\"language\": \"en-").unwrap();
+
+        assert_eq!(config_restore_from_backup(&config_path), Some(good.clone()));
+        let restored: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(restored, good);
+
+        std::fs::remove_file(config_calc_backup_path(&config_path)).unwrap();
+        assert_eq!(config_restore_from_backup(&config_path), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stroke_finalize_smooths_noisy_stroke() {
+        // 平缓的正弦曲线，每个采样点加 ±1 像素的伪随机抖动
+        let mut seed = 11u32;
+        let mut jitter = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % 201) as f32 / 100.0 - 1.0
+        };
+        let noisy: Vec<PathPoint> = (0..200)
+            .map(|i| {
+                let x = i as f32;
+                PathPoint { x: x + jitter(), y: 50.0 + 20.0 * (x / 40.0).sin() + jitter() }
+            })
+            .collect();
+        let total_turning = |points: &[PathPoint]| -> f32 {
+            points.windows(3)
+                .map(|w| {
+                    let a = (w[1].y - w[0].y).atan2(w[1].x - w[0].x);
+                    let b = (w[2].y - w[1].y).atan2(w[2].x - w[1].x);
+                    let turn = (b - a).abs();
+                    turn.min(std::f32::consts::TAU - turn)
+                })
+                .sum()
+        };
+        let request = FinalizeStrokeRequest {
+            points: noisy.clone(),
+            quantize: None,
+            spacing: None,
+            smoothness: None,
+            tolerance: None,
+        };
+
+        let finalized = stroke_calc_finalize(&request, 0.8).unwrap();
+        assert!(total_turning(&finalized) < total_turning(&noisy) / 5.0);
+        assert!((2..=noisy.len() / 2).contains(&finalized.len()), "{} points", finalized.len());
+
+        let single = FinalizeStrokeRequest { points: vec![PathPoint { x: 1.3, y: 2.7 }], ..request };
+        let result = stroke_calc_finalize(&single, 0.8).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!((result[0].x, result[0].y), (1.3, 2.7));
+    }
+
+    #[test]
+    fn file_delete_old_images_only_removes_old_dated_folders() {
+        let base = test_temp_dir("purge_old");
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        for (date, name) in [("2024-05-01", "old.png"), ("2024-06-29", "recent.png"), ("notes", "keep.png")] {
+            std::fs::create_dir_all(base.join(date)).unwrap();
+            std::fs::write(base.join(date).join(name), b"png").unwrap();
+        }
+        std::fs::write(base.join("2024-05-01").join("readme.txt"), b"text").unwrap();
+
+        let removed = file_delete_old_images(&base, today - chrono::Duration::days(15), false).unwrap();
+        assert_eq!(removed, 1);
+        assert!(!base.join("2024-05-01").join("old.png").exists());
+        assert!(base.join("2024-05-01").join("readme.txt").exists());
+        assert!(base.join("2024-06-29").join("recent.png").exists());
+        assert!(base.join("notes").join("keep.png").exists());
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn zip_write_images_stores_each_image() {
+        let dir = test_temp_dir("zip_export");
+        let zip_path = dir.join("export.zip");
+        let images: Vec<String> = (0..3u8)
+            .map(|i| image_encode_png_base64(&DynamicImage::ImageRgba8(ImageBuffer::from_pixel(4, 3, Rgba([i * 80, 0, 0, 255])))).unwrap())
+            .collect();
+        let progress = std::cell::RefCell::new(Vec::new());
+
+        let file = std::fs::File::create(&zip_path).unwrap();
+        let count = zip_write_images(file, &images, image::ImageFormat::Png, "png", 90, |current, total| {
+            progress.borrow_mut().push((current, total));
+        }).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(progress.into_inner(), vec![(1, 3), (2, 3), (3, 3)]);
+
+        let mut archive = ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 3);
+        for i in 0..3 {
+            let mut entry = archive.by_index(i).unwrap();
+            assert_eq!(entry.name(), format!("image_{:03}.png", i + 1));
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).unwrap();
+            let img = image::load_from_memory(&bytes).unwrap();
+            assert_eq!((img.width(), img.height()), (4, 3));
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn erase_to_base_restores_photo_pixels() {
+        let photo: RgbaImage = ImageBuffer::from_fn(40, 40, |x, y| Rgba([x as u8 * 6, y as u8 * 6, 90, 255]));
+        let strokes: Vec<Stroke> = serde_json::from_value(serde_json::json!([
+            { "type": "draw", "color": "#FF0000", "line_width": 6,
+              "points": [{ "from_x": 5.0, "from_y": 20.0, "to_x": 35.0, "to_y": 20.0 }] },
+            { "type": "erase", "eraser_size": 20,
+              "points": [{ "from_x": 5.0, "from_y": 20.0, "to_x": 35.0, "to_y": 20.0 }] },
+        ])).unwrap();
+
+        let mut restored = photo.clone();
+        canvas_render_strokes(&mut restored, &strokes, Some(&photo));
+        assert_eq!(restored, photo);
+
+        let mut transparent = photo.clone();
+        canvas_render_strokes(&mut transparent, &strokes, None);
+        assert_eq!(transparent.get_pixel(20, 20)[3], 0);
+        assert_eq!(compact_parse_erase_to_base(Some("base")), Ok(true));
+        assert!(compact_parse_erase_to_base(Some("white")).is_err());
+    }
+
+    #[test]
+    fn config_repair_at_startup_replaces_only_bad_fields() {
+        let dir = test_temp_dir("config_repair");
+        let config_path = dir.join("config.json");
+        let config = serde_json::json!({
+            "configVersion": CONFIG_VERSION,
+            "cameraWidth": 0,
+            "saturation": "high",
+            "moveFps": 60,
+            "language": "en-US",
+        });
+        std::fs::write(&config_path, serde_json::to_string(&config).unwrap()).unwrap();
+
+        config_repair_at_startup(&config_path);
+
+        let defaults = config_fetch_default();
+        let repaired: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(repaired["cameraWidth"], defaults["cameraWidth"]);
+        assert_eq!(repaired["saturation"], defaults["saturation"]);
+        assert_eq!(repaired["moveFps"], 60);
+        assert_eq!(repaired["language"], "en-US");
+        // 原子写入不应残留临时文件
+        assert!(!config_path.with_extension("json.tmp").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn canvas_validate_size_rejects_empty_and_oversized() {
+        let empty = canvas_validate_size(0, 0).unwrap_err();
+        assert!(empty.contains("0x0"), "{}", empty);
+        assert!(canvas_validate_size(0, 100).is_err());
+
+        let huge = canvas_validate_size(MAX_CANVAS_DIMENSION + 1, 100).unwrap_err();
+        assert!(huge.contains("exceeds"), "{}", huge);
+        assert!(canvas_validate_size(100, u32::MAX).is_err());
+
+        assert!(canvas_validate_size(1, 1).is_ok());
+        assert!(canvas_validate_size(MAX_CANVAS_DIMENSION, MAX_CANVAS_DIMENSION).is_ok());
+    }
+
+    #[test]
+    fn settings_share_code_round_trips_changed_keys_only() {
+        let mut current = config_fetch_default();
+        current["language"] = serde_json::json!("en-US");
+        current["moveFps"] = serde_json::json!(60);
+        current["defaultCamera"] = serde_json::json!("USB Camera");
+
+        let code = config_format_share_code(&current).unwrap();
+
+        // 分享码只包含与默认值不同的项，本机相关项不导出
+        let compressed = general_purpose::URL_SAFE_NO_PAD.decode(&code).unwrap();
+        let mut json = Vec::new();
+        flate2::read::DeflateDecoder::new(compressed.as_slice()).read_to_end(&mut json).unwrap();
+        let encoded: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(encoded, serde_json::json!({ "language": "en-US", "moveFps": 60 }));
+
+        let applied = config_parse_share_code(&code).unwrap();
+        assert_eq!(applied["language"], "en-US");
+        assert_eq!(applied["moveFps"], 60);
+        assert_eq!(applied["drawFps"], config_fetch_default()["drawFps"]);
+        assert!(applied.get("defaultCamera").is_none());
+
+        assert!(config_parse_share_code("not a code").is_err());
+    }
+
+    #[test]
+    fn smoothing_huge_path_is_downsampled_and_bounded() {
+        let points: Vec<PathPoint> = (0..100_000)
+            .map(|i| PathPoint { x: i as f32 * 0.1, y: (i as f32 * 0.01).sin() * 50.0 })
+            .collect();
+
+        let started = std::time::Instant::now();
+        let sampled = point_calc_downsample(&points, SMOOTH_MAX_POINTS);
+        let smoothed = stroke_calc_smooth_points(&sampled, 0.8);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5), "took {:?}", started.elapsed());
+
+        assert_eq!(sampled.len(), SMOOTH_MAX_POINTS);
+        assert_eq!(sampled.first().unwrap().x, points.first().unwrap().x);
+        assert_eq!(sampled.last().unwrap().x, points.last().unwrap().x);
+        assert!(!smoothed.is_empty());
+        assert!(smoothed.len() < points.len());
+        assert_eq!(point_calc_downsample(&points[..10], SMOOTH_MAX_POINTS).len(), 10);
+    }
+
+    #[test]
+    fn dir_calc_usage_totals_by_date() {
+        let base = test_temp_dir("storage_usage");
+        for (dir, name, size) in [
+            ("2024-05-01", "a.png", 100),
+            ("2024-05-01", "b.jpg", 50),
+            ("2024-06-02", "c.png", 30),
+            ("2024-06-02/sub", "d.png", 20),
+        ] {
+            std::fs::create_dir_all(base.join(dir)).unwrap();
+            std::fs::write(base.join(dir).join(name), vec![0u8; size]).unwrap();
+        }
+        // 非图片文件不计入
+        std::fs::write(base.join("2024-06-02").join("notes.txt"), vec![0u8; 999]).unwrap();
+
+        let usage = dir_calc_usage(&base).unwrap();
+        assert_eq!(usage.total_bytes, 200);
+        assert_eq!(usage.file_count, 4);
+        let by_date: Vec<_> = usage.by_date.iter().map(|d| (d.date.as_str(), d.bytes, d.count)).collect();
+        assert_eq!(by_date, vec![("2024-05-01", 150, 2), ("2024-06-02", 50, 2)]);
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn zip_export_failure_keeps_existing_file() {
+        let dir = test_temp_dir("zip_export_keep");
+        let zip_path = dir.join("export.zip");
+        std::fs::write(&zip_path, b"keep me").unwrap();
+
+        let images = vec![
+            image_encode_png_base64(&DynamicImage::ImageRgba8(ImageBuffer::from_pixel(4, 3, Rgba([0, 0, 0, 255])))).unwrap(),
+            "not an image".to_string(),
+        ];
+        assert!(zip_export_file(&zip_path, &images, image::ImageFormat::Png, "png", 90, |_, _| {}).is_err());
+        assert_eq!(std::fs::read(&zip_path).unwrap(), b"keep me");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // 成功时替换为新的 ZIP
+        let count = zip_export_file(&zip_path, &images[..1], image::ImageFormat::Png, "png", 90, |_, _| {}).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap().len(), 1);
+
+        assert!(path_validate_output("export.zip").is_err());
+        assert!(path_validate_output(dir.join("missing").join("export.zip").to_str().unwrap()).is_err());
+        assert_eq!(path_validate_output(zip_path.to_str().unwrap()).unwrap(), zip_path);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_update_rotation_rewrites_jpeg_orientation_losslessly() {
+        use image::ImageDecoder;
+
+        let dir = test_temp_dir("rotate_file");
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(8, 4, |x, _| Rgba([x as u8 * 30, 0, 0, 255])));
+        let orientation = |path: &std::path::Path| {
+            let bytes = std::fs::read(path).unwrap();
+            let mut decoder = image::codecs::jpeg::JpegDecoder::new(std::io::Cursor::new(bytes)).unwrap();
+            decoder.orientation().unwrap()
+        };
+
+        // 没有 EXIF：在 JFIF APP0 之后插入方向标签，其余字节不变
+        let jpeg_path = dir.join("photo.jpg");
+        let original = image_encode_jpeg_with_metadata(&img, 90, ChromaSubsampling::default(), None, None).unwrap();
+        std::fs::write(&jpeg_path, &original).unwrap();
+        file_update_rotation(&jpeg_path, true).unwrap();
+        let rotated = std::fs::read(&jpeg_path).unwrap();
+        let insert_at = 4 + u16::from_be_bytes([original[4], original[5]]) as usize;
+        assert_eq!(rotated.len(), original.len() + 36);
+        assert_eq!(rotated[..insert_at], original[..insert_at]);
+        assert_eq!(rotated[insert_at + 36..], original[insert_at..]);
+        assert_eq!(orientation(&jpeg_path), image::metadata::Orientation::Rotate90);
+
+        file_update_rotation(&jpeg_path, true).unwrap();
+        assert_eq!(orientation(&jpeg_path), image::metadata::Orientation::Rotate180);
+        file_update_rotation(&jpeg_path, false).unwrap();
+        file_update_rotation(&jpeg_path, false).unwrap();
+        assert_eq!(orientation(&jpeg_path), image::metadata::Orientation::NoTransforms);
+        assert_eq!(std::fs::read(&jpeg_path).unwrap().len(), rotated.len());
+
+        // EXIF 中没有方向标签：重新编码旋转像素并保留 EXIF
+        let mut tiff = b"II\x2a\x00\x08\x00\x00\x00\x01\x00".to_vec();
+        tiff.extend_from_slice(&[0x32, 0x01, 2, 0, 20, 0, 0, 0, 26, 0, 0, 0]);
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(b"2024:05:01 10:20:30\0");
+        let app1 = [b"Exif\0\0".as_slice(), &tiff].concat();
+        let exif_path = dir.join("exif.jpg");
+        std::fs::write(&exif_path, image_encode_jpeg_with_metadata(&img, 90, ChromaSubsampling::default(), Some(&app1), None).unwrap()).unwrap();
+        file_update_rotation(&exif_path, false).unwrap();
+        let bytes = std::fs::read(&exif_path).unwrap();
+        let mut decoder = image::codecs::jpeg::JpegDecoder::new(std::io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(decoder.dimensions(), (4, 8));
+        assert_eq!(decoder.exif_metadata().unwrap().unwrap(), tiff);
+
+        let png_path = dir.join("image.png");
+        std::fs::write(&png_path, image_encode_bytes(&img, image::ImageFormat::Png, 90).unwrap()).unwrap();
+        file_update_rotation(&png_path, true).unwrap();
+        let rotated = image::open(&png_path).unwrap();
+        assert_eq!((rotated.width(), rotated.height()), (4, 8));
+
+        let invalid = tauri::async_runtime::block_on(image_update_file_rotation(
+            png_path.to_string_lossy().to_string(),
+            "up".to_string(),
+        ));
+        assert!(invalid.unwrap_err().contains("Invalid rotation direction"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_claim_unique_name_skips_taken_names() {
+        let dir = test_temp_dir("claim_unique");
+        std::fs::write(dir.join("photo_enhanced.png"), b"first").unwrap();
+        std::fs::write(dir.join("photo_enhanced_1.png"), b"second").unwrap();
+
+        let create = |target: &std::path::Path| std::fs::OpenOptions::new().write(true).create_new(true).open(target);
+        let (target, _) = file_claim_unique_name(&dir, "photo_enhanced", "png", create).unwrap();
+        assert_eq!(target, dir.join("photo_enhanced_2.png"));
+        assert_eq!(std::fs::read(dir.join("photo_enhanced.png")).unwrap(), b"first");
+        assert_eq!(std::fs::read(dir.join("photo_enhanced_1.png")).unwrap(), b"second");
+
+        let (target, _) = file_claim_unique_name(&dir, "other", "png", create).unwrap();
+        assert_eq!(target, dir.join("other.png"));
+        assert!(file_claim_unique_name(&dir.join("missing"), "x", "png", create).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_update_names_keeps_files_outside_the_batch() {
+        let dir = test_temp_dir("rename_keep");
+        std::fs::write(dir.join("a.png"), b"a").unwrap();
+        std::fs::write(dir.join("b.png"), b"b").unwrap();
+        // 不在本次重命名列表中、但占用了目标文件名的文件
+        std::fs::write(dir.join("shot_001.png"), b"keep").unwrap();
+        let root = std::fs::canonicalize(&dir).unwrap();
+
+        let files = vec![dir.join("a.png"), dir.join("b.png")];
+        assert_eq!(file_update_names_in_dir(files, "shot_{index}", &root), 2);
+        assert_eq!(std::fs::read(dir.join("shot_001.png")).unwrap(), b"keep");
+        let mut renamed = vec![
+            std::fs::read(dir.join("shot_001_1.png")).unwrap(),
+            std::fs::read(dir.join("shot_002.png")).unwrap(),
+        ];
+        renamed.sort();
+        assert_eq!(renamed, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn jpeg_format_subsampling_defaults_to_420_and_keeps_metadata() {
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(16, 16, Rgba([200, 40, 90, 255])));
+        let exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0".to_vec();
+        let full = image_encode_jpeg_with_metadata(&img, 90, ChromaSubsampling::Yuv444, Some(&exif), None).unwrap();
+        assert_eq!(ChromaSubsampling::detect(&full), Some(ChromaSubsampling::Yuv444));
+
+        // 4:4:4 按默认值重新编码为 4:2:0，EXIF 保留
+        let converted = jpeg_format_subsampling(full, ChromaSubsampling::default()).unwrap();
+        assert_eq!(ChromaSubsampling::detect(&converted), Some(ChromaSubsampling::Yuv420));
+        let kept = image_load_with_metadata(&converted).unwrap();
+        assert_eq!(kept.exif.as_deref(), Some(exif.as_slice()));
+
+        // 已是目标抽样时原样返回
+        let again = jpeg_format_subsampling(converted.clone(), ChromaSubsampling::Yuv420).unwrap();
+        assert_eq!(again, converted);
+    }
+
+    #[test]
+    fn shadow_render_offsets_and_blurs_shadow() {
+        let request: CompactStrokesRequest = serde_json::from_value(serde_json::json!({
+            "canvas_width": 60,
+            "canvas_height": 60,
+            "strokes": [{
+                "type": "draw",
+                "color": "#FF0000",
+                "line_width": 4,
+                "points": [{ "from_x": 10.0, "from_y": 20.0, "to_x": 50.0, "to_y": 20.0 }],
+            }],
+        })).unwrap();
+        let shadow_color = Rgba([0, 0, 255, 255]);
+
+        // 硬阴影：笔画下方 10 像素处为阴影颜色，笔画本身不变
+        let hard = image_load_base64(&stroke_calc_shadow(&request, 0.0, 0, 10, shadow_color).unwrap()).unwrap().to_rgba8();
+        assert_eq!(hard.get_pixel(30, 30), &Rgba([0, 0, 255, 255]));
+        assert_eq!(hard.get_pixel(30, 20), &Rgba([255, 0, 0, 255]));
+        assert_eq!(hard.get_pixel(30, 45)[3], 0);
+
+        // 模糊后阴影中心变淡并向外扩散
+        let soft = image_load_base64(&stroke_calc_shadow(&request, 4.0, 0, 10, shadow_color).unwrap()).unwrap().to_rgba8();
+        assert!(soft.get_pixel(30, 30)[3] < 255);
+        assert!(soft.get_pixel(30, 36)[3] > 0);
+        assert_eq!(soft.get_pixel(30, 20), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn build_info_reports_resolved_image_version() {
+        let info = app_fetch_build_info();
+        // 应为 Cargo.lock 中的完整版本号，而不是 Cargo.toml 的版本要求
+        assert!(info.image_crate_version.starts_with("0.25."), "{}", info.image_crate_version);
+        assert_eq!(info.image_crate_version.split('.').count(), 3);
+        assert!(info.decode_formats.iter().any(|ext| ext == "png"));
+    }
+
+    #[test]
+    fn resolution_list_limits_to_monitor() {
+        let resolutions = resolution_list_calc(Some((2560, 1440)), (1024, 768));
+        assert_eq!((resolutions[0].width, resolutions[0].height, resolutions[0].native), (2560, 1440, true));
+        assert!(resolutions.iter().all(|r| r.width <= 2560 && r.height <= 1440));
+        assert_eq!(resolutions.iter().filter(|r| (r.width, r.height) == (2560, 1440)).count(), 1);
+    }
+}
@@ -0,0 +1,1389 @@
+// stroke_processing.rs — 笔画几何计算
+// 提供笔画碰撞检测、凸包等几何工具及对应的 Tauri IPC 命令
+//
+// 笔画数组的顺序即绘制顺序（z-order）：下标越大越晚绘制，位于越上层
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PathPoint, Stroke, StrokePoint};
+
+/// 擦除笔画未指定大小时的默认直径，与 stroke_format_compact 保持一致
+const DEFAULT_ERASER_SIZE: u32 = 15;
+
+/// 绘制笔画未指定线宽时的默认线宽，与 stroke_format_compact 保持一致
+const DEFAULT_LINE_WIDTH: u32 = 2;
+
+/// 简化时压感与弦上线性插值的最大允许偏差，超过则保留该点以维持线宽变化
+const SIMPLIFY_PRESSURE_TOLERANCE: f32 = 0.1;
+
+/// 简化后的路径，points 与 pressures 一一对应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimplifiedPath {
+    pub points: Vec<PathPoint>,
+    pub pressures: Option<Vec<f32>>,
+}
+
+/// 点 (px, py) 到线段的最短距离
+fn point_calc_segment_distance(px: f32, py: f32, segment: &StrokePoint) -> f32 {
+    let dx = segment.to_x - segment.from_x;
+    let dy = segment.to_y - segment.from_y;
+    let length_sq = dx * dx + dy * dy;
+
+    let t = if length_sq > 0.0 {
+        (((px - segment.from_x) * dx + (py - segment.from_y) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    let cx = segment.from_x + t * dx;
+    let cy = segment.from_y + t * dy;
+    ((px - cx) * (px - cx) + (py - cy) * (py - cy)).sqrt()
+}
+
+/// 判断两条线段是否相交（含端点接触）
+fn segment_validate_intersect(a: &StrokePoint, b: &StrokePoint) -> bool {
+    fn cross(ox: f32, oy: f32, ax: f32, ay: f32, bx: f32, by: f32) -> f32 {
+        (ax - ox) * (by - oy) - (ay - oy) * (bx - ox)
+    }
+
+    let d1 = cross(b.from_x, b.from_y, b.to_x, b.to_y, a.from_x, a.from_y);
+    let d2 = cross(b.from_x, b.from_y, b.to_x, b.to_y, a.to_x, a.to_y);
+    let d3 = cross(a.from_x, a.from_y, a.to_x, a.to_y, b.from_x, b.from_y);
+    let d4 = cross(a.from_x, a.from_y, a.to_x, a.to_y, b.to_x, b.to_y);
+
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+/// 两条线段之间的最短距离，相交时为 0
+pub fn segment_calc_distance(a: &StrokePoint, b: &StrokePoint) -> f32 {
+    if segment_validate_intersect(a, b) {
+        return 0.0;
+    }
+
+    point_calc_segment_distance(a.from_x, a.from_y, b)
+        .min(point_calc_segment_distance(a.to_x, a.to_y, b))
+        .min(point_calc_segment_distance(b.from_x, b.from_y, a))
+        .min(point_calc_segment_distance(b.to_x, b.to_y, a))
+}
+
+/// 判断擦除笔画是否触碰到绘制笔画（考虑橡皮半径与线宽）
+fn stroke_validate_eraser_hit(eraser: &Stroke, target: &Stroke) -> bool {
+    let eraser_radius = eraser.eraser_size.unwrap_or(DEFAULT_ERASER_SIZE) as f32 / 2.0;
+    let line_radius = target.line_width.unwrap_or(DEFAULT_LINE_WIDTH) as f32 / 2.0;
+    let reach = eraser_radius + line_radius;
+
+    eraser.points.iter().any(|eraser_segment| {
+        target.points.iter().any(|segment| segment_calc_distance(eraser_segment, segment) <= reach)
+    })
+}
+
+/// Tauri IPC 命令：检测被擦除笔画触碰到的绘制笔画
+///
+/// 按数组顺序区分上下层：擦除笔画只影响在它之前绘制（下标更小）的绘制笔画，
+/// 之后绘制的笔画即使与擦除路径重叠也不受影响，与真实白板行为一致
+///
+/// # 参数
+/// * `strokes` — 按绘制顺序排列的笔画数组
+///
+/// # 返回值
+/// * `Ok(Vec<usize>)` — 被擦除触碰到的绘制笔画下标（升序、去重）
+#[tauri::command]
+pub fn stroke_detect_eraser_collision(strokes: Vec<Stroke>) -> Result<Vec<usize>, String> {
+    let mut hit = vec![false; strokes.len()];
+
+    for (eraser_index, eraser) in strokes.iter().enumerate() {
+        if eraser.stroke_type != "erase" || eraser.points.is_empty() {
+            continue;
+        }
+
+        for (index, target) in strokes[..eraser_index].iter().enumerate() {
+            if hit[index] || target.stroke_type != "draw" {
+                continue;
+            }
+            if stroke_validate_eraser_hit(eraser, target) {
+                hit[index] = true;
+            }
+        }
+    }
+
+    Ok(hit.iter()
+        .enumerate()
+        .filter_map(|(index, &is_hit)| is_hit.then_some(index))
+        .collect())
+}
+
+/// 笔画的轴对齐包围盒 [min_x, min_y, max_x, max_y]，已计入线宽、橡皮半径或文字尺寸；
+/// 清空笔画及没有可绘制内容的笔画返回 None
+pub fn stroke_calc_bounds(stroke: &Stroke) -> Option<[f32; 4]> {
+    if stroke.stroke_type == "text" {
+        let (text, position) = (stroke.text.as_deref()?, stroke.position?);
+        let font_size = stroke.font_size.unwrap_or(crate::DEFAULT_FONT_SIZE);
+        let (width, height) = crate::text_processing::text_calc_block_size(text, font_size).ok()?;
+        return Some([position.x, position.y, position.x + width, position.y + height]);
+    }
+
+    let pad = match stroke.stroke_type.as_str() {
+        "draw" => stroke.line_width.unwrap_or(DEFAULT_LINE_WIDTH) as f32 / 2.0 + 1.0,
+        "erase" => stroke.eraser_size.unwrap_or(DEFAULT_ERASER_SIZE) as f32 / 2.0 + 1.0,
+        _ => return None,
+    };
+
+    let mut bounds: Option<[f32; 4]> = None;
+    for segment in &stroke.points {
+        let [min_x, min_y, max_x, max_y] = bounds.get_or_insert([f32::MAX, f32::MAX, f32::MIN, f32::MIN]);
+        *min_x = min_x.min(segment.from_x.min(segment.to_x));
+        *min_y = min_y.min(segment.from_y.min(segment.to_y));
+        *max_x = max_x.max(segment.from_x.max(segment.to_x));
+        *max_y = max_y.max(segment.from_y.max(segment.to_y));
+    }
+
+    bounds.map(|[min_x, min_y, max_x, max_y]| [min_x - pad, min_y - pad, max_x + pad, max_y + pad])
+}
+
+/// 收集笔画中所有线段端点
+fn stroke_collect_points(strokes: &[Stroke]) -> Vec<PathPoint> {
+    let mut points = Vec::new();
+    for stroke in strokes {
+        for segment in &stroke.points {
+            points.push(PathPoint { x: segment.from_x, y: segment.from_y });
+            points.push(PathPoint { x: segment.to_x, y: segment.to_y });
+        }
+    }
+    points
+}
+
+/// Andrew 单调链算法计算凸包，返回逆时针顺序的顶点（不重复首点）
+pub fn point_calc_convex_hull(mut points: Vec<PathPoint>) -> Vec<PathPoint> {
+    points.retain(|p| p.x.is_finite() && p.y.is_finite());
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+
+    if points.len() < 3 {
+        return points;
+    }
+
+    fn cross(o: &PathPoint, a: &PathPoint, b: &PathPoint) -> f32 {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    }
+
+    let mut hull: Vec<PathPoint> = Vec::with_capacity(points.len() * 2);
+
+    // 下凸链
+    for p in &points {
+        while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+
+    // 上凸链
+    let lower_len = hull.len() + 1;
+    for p in points.iter().rev().skip(1) {
+        while hull.len() >= lower_len && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+
+    hull.pop();
+    hull
+}
+
+/// Tauri IPC 命令：计算一组笔画所有点的凸包多边形
+///
+/// 用于绘制紧贴内容的分组选择框，替代轴对齐包围盒
+///
+/// # 参数
+/// * `strokes` — 参与计算的笔画数组
+///
+/// # 返回值
+/// * `Ok(Vec<PathPoint>)` — 逆时针排列的凸包顶点；点数不足 3 个时原样返回去重后的点
+#[tauri::command]
+pub fn stroke_calc_convex_hull(strokes: Vec<Stroke>) -> Result<Vec<PathPoint>, String> {
+    Ok(point_calc_convex_hull(stroke_collect_points(&strokes)))
+}
+
+/// 带旋转角度的矩形（有向包围盒）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotatedRect {
+    pub center: PathPoint,
+    /// 沿 angle 方向的边长
+    pub width: f32,
+    /// 垂直于 angle 方向的边长
+    pub height: f32,
+    /// width 边相对 x 轴的旋转角度（角度，顺时针为正，范围 (-90, 90]）
+    pub angle: f32,
+}
+
+/// 旋转卡壳求凸包的最小面积外接矩形，hull 为逆时针顶点
+///
+/// 最小面积矩形必有一边与凸包某条边共线，依次以每条边为底，
+/// 三个卡壳指针（沿边方向最远、最近及离边最远的顶点）随边单调前进，整体 O(n)
+fn point_calc_min_area_rect(hull: &[PathPoint]) -> Option<RotatedRect> {
+    let n = hull.len();
+    match n {
+        0 => return None,
+        1 => return Some(RotatedRect { center: hull[0], width: 0.0, height: 0.0, angle: 0.0 }),
+        _ => {}
+    }
+
+    let project = |p: &PathPoint, origin: &PathPoint, (ux, uy): (f32, f32)| (p.x - origin.x) * ux + (p.y - origin.y) * uy;
+    // 沿 dir 投影不再增大时停止，最多前进 n 步，避免共线点导致死循环
+    let advance = |mut index: usize, origin: &PathPoint, dir: (f32, f32)| {
+        for _ in 0..n {
+            let next = (index + 1) % n;
+            if project(&hull[next], origin, dir) < project(&hull[index], origin, dir) + f32::EPSILON {
+                break;
+            }
+            index = next;
+        }
+        index
+    };
+
+    let mut best: Option<(f32, RotatedRect)> = None;
+    let (mut right, mut top, mut left) = (0, 0, 0);
+
+    for i in 0..n {
+        let (origin, next) = (&hull[i], &hull[(i + 1) % n]);
+        let length = ((next.x - origin.x).powi(2) + (next.y - origin.y).powi(2)).sqrt();
+        if length == 0.0 {
+            continue;
+        }
+        let u = ((next.x - origin.x) / length, (next.y - origin.y) / length);
+        // 逆时针凸包的左法线指向内部
+        let normal = (-u.1, u.0);
+
+        if i == 0 {
+            right = advance(i, origin, u);
+            top = advance(right, origin, normal);
+            left = advance(top, origin, (-u.0, -u.1));
+        } else {
+            right = advance(right, origin, u);
+            top = advance(top, origin, normal);
+            left = advance(left, origin, (-u.0, -u.1));
+        }
+
+        let max_u = project(&hull[right], origin, u);
+        let min_u = project(&hull[left], origin, u);
+        let max_n = project(&hull[top], origin, normal);
+        let (width, height) = (max_u - min_u, max_n);
+        let area = width * height;
+        if best.as_ref().is_some_and(|(best_area, _)| *best_area <= area) {
+            continue;
+        }
+
+        let (mid_u, mid_n) = ((max_u + min_u) / 2.0, max_n / 2.0);
+        let mut angle = u.1.atan2(u.0).to_degrees();
+        if angle <= -90.0 {
+            angle += 180.0;
+        } else if angle > 90.0 {
+            angle -= 180.0;
+        }
+        best = Some((area, RotatedRect {
+            center: PathPoint {
+                x: origin.x + u.0 * mid_u + normal.0 * mid_n,
+                y: origin.y + u.1 * mid_u + normal.1 * mid_n,
+            },
+            width,
+            height,
+            angle,
+        }));
+    }
+
+    best.map(|(_, rect)| rect)
+}
+
+/// Tauri IPC 命令：计算一组笔画的最小面积外接矩形（有向包围盒）
+///
+/// 与轴对齐的 stroke_calc_bounds 不同，矩形可以旋转以紧贴倾斜的内容，
+/// 用于批注分组的旋转选择框。与 stroke_calc_convex_hull 一样只考虑线段端点，不计线宽
+///
+/// # 参数
+/// * `strokes` — 参与计算的笔画数组
+///
+/// # 返回值
+/// * `Ok(Some(RotatedRect))` — 中心、宽高与旋转角度；所有点共线时高度为 0
+/// * `Ok(None)` — 笔画中没有任何点
+#[tauri::command]
+pub fn stroke_calc_min_area_rect(strokes: Vec<Stroke>) -> Result<Option<RotatedRect>, String> {
+    let hull = point_calc_convex_hull(stroke_collect_points(&strokes));
+    Ok(point_calc_min_area_rect(&hull))
+}
+
+/// 迭代式 Douglas–Peucker 简化，返回保留点的下标（升序）
+///
+/// 使用显式栈代替递归，避免长笔画导致栈溢出；
+/// 提供 pressures 时，压感偏离弦上插值过多的点同样保留
+fn point_calc_simplify_indices(points: &[PathPoint], pressures: Option<&[f32]>, tolerance: f32) -> Vec<usize> {
+    if points.len() < 3 {
+        return (0..points.len()).collect();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+
+    while let Some((start, end)) = stack.pop() {
+        if end <= start + 1 {
+            continue;
+        }
+
+        let chord = StrokePoint {
+            from_x: points[start].x,
+            from_y: points[start].y,
+            to_x: points[end].x,
+            to_y: points[end].y,
+        };
+
+        let mut split = None;
+        let mut max_distance = tolerance;
+        for (index, point) in points.iter().enumerate().take(end).skip(start + 1) {
+            let distance = point_calc_segment_distance(point.x, point.y, &chord);
+            let pressure_off = pressures.is_some_and(|p| {
+                let t = (index - start) as f32 / (end - start) as f32;
+                let expected = p[start] + (p[end] - p[start]) * t;
+                (p[index] - expected).abs() > SIMPLIFY_PRESSURE_TOLERANCE
+            });
+
+            if distance > max_distance || (split.is_none() && pressure_off) {
+                max_distance = distance.max(tolerance);
+                split = Some(index);
+            }
+        }
+
+        if let Some(index) = split {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+
+    keep.iter()
+        .enumerate()
+        .filter_map(|(index, &kept)| kept.then_some(index))
+        .collect()
+}
+
+/// Tauri IPC 命令：简化路径点，并保持压感数组与点一一对齐
+///
+/// 点与压感作为整体参与简化，删除点时同步删除对应压感，
+/// 保证简化后变宽渲染仍然正确
+///
+/// # 参数
+/// * `points` — 原始路径点
+/// * `pressures` — 与 points 等长的压感数组（可选）
+/// * `tolerance` — 允许的最大偏离距离（像素）
+///
+/// # 返回值
+/// * `Ok(SimplifiedPath)` — 简化后的点及对齐的压感
+///
+/// # 异常
+/// * 压感数组长度与点数不一致
+/// * tolerance 非法
+#[tauri::command]
+pub fn stroke_format_simplify(points: Vec<PathPoint>, pressures: Option<Vec<f32>>, tolerance: f32) -> Result<SimplifiedPath, String> {
+    if !tolerance.is_finite() || tolerance < 0.0 {
+        return Err(format!("Invalid tolerance: {}", tolerance));
+    }
+    if let Some(ref p) = pressures {
+        if p.len() != points.len() {
+            return Err(format!("Pressure count {} does not match point count {}", p.len(), points.len()));
+        }
+    }
+
+    let indices = point_calc_simplify_indices(&points, pressures.as_deref(), tolerance);
+
+    Ok(SimplifiedPath {
+        points: indices.iter().map(|&i| points[i]).collect(),
+        pressures: pressures.map(|p| indices.iter().map(|&i| p[i]).collect()),
+    })
+}
+
+/// Fritsch–Carlson 单调三次 Hermite 插值的各控制点切线
+fn curve_calc_monotone_tangents(curve: &[PathPoint]) -> Vec<f32> {
+    let n = curve.len();
+    let secants: Vec<f32> = curve.windows(2)
+        .map(|w| (w[1].y - w[0].y) / (w[1].x - w[0].x))
+        .collect();
+
+    let mut tangents = vec![0.0f32; n];
+    tangents[0] = secants[0];
+    tangents[n - 1] = secants[n - 2];
+    for i in 1..n - 1 {
+        tangents[i] = if secants[i - 1] * secants[i] <= 0.0 {
+            0.0
+        } else {
+            (secants[i - 1] + secants[i]) / 2.0
+        };
+    }
+
+    // 限制切线大小，保证每段插值保持单调
+    for i in 0..n - 1 {
+        if secants[i] == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+        let alpha = tangents[i] / secants[i];
+        let beta = tangents[i + 1] / secants[i];
+        let magnitude = alpha * alpha + beta * beta;
+        if magnitude > 9.0 {
+            let tau = 3.0 / magnitude.sqrt();
+            tangents[i] = tau * alpha * secants[i];
+            tangents[i + 1] = tau * beta * secants[i];
+        }
+    }
+
+    tangents
+}
+
+/// Tauri IPC 命令：按压感曲线重映射原始压感值
+///
+/// 曲线由控制点 (x = 原始压感, y = 输出压感) 定义，控制点之间使用单调三次插值，
+/// 不会产生过冲；超出曲线 x 范围的输入取端点值
+///
+/// # 参数
+/// * `values` — 原始压感值（0..1，超出范围会被夹取）
+/// * `curve` — 曲线控制点，至少 2 个，x 必须严格递增
+///
+/// # 返回值
+/// * `Ok(Vec<f32>)` — 与输入等长的重映射结果（0..1）
+///
+/// # 异常
+/// * 控制点少于 2 个、x 未严格递增或包含非有限值
+#[tauri::command]
+pub fn stroke_calc_pressure_curve(values: Vec<f32>, curve: Vec<PathPoint>) -> Result<Vec<f32>, String> {
+    if curve.len() < 2 {
+        return Err("Pressure curve needs at least 2 control points".to_string());
+    }
+    if curve.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        return Err("Pressure curve contains non-finite values".to_string());
+    }
+    if curve.windows(2).any(|w| w[1].x <= w[0].x) {
+        return Err("Pressure curve x values must be strictly increasing".to_string());
+    }
+
+    let tangents = curve_calc_monotone_tangents(&curve);
+    let last = curve.len() - 1;
+
+    Ok(values.iter()
+        .map(|&value| {
+            let x = if value.is_finite() { value.clamp(0.0, 1.0) } else { 0.0 };
+            if x <= curve[0].x {
+                return curve[0].y.clamp(0.0, 1.0);
+            }
+            if x >= curve[last].x {
+                return curve[last].y.clamp(0.0, 1.0);
+            }
+
+            let i = curve.windows(2).position(|w| x < w[1].x).unwrap_or(last - 1);
+            let (p0, p1) = (curve[i], curve[i + 1]);
+            let h = p1.x - p0.x;
+            let t = (x - p0.x) / h;
+            let (t2, t3) = (t * t, t * t * t);
+
+            let y = (2.0 * t3 - 3.0 * t2 + 1.0) * p0.y
+                + (t3 - 2.0 * t2 + t) * h * tangents[i]
+                + (-2.0 * t3 + 3.0 * t2) * p1.y
+                + (t3 - t2) * h * tangents[i + 1];
+            y.clamp(0.0, 1.0)
+        })
+        .collect())
+}
+
+/// 两个包围盒是否相交
+fn bounds_validate_overlap(a: &[f32; 4], b: &[f32; 4]) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+/// 点到擦除笔画路径的最短距离
+fn point_calc_eraser_distance(x: f32, y: f32, eraser: &Stroke) -> f32 {
+    eraser.points.iter()
+        .map(|segment| point_calc_segment_distance(x, y, segment))
+        .fold(f32::MAX, f32::min)
+}
+
+/// 将擦除笔画的效果烘焙进一条绘制笔画，返回是否存在无法烘焙的部分擦除
+///
+/// 同时考虑橡皮半径和线宽：与橡皮接触的线段先细分为不超过半个橡皮半径的小段，
+/// 整段线宽都落在橡皮内的小段被删除，完全未接触的保留；只擦掉一部分线宽的小段无法用线段表示，
+/// 原样保留并返回 true，调用方需把擦除笔画留在原位置。方形橡皮按内切圆判定，多出的角落同样视为部分擦除
+fn stroke_update_bake_erase(target: &mut Stroke, eraser: &Stroke) -> bool {
+    let eraser_radius = eraser.eraser_size.unwrap_or(DEFAULT_ERASER_SIZE) as f32 / 2.0;
+    let line_radius = target.line_width.unwrap_or(DEFAULT_LINE_WIDTH) as f32 / 2.0;
+    let step = (eraser_radius / 2.0).max(1.0);
+    let touches = |segment: &StrokePoint| {
+        eraser.points.iter().any(|e| segment_calc_distance(e, segment) <= eraser_radius + line_radius)
+    };
+    // 小段长度不超过半个橡皮半径，用两端点的距离近似整段到橡皮路径的最大距离
+    let fully_erased = |segment: &StrokePoint| {
+        point_calc_eraser_distance(segment.from_x, segment.from_y, eraser)
+            .max(point_calc_eraser_distance(segment.to_x, segment.to_y, eraser))
+            + line_radius <= eraser_radius
+    };
+
+    let mut partial = false;
+    let mut remaining = Vec::with_capacity(target.points.len());
+    for segment in &target.points {
+        if !touches(segment) {
+            remaining.push(segment.clone());
+            continue;
+        }
+
+        let (dx, dy) = (segment.to_x - segment.from_x, segment.to_y - segment.from_y);
+        let pieces = ((dx * dx + dy * dy).sqrt() / step).ceil().max(1.0) as usize;
+        for i in 0..pieces {
+            let (t0, t1) = (i as f32 / pieces as f32, (i + 1) as f32 / pieces as f32);
+            let piece = StrokePoint {
+                from_x: segment.from_x + dx * t0,
+                from_y: segment.from_y + dy * t0,
+                to_x: segment.from_x + dx * t1,
+                to_y: segment.from_y + dy * t1,
+            };
+            if fully_erased(&piece) {
+                continue;
+            }
+            if touches(&piece) {
+                partial = true;
+            }
+            remaining.push(piece);
+        }
+    }
+
+    target.points = remaining;
+    partial || eraser.eraser_shape.as_deref() == Some("square") && !target.points.is_empty()
+}
+
+/// Tauri IPC 命令：将笔画整理为与绘制顺序无关的等效序列
+///
+/// 擦除笔画只影响在它之前的绘制笔画。整理后擦除效果被直接烘焙进受影响的绘制笔画
+/// （删除整段线宽都被擦掉的部分），擦除笔画移到最前，仅用于擦除底图；之后撤销时把绘制笔画
+/// 重新插入任意位置都不会再被先前的擦除误伤
+///
+/// 只擦掉部分线宽的笔画边缘和文字笔画无法按线段拆分，此时擦除笔画保留在原位置；
+/// "clear" 之前的笔画全部丢弃，"clear" 本身保留在序列开头
+///
+/// # 参数
+/// * `strokes` — 按绘制顺序排列的笔画数组
+///
+/// # 返回值
+/// * `Ok(Vec<Stroke>)` — 整理后的笔画数组；重新渲染结果与原顺序一致，
+///   仅线段细分后端点取整可能带来个别像素差异
+#[tauri::command]
+pub fn stroke_format_resolve_order(strokes: Vec<Stroke>) -> Result<Vec<Stroke>, String> {
+    let mut leading: Vec<Stroke> = Vec::new();
+    let mut resolved: Vec<Stroke> = Vec::new();
+
+    for stroke in strokes {
+        match stroke.stroke_type.as_str() {
+            "clear" => {
+                resolved.clear();
+                leading.clear();
+                leading.push(stroke);
+            }
+            "erase" => {
+                if stroke.points.is_empty() {
+                    continue;
+                }
+                let Some(eraser_bounds) = stroke_calc_bounds(&stroke) else {
+                    continue;
+                };
+
+                let mut keep_in_place = false;
+                for target in resolved.iter_mut() {
+                    let Some(target_bounds) = stroke_calc_bounds(target) else {
+                        continue;
+                    };
+                    if !bounds_validate_overlap(&eraser_bounds, &target_bounds) {
+                        continue;
+                    }
+                    match target.stroke_type.as_str() {
+                        "draw" => keep_in_place |= stroke_update_bake_erase(target, &stroke),
+                        "text" => keep_in_place = true,
+                        _ => {}
+                    }
+                }
+                resolved.retain(|s| s.stroke_type != "draw" || !s.points.is_empty());
+
+                if keep_in_place {
+                    resolved.push(stroke);
+                } else {
+                    leading.push(stroke);
+                }
+            }
+            _ => resolved.push(stroke),
+        }
+    }
+
+    leading.extend(resolved);
+    Ok(leading)
+}
+
+/// Liang–Barsky 算法将线段裁剪到矩形 [min_x, min_y, max_x, max_y]，完全在外时返回 None
+fn segment_calc_clip(segment: &StrokePoint, rect: [f32; 4]) -> Option<StrokePoint> {
+    let (dx, dy) = (segment.to_x - segment.from_x, segment.to_y - segment.from_y);
+    let checks = [
+        (-dx, segment.from_x - rect[0]),
+        (dx, rect[2] - segment.from_x),
+        (-dy, segment.from_y - rect[1]),
+        (dy, rect[3] - segment.from_y),
+    ];
+
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in checks {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+    }
+
+    Some(StrokePoint {
+        from_x: segment.from_x + t0 * dx,
+        from_y: segment.from_y + t0 * dy,
+        to_x: segment.from_x + t1 * dx,
+        to_y: segment.from_y + t1 * dy,
+    })
+}
+
+/// Tauri IPC 命令：将笔画线段裁剪到画布范围内
+///
+/// 画布尺寸变化后残留的越界线段不会绘制任何内容却仍要光栅化。
+/// 裁剪矩形按线宽或橡皮半径向外扩展，保证贴近边缘的粗线仍能画入画布；
+/// 全部线段都在画布外的绘制/擦除笔画被丢弃，文字与清空笔画原样保留
+///
+/// # 参数
+/// * `strokes` — 按绘制顺序排列的笔画数组
+/// * `width` / `height` — 画布尺寸
+///
+/// # 返回值
+/// * `Ok(Vec<Stroke>)` — 裁剪后的笔画数组，顺序不变
+///
+/// # 异常
+/// * 画布尺寸为 0
+#[tauri::command]
+pub fn stroke_format_clip(strokes: Vec<Stroke>, width: u32, height: u32) -> Result<Vec<Stroke>, String> {
+    if width == 0 || height == 0 {
+        return Err("Canvas size must be non-zero".to_string());
+    }
+
+    Ok(strokes.into_iter()
+        .filter_map(|mut stroke| {
+            let pad = match stroke.stroke_type.as_str() {
+                "draw" => stroke.line_width.unwrap_or(DEFAULT_LINE_WIDTH) as f32 / 2.0 + 1.0,
+                "erase" => stroke.eraser_size.unwrap_or(DEFAULT_ERASER_SIZE) as f32 / 2.0 + 1.0,
+                _ => return Some(stroke),
+            };
+            let rect = [-pad, -pad, width as f32 + pad, height as f32 + pad];
+
+            stroke.points = stroke.points.iter()
+                .filter_map(|segment| segment_calc_clip(segment, rect))
+                .collect();
+            (!stroke.points.is_empty()).then_some(stroke)
+        })
+        .collect())
+}
+
+/// 多边形有向面积（鞋带公式）：数学坐标系（y 轴向上）下逆时针为正
+fn polygon_calc_signed_area(points: &[PathPoint]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>() / 2.0
+}
+
+/// Tauri IPC 命令：判断点集构成的多边形的绕向，用于形状填充规则和箭头方向
+///
+/// 按有向面积的符号判断，约定与数学坐标系一致；画布坐标 y 轴向下，
+/// 因此屏幕上看起来顺时针的多边形返回 +1
+///
+/// # 参数
+/// * `points` — 多边形顶点，首尾无需重复
+///
+/// # 返回值
+/// * `Ok(i32)` — +1 逆时针，-1 顺时针，0 退化（少于 3 个点、面积为 0 或含非有限值）
+#[tauri::command]
+pub fn stroke_calc_winding(points: Vec<PathPoint>) -> Result<i32, String> {
+    if points.len() < 3 || points.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        return Ok(0);
+    }
+
+    let area = polygon_calc_signed_area(&points);
+    Ok(if area > f32::EPSILON {
+        1
+    } else if area < -f32::EPSILON {
+        -1
+    } else {
+        0
+    })
+}
+
+/// 距离场默认截断距离（像素），超出此距离的像素保持该值
+const DISTANCE_FIELD_DEFAULT_MAX: f32 = 32.0;
+
+/// Tauri IPC 命令：将新线段增量合并进笔画距离场，用于实时笔画的外发光效果
+///
+/// 只重算每条新线段包围盒（按截断距离扩展）内的像素，取已有距离与到新线段距离的较小值，
+/// 无需每次为全部笔画重算整张距离场。传入空距离场时从头创建
+///
+/// # 参数
+/// * `field` — 行主序距离场（长度为 width × height），为空时新建
+/// * `segments` — 新增的线段
+/// * `width` / `height` — 距离场尺寸
+/// * `max_distance` — 截断距离，默认 32
+///
+/// # 返回值
+/// * `Ok(Vec<f32>)` — 更新后的距离场，每个像素为到最近线段的距离（不超过截断距离）
+///
+/// # 异常
+/// * 尺寸为 0 或超过上限
+/// * 距离场长度与尺寸不符
+/// * 截断距离非法
+#[tauri::command]
+pub fn stroke_update_distance_field(
+    field: Vec<f32>,
+    segments: Vec<StrokePoint>,
+    width: u32,
+    height: u32,
+    max_distance: Option<f32>,
+) -> Result<Vec<f32>, String> {
+    crate::canvas_validate_size(width, height)?;
+    let max_distance = max_distance.unwrap_or(DISTANCE_FIELD_DEFAULT_MAX);
+    if !max_distance.is_finite() || max_distance <= 0.0 {
+        return Err(format!("Invalid max distance: {}", max_distance));
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let mut field = if field.is_empty() {
+        vec![max_distance; w * h]
+    } else if field.len() == w * h {
+        field
+    } else {
+        return Err(format!("Distance field length {} does not match {}x{}", field.len(), width, height));
+    };
+
+    for segment in &segments {
+        if ![segment.from_x, segment.from_y, segment.to_x, segment.to_y].iter().all(|v| v.is_finite()) {
+            continue;
+        }
+
+        let min_x = (segment.from_x.min(segment.to_x) - max_distance).floor().max(0.0) as usize;
+        let min_y = (segment.from_y.min(segment.to_y) - max_distance).floor().max(0.0) as usize;
+        let max_x = (segment.from_x.max(segment.to_x) + max_distance).ceil().min(w as f32 - 1.0);
+        let max_y = (segment.from_y.max(segment.to_y) + max_distance).ceil().min(h as f32 - 1.0);
+        if max_x < 0.0 || max_y < 0.0 {
+            continue;
+        }
+
+        for y in min_y..=max_y as usize {
+            let row = &mut field[y * w..(y + 1) * w];
+            for (x, value) in row.iter_mut().enumerate().take(max_x as usize + 1).skip(min_x) {
+                let distance = point_calc_segment_distance(x as f32, y as f32, segment);
+                if distance < *value {
+                    *value = distance.min(max_distance);
+                }
+            }
+        }
+    }
+
+    Ok(field)
+}
+
+/// 按 2D 仿射矩阵 [a, b, c, d, e, f] 变换点：x' = a·x + c·y + e，y' = b·x + d·y + f
+fn point_calc_affine(x: f32, y: f32, matrix: &[f32; 6]) -> (f32, f32) {
+    (
+        matrix[0] * x + matrix[2] * y + matrix[4],
+        matrix[1] * x + matrix[3] * y + matrix[5],
+    )
+}
+
+/// Tauri IPC 命令：对多个笔画图层批量应用同一个仿射变换（如页面缩放）
+///
+/// 线段端点与文字位置按矩阵变换；线宽、橡皮大小和字号按矩阵的面积缩放系数
+/// （行列式绝对值的平方根）等比缩放，保证缩放后笔画粗细与内容一致
+///
+/// # 参数
+/// * `layers` — 笔画图层数组，每个图层为按绘制顺序排列的笔画数组
+/// * `matrix` — 仿射矩阵 [a, b, c, d, e, f]，与 Canvas setTransform 参数顺序相同
+///
+/// # 返回值
+/// * `Ok(Vec<Vec<Stroke>>)` — 变换后的图层，结构与输入一致
+///
+/// # 异常
+/// * 矩阵含非有限值
+#[tauri::command]
+pub fn stroke_format_transform_layers(layers: Vec<Vec<Stroke>>, matrix: [f32; 6]) -> Result<Vec<Vec<Stroke>>, String> {
+    if matrix.iter().any(|v| !v.is_finite()) {
+        return Err("Transform matrix contains non-finite values".to_string());
+    }
+
+    let scale = (matrix[0] * matrix[3] - matrix[1] * matrix[2]).abs().sqrt();
+    let scale_size = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+
+    Ok(layers.into_iter()
+        .map(|strokes| {
+            stroke_format_affine_points(strokes, &matrix).into_iter()
+                .map(|mut stroke| {
+                    // 未指定尺寸时按默认值缩放，否则渲染时会退回未缩放的默认值
+                    match stroke.stroke_type.as_str() {
+                        "draw" => stroke.line_width = Some(scale_size(stroke.line_width.unwrap_or(DEFAULT_LINE_WIDTH))),
+                        "erase" => stroke.eraser_size = Some(scale_size(stroke.eraser_size.unwrap_or(DEFAULT_ERASER_SIZE))),
+                        "text" => stroke.font_size = Some(stroke.font_size.unwrap_or(crate::DEFAULT_FONT_SIZE) * scale),
+                        _ => {}
+                    }
+                    stroke
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Tauri IPC 命令：将一组笔画平移后追加到已有笔画之后，用于批注的复制粘贴
+///
+/// 追加的笔画保持原有顺序并排在已有笔画之后，渲染时位于最上层；
+/// 线段端点与文字位置一起平移，线宽等尺寸不变
+///
+/// # 参数
+/// * `base` — 已有笔画数组
+/// * `added` — 要粘贴的笔画数组
+/// * `offset_x` / `offset_y` — 粘贴偏移量（像素）
+///
+/// # 返回值
+/// * `Ok(Vec<Stroke>)` — 合并后的笔画数组
+///
+/// # 异常
+/// * 偏移量含非有限值
+#[tauri::command]
+pub fn stroke_format_merge(base: Vec<Stroke>, added: Vec<Stroke>, offset_x: f32, offset_y: f32) -> Result<Vec<Stroke>, String> {
+    if !offset_x.is_finite() || !offset_y.is_finite() {
+        return Err("Merge offset contains non-finite values".to_string());
+    }
+
+    let mut merged = base;
+    merged.extend(stroke_format_affine_points(added, &[1.0, 0.0, 0.0, 1.0, offset_x, offset_y]));
+
+    Ok(merged)
+}
+
+/// 轮廓拐角的斜接长度上限（相对半线宽），超过时截断，避免锐角处出现长尖刺
+const OUTLINE_MITER_LIMIT: f32 = 4.0;
+
+/// 轮廓圆形端点的分段数（半圆）
+const OUTLINE_CAP_SEGMENTS: usize = 8;
+
+/// 以 center 为圆心、从 start_angle 起按角度递减方向追加半圆弧上的点（不含起点，含终点）
+///
+/// 起点为行进方向左侧法线时，弧线经过行进方向前方
+fn outline_push_cap(outline: &mut Vec<PathPoint>, center: PathPoint, radius: f32, start_angle: f32) {
+    for i in 1..=OUTLINE_CAP_SEGMENTS {
+        let angle = start_angle - std::f32::consts::PI * i as f32 / OUTLINE_CAP_SEGMENTS as f32;
+        outline.push(PathPoint { x: center.x + radius * angle.cos(), y: center.y + radius * angle.sin() });
+    }
+}
+
+/// Tauri IPC 命令：将折线中心线扩展为指定线宽的闭合轮廓多边形，用于将笔画导出为可缩放的填充形状
+///
+/// 拐角处使用斜接（长度超过半线宽 4 倍时截断），两端为圆形端点；
+/// 相邻重复点会被忽略，只有一个点时返回圆形
+///
+/// # 参数
+/// * `points` — 中心线路径点
+/// * `width` — 线宽（像素）
+///
+/// # 返回值
+/// * `Ok(Vec<PathPoint>)` — 轮廓多边形顶点（不重复首点）：先沿一侧前进，经末端圆头沿另一侧返回
+///
+/// # 异常
+/// * 路径为空
+/// * 线宽不是正的有限值
+#[tauri::command]
+pub fn stroke_calc_outline(points: Vec<PathPoint>, width: f32) -> Result<Vec<PathPoint>, String> {
+    if !width.is_finite() || width <= 0.0 {
+        return Err(format!("Invalid outline width: {}", width));
+    }
+
+    let mut path: Vec<PathPoint> = Vec::with_capacity(points.len());
+    for point in points {
+        if path.last().is_none_or(|last: &PathPoint| last.x != point.x || last.y != point.y) {
+            path.push(point);
+        }
+    }
+    let half = width / 2.0;
+
+    let Some(&first) = path.first() else {
+        return Err("Outline path is empty".to_string());
+    };
+    if path.len() == 1 {
+        let mut circle = Vec::with_capacity(OUTLINE_CAP_SEGMENTS * 2);
+        outline_push_cap(&mut circle, first, half, 0.0);
+        outline_push_cap(&mut circle, first, half, -std::f32::consts::PI);
+        return Ok(circle);
+    }
+
+    // 每段的单位法线（指向行进方向左侧）
+    let normals: Vec<(f32, f32)> = path.windows(2)
+        .map(|pair| {
+            let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            let length = (dx * dx + dy * dy).sqrt();
+            (-dy / length, dx / length)
+        })
+        .collect();
+
+    // 各顶点的偏移向量：端点取所在段法线，中间点取两段法线的斜接方向
+    let offsets: Vec<(f32, f32)> = (0..path.len())
+        .map(|i| {
+            let n1 = normals[i.saturating_sub(1)];
+            let n2 = normals[i.min(normals.len() - 1)];
+            let (mx, my) = (n1.0 + n2.0, n1.1 + n2.1);
+            let length = (mx * mx + my * my).sqrt();
+            if length < 1e-6 {
+                return (n1.0 * half, n1.1 * half);
+            }
+            let (mx, my) = (mx / length, my / length);
+            let miter = (half / (mx * n1.0 + my * n1.1)).min(half * OUTLINE_MITER_LIMIT);
+            (mx * miter, my * miter)
+        })
+        .collect();
+
+    let mut outline = Vec::with_capacity(path.len() * 2 + OUTLINE_CAP_SEGMENTS * 2);
+    outline.extend(path.iter().zip(&offsets).map(|(p, o)| PathPoint { x: p.x + o.0, y: p.y + o.1 }));
+
+    let last = path[path.len() - 1];
+    let end_normal = normals[normals.len() - 1];
+    outline_push_cap(&mut outline, last, half, end_normal.1.atan2(end_normal.0));
+    outline.pop();
+
+    outline.extend(path.iter().zip(&offsets).rev().map(|(p, o)| PathPoint { x: p.x - o.0, y: p.y - o.1 }));
+
+    let start_normal = normals[0];
+    outline_push_cap(&mut outline, first, half, (-start_normal.1).atan2(-start_normal.0));
+    outline.pop();
+
+    Ok(outline)
+}
+
+/// 比较重复笔画时每条笔画重采样的点数
+const DUPLICATE_SAMPLE_COUNT: usize = 32;
+
+/// 将绘制笔画的线段串成折线，并按弧长等距重采样为固定点数；没有线段时返回 None
+fn stroke_calc_resampled(stroke: &Stroke, count: usize) -> Option<Vec<PathPoint>> {
+    let first = stroke.points.first()?;
+    let mut polyline = vec![PathPoint { x: first.from_x, y: first.from_y }];
+    polyline.extend(stroke.points.iter().map(|segment| PathPoint { x: segment.to_x, y: segment.to_y }));
+
+    let mut cumulative = vec![0.0f32];
+    for pair in polyline.windows(2) {
+        let length = ((pair[1].x - pair[0].x).powi(2) + (pair[1].y - pair[0].y).powi(2)).sqrt();
+        cumulative.push(cumulative[cumulative.len() - 1] + length);
+    }
+    let total = cumulative[cumulative.len() - 1];
+
+    let mut segment = 0;
+    Some((0..count)
+        .map(|i| {
+            let target = total * i as f32 / (count - 1) as f32;
+            while segment + 2 < cumulative.len() && cumulative[segment + 1] < target {
+                segment += 1;
+            }
+            let (a, b) = (polyline[segment], polyline[(segment + 1).min(polyline.len() - 1)]);
+            let span = cumulative[(segment + 1).min(cumulative.len() - 1)] - cumulative[segment];
+            let t = if span > 0.0 { ((target - cumulative[segment]) / span).clamp(0.0, 1.0) } else { 0.0 };
+            PathPoint { x: a.x + (b.x - a.x) * t, y: a.y + (b.y - a.y) * t }
+        })
+        .collect())
+}
+
+/// 两条重采样路径对应点的均方根距离，取正向与反向（反方向重描）中较小者
+fn path_calc_rms_distance(a: &[PathPoint], b: &[PathPoint]) -> f32 {
+    let rms = |reverse: bool| {
+        let sum: f32 = a.iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let q = if reverse { b[b.len() - 1 - i] } else { b[i] };
+                (p.x - q.x).powi(2) + (p.y - q.y).powi(2)
+            })
+            .sum();
+        (sum / a.len() as f32).sqrt()
+    };
+    rms(false).min(rms(true))
+}
+
+/// 并查集查找根节点（带路径压缩）
+fn union_find_root(parents: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = index;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+/// Tauri IPC 命令：查找形状和位置几乎相同的重复绘制笔画（如不小心描了两遍）
+///
+/// 只比较绘制笔画：每条笔画按弧长重采样为 32 个点，计算对应点的均方根距离
+/// （反方向重描同样视为重复），再除以两者中较大的包围盒对角线得到归一化偏差；
+/// 相似度 = 1 - 归一化偏差，不低于阈值的笔画归为一组（传递合并）
+///
+/// # 参数
+/// * `strokes` — 按绘制顺序排列的笔画数组
+/// * `similarity` — 相似度阈值（0..1），越接近 1 越严格，常用 0.95
+///
+/// # 返回值
+/// * `Ok(Vec<Vec<usize>>)` — 重复笔画分组，组内下标升序，各组按首个下标排序；只含两条及以上笔画的组
+///
+/// # 异常
+/// * 相似度阈值不在 0..1 范围内
+#[tauri::command]
+pub fn stroke_detect_duplicates(strokes: Vec<Stroke>, similarity: f32) -> Result<Vec<Vec<usize>>, String> {
+    if !(0.0..=1.0).contains(&similarity) {
+        return Err(format!("Similarity must be within 0..1, got {}", similarity));
+    }
+
+    let candidates: Vec<(usize, [f32; 4], f32, Vec<PathPoint>)> = strokes.iter()
+        .enumerate()
+        .filter(|(_, stroke)| stroke.stroke_type == "draw")
+        .filter_map(|(index, stroke)| {
+            let bounds = stroke_calc_bounds(stroke)?;
+            let diagonal = ((bounds[2] - bounds[0]).powi(2) + (bounds[3] - bounds[1]).powi(2)).sqrt();
+            Some((index, bounds, diagonal, stroke_calc_resampled(stroke, DUPLICATE_SAMPLE_COUNT)?))
+        })
+        .collect();
+
+    let mut parents: Vec<usize> = (0..strokes.len()).collect();
+    for (i, (index_a, bounds_a, diagonal_a, samples_a)) in candidates.iter().enumerate() {
+        for (index_b, bounds_b, diagonal_b, samples_b) in &candidates[i + 1..] {
+            if !bounds_validate_overlap(bounds_a, bounds_b) {
+                continue;
+            }
+            let deviation = path_calc_rms_distance(samples_a, samples_b) / diagonal_a.max(*diagonal_b).max(1.0);
+            if 1.0 - deviation >= similarity {
+                let (root_a, root_b) = (union_find_root(&mut parents, *index_a), union_find_root(&mut parents, *index_b));
+                parents[root_a.max(root_b)] = root_a.min(root_b);
+            }
+        }
+    }
+
+    let mut groups: std::collections::BTreeMap<usize, Vec<usize>> = std::collections::BTreeMap::new();
+    for (index, _, _, _) in &candidates {
+        let root = union_find_root(&mut parents, *index);
+        groups.entry(root).or_default().push(*index);
+    }
+
+    Ok(groups.into_values().filter(|group| group.len() > 1).collect())
+}
+
+/// 笔画文档的统计信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrokeStats {
+    pub stroke_count: usize,
+    /// 所有笔画的线段数之和
+    pub total_points: usize,
+    pub draw_count: usize,
+    pub erase_count: usize,
+    /// 绘制笔画中心线总长度（像素）
+    pub total_ink_length: f32,
+    /// 所有笔画的包围盒 [min_x, min_y, max_x, max_y]，没有可绘制内容时为 None
+    pub bounds: Option<[f32; 4]>,
+}
+
+/// Tauri IPC 命令：一次遍历统计笔画文档的规模，供前端在文档过大时提示
+///
+/// # 参数
+/// * `strokes` — 按绘制顺序排列的笔画数组
+///
+/// # 返回值
+/// * `Ok(StrokeStats)` — 笔画数、线段数、绘制/擦除笔画数、墨迹总长及整体包围盒
+#[tauri::command]
+pub fn stroke_calc_stats(strokes: Vec<Stroke>) -> Result<StrokeStats, String> {
+    let mut stats = StrokeStats {
+        stroke_count: strokes.len(),
+        total_points: 0,
+        draw_count: 0,
+        erase_count: 0,
+        total_ink_length: 0.0,
+        bounds: None,
+    };
+
+    for stroke in &strokes {
+        stats.total_points += stroke.points.len();
+        match stroke.stroke_type.as_str() {
+            "draw" => {
+                stats.draw_count += 1;
+                stats.total_ink_length += stroke.points.iter()
+                    .map(|s| ((s.to_x - s.from_x).powi(2) + (s.to_y - s.from_y).powi(2)).sqrt())
+                    .sum::<f32>();
+            }
+            "erase" => stats.erase_count += 1,
+            _ => {}
+        }
+
+        if let Some([min_x, min_y, max_x, max_y]) = stroke_calc_bounds(stroke) {
+            let bounds = stats.bounds.get_or_insert([min_x, min_y, max_x, max_y]);
+            bounds[0] = bounds[0].min(min_x);
+            bounds[1] = bounds[1].min(min_y);
+            bounds[2] = bounds[2].max(max_x);
+            bounds[3] = bounds[3].max(max_y);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// 按仿射矩阵变换笔画的线段端点和文字位置，线宽等尺寸不变
+fn stroke_format_affine_points(strokes: Vec<Stroke>, matrix: &[f32; 6]) -> Vec<Stroke> {
+    strokes.into_iter()
+        .map(|mut stroke| {
+            for segment in &mut stroke.points {
+                (segment.from_x, segment.from_y) = point_calc_affine(segment.from_x, segment.from_y, matrix);
+                (segment.to_x, segment.to_y) = point_calc_affine(segment.to_x, segment.to_y, matrix);
+            }
+            if let Some(position) = stroke.position.as_mut() {
+                (position.x, position.y) = point_calc_affine(position.x, position.y, matrix);
+            }
+            stroke
+        })
+        .collect()
+}
+
+/// 校验画布尺寸为正的有限值
+fn canvas_validate_extent(width: f32, height: f32) -> Result<(), String> {
+    if !width.is_finite() || !height.is_finite() || width <= 0.0 || height <= 0.0 {
+        return Err(format!("Invalid canvas size {}x{}", width, height));
+    }
+    Ok(())
+}
+
+/// Tauri IPC 命令：将笔画坐标除以画布尺寸，转换为与分辨率无关的 0..1 坐标
+///
+/// 与 stroke_format_denormalize 配合使用，画布尺寸变化后批注按比例重新排布；
+/// 线宽、橡皮大小和字号仍为像素，不参与换算
+///
+/// # 参数
+/// * `strokes` — 像素坐标的笔画数组
+/// * `width` / `height` — 当前画布尺寸
+///
+/// # 返回值
+/// * `Ok(Vec<Stroke>)` — 归一化坐标的笔画数组
+///
+/// # 异常
+/// * 画布尺寸不是正的有限值
+#[tauri::command]
+pub fn stroke_format_normalize(strokes: Vec<Stroke>, width: f32, height: f32) -> Result<Vec<Stroke>, String> {
+    canvas_validate_extent(width, height)?;
+    Ok(stroke_format_affine_points(strokes, &[1.0 / width, 0.0, 0.0, 1.0 / height, 0.0, 0.0]))
+}
+
+/// Tauri IPC 命令：将 0..1 归一化坐标的笔画还原为指定画布尺寸下的像素坐标
+///
+/// # 参数
+/// * `strokes` — stroke_format_normalize 输出的笔画数组
+/// * `width` / `height` — 目标画布尺寸
+///
+/// # 返回值
+/// * `Ok(Vec<Stroke>)` — 像素坐标的笔画数组
+///
+/// # 异常
+/// * 画布尺寸不是正的有限值
+#[tauri::command]
+pub fn stroke_format_denormalize(strokes: Vec<Stroke>, width: f32, height: f32) -> Result<Vec<Stroke>, String> {
+    canvas_validate_extent(width, height)?;
+    Ok(stroke_format_affine_points(strokes, &[width, 0.0, 0.0, height, 0.0, 0.0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(from_x: f32, from_y: f32, to_x: f32, to_y: f32) -> StrokePoint {
+        StrokePoint { from_x, from_y, to_x, to_y }
+    }
+
+    fn stroke(stroke_type: &str, points: Vec<StrokePoint>) -> Stroke {
+        Stroke {
+            stroke_type: stroke_type.to_string(),
+            points,
+            color: None,
+            line_width: Some(4),
+            eraser_size: Some(20),
+            eraser_shape: None,
+            text: None,
+            font_size: None,
+            position: None,
+        }
+    }
+
+    #[test]
+    fn resolve_order_bakes_erase_into_earlier_draws() {
+        let first = stroke("draw", vec![segment(0.0, 50.0, 100.0, 50.0)]);
+        let eraser = stroke("erase", vec![segment(50.0, 0.0, 50.0, 100.0)]);
+        let last = stroke("draw", vec![segment(0.0, 60.0, 100.0, 60.0)]);
+
+        let resolved = stroke_format_resolve_order(vec![first, eraser, last.clone()]).unwrap();
+        let erase_at = resolved.iter().position(|s| s.stroke_type == "erase").unwrap();
+        let last_at = resolved.iter().rposition(|s| s.stroke_type == "draw").unwrap();
+        assert!(erase_at < last_at);
+        assert_eq!(resolved[last_at].points.len(), last.points.len());
+        assert_eq!(resolved[last_at].points[0].from_x, 0.0);
+        assert_eq!(resolved[last_at].points[0].to_x, 100.0);
+
+        // 第一笔中整段线宽都落在橡皮内的部分被删除，剩余小段都至少有一端超出橡皮半径减线宽
+        let first = resolved.iter().find(|s| s.stroke_type == "draw").unwrap();
+        assert!(!first.points.is_empty());
+        for piece in &first.points {
+            assert!((piece.from_x - 50.0).abs().max((piece.to_x - 50.0).abs()) > 10.0 - 2.0);
+        }
+    }
+
+    #[test]
+    fn normalize_then_denormalize_scales_to_new_canvas() {
+        let mut text = stroke("text", Vec::new());
+        text.position = Some(PathPoint { x: 200.0, y: 100.0 });
+        let strokes = vec![stroke("draw", vec![segment(40.0, 30.0, 400.0, 300.0)]), text];
+
+        let normalized = stroke_format_normalize(strokes, 400.0, 300.0).unwrap();
+        let segment = &normalized[0].points[0];
+        assert!((segment.from_x - 0.1).abs() < 1e-6 && (segment.from_y - 0.1).abs() < 1e-6);
+        assert_eq!((segment.to_x, segment.to_y), (1.0, 1.0));
+
+        let resized = stroke_format_denormalize(normalized, 800.0, 150.0).unwrap();
+        let segment = &resized[0].points[0];
+        assert!((segment.from_x - 80.0).abs() < 1e-3 && (segment.from_y - 15.0).abs() < 1e-3);
+        assert!((segment.to_x - 800.0).abs() < 1e-3 && (segment.to_y - 150.0).abs() < 1e-3);
+        let position = resized[1].position.as_ref().unwrap();
+        assert!((position.x - 400.0).abs() < 1e-3 && (position.y - 50.0).abs() < 1e-3);
+        assert_eq!(resized[0].line_width, Some(4));
+
+        assert!(stroke_format_normalize(Vec::new(), 0.0, 300.0).is_err());
+    }
+
+    #[test]
+    fn transform_layers_scales_points_and_sizes() {
+        let layers = vec![vec![stroke("draw", vec![segment(1.0, 2.0, 3.0, 4.0)])]];
+        let transformed = stroke_format_transform_layers(layers, [2.0, 0.0, 0.0, 2.0, 10.0, 0.0]).unwrap();
+        let segment = &transformed[0][0].points[0];
+        assert_eq!((segment.from_x, segment.from_y, segment.to_x, segment.to_y), (12.0, 4.0, 16.0, 8.0));
+        assert_eq!(transformed[0][0].line_width, Some(8));
+    }
+
+    #[test]
+    fn resolve_order_moves_eraser_first_when_fully_baked() {
+        let short = stroke("draw", vec![segment(45.0, 50.0, 55.0, 50.0)]);
+        let eraser = stroke("erase", vec![segment(50.0, 0.0, 50.0, 100.0)]);
+        let resolved = stroke_format_resolve_order(vec![short, eraser]).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].stroke_type, "erase");
+    }
+
+    #[test]
+    fn simplify_keeps_pressures_aligned() {
+        // 直线上的点会被删除，拐角保留；压感取 x 值便于核对对应关系
+        let points: Vec<PathPoint> = (0..10).map(|i| PathPoint { x: i as f32, y: 0.0 })
+            .chain((1..10).map(|i| PathPoint { x: 9.0, y: i as f32 }))
+            .collect();
+        let pressures: Vec<f32> = points.iter().map(|p| p.x * 10.0 + p.y).collect();
+
+        let simplified = stroke_format_simplify(points.clone(), Some(pressures), 0.5).unwrap();
+        let kept_pressures = simplified.pressures.unwrap();
+        assert_eq!(simplified.points.len(), kept_pressures.len());
+        assert_eq!(simplified.points.len(), 3);
+        for (point, pressure) in simplified.points.iter().zip(&kept_pressures) {
+            assert_eq!(point.x * 10.0 + point.y, *pressure);
+        }
+
+        assert!(stroke_format_simplify(points, Some(vec![1.0]), 0.5).is_err());
+    }
+
+    #[test]
+    fn pressure_curve_identity_and_convex() {
+        let point = |x: f32, y: f32| PathPoint { x, y };
+        let values = vec![0.0, 0.1, 0.25, 0.5, 0.75, 1.0];
+
+        let identity = stroke_calc_pressure_curve(values.clone(), vec![point(0.0, 0.0), point(1.0, 1.0)]).unwrap();
+        for (out, input) in identity.iter().zip(&values) {
+            assert!((out - input).abs() < 1e-5, "{} != {}", out, input);
+        }
+
+        // 下凸曲线：轻压更轻，端点保持不变
+        let convex = vec![point(0.0, 0.0), point(0.5, 0.2), point(1.0, 1.0)];
+        let remapped = stroke_calc_pressure_curve(values.clone(), convex).unwrap();
+        assert!(remapped[2] < 0.25 && remapped[3] < 0.5 && remapped[4] < 0.75);
+        assert!(remapped[0].abs() < 1e-5 && (remapped[5] - 1.0).abs() < 1e-5);
+        assert!(remapped.windows(2).all(|w| w[1] >= w[0]));
+
+        let not_monotone = vec![point(0.0, 0.0), point(0.6, 0.5), point(0.4, 1.0)];
+        assert!(stroke_calc_pressure_curve(values, not_monotone).is_err());
+    }
+
+    #[test]
+    fn duplicate_strokes_are_grouped() {
+        let path = |offset: f32, reversed: bool| {
+            let mut points: Vec<StrokePoint> = (0..10)
+                .map(|i| {
+                    let x = i as f32 * 10.0;
+                    segment(x, 50.0 + offset + (x * 0.1).sin() * 5.0, x + 10.0, 50.0 + offset + ((x + 10.0) * 0.1).sin() * 5.0)
+                })
+                .collect();
+            if reversed {
+                points.reverse();
+                for p in &mut points {
+                    (p.from_x, p.from_y, p.to_x, p.to_y) = (p.to_x, p.to_y, p.from_x, p.from_y);
+                }
+            }
+            stroke("draw", points)
+        };
+        // 擦除笔画与绘制笔画形状相同也不参与比较
+        let mut erase = path(0.0, false);
+        erase.stroke_type = "erase".to_string();
+        let strokes = vec![
+            path(0.0, false),
+            stroke("draw", vec![segment(0.0, 0.0, 0.0, 100.0)]),
+            path(0.5, true),
+            path(0.0, false),
+            erase,
+        ];
+
+        assert_eq!(stroke_detect_duplicates(strokes.clone(), 0.95).unwrap(), vec![vec![0, 2, 3]]);
+        assert!(stroke_detect_duplicates(strokes, 1.5).is_err());
+    }
+
+    #[test]
+    fn stroke_stats_count_mixed_document() {
+        let strokes = vec![
+            stroke("draw", vec![segment(10.0, 10.0, 40.0, 50.0), segment(40.0, 50.0, 40.0, 60.0)]),
+            stroke("erase", vec![segment(100.0, 100.0, 120.0, 100.0)]),
+            stroke("draw", vec![segment(0.0, 0.0, 0.0, 20.0)]),
+            stroke("clear", Vec::new()),
+        ];
+
+        let stats = stroke_calc_stats(strokes).unwrap();
+        assert_eq!(stats.stroke_count, 4);
+        assert_eq!(stats.total_points, 4);
+        assert_eq!((stats.draw_count, stats.erase_count), (2, 1));
+        // 50 + 10 + 20，擦除不计入墨迹长度
+        assert!((stats.total_ink_length - 80.0).abs() < 1e-3, "{}", stats.total_ink_length);
+        // 线宽 4 和橡皮 20 的包围盒各自外扩半径 + 1
+        assert_eq!(stats.bounds, Some([-3.0, -3.0, 131.0, 111.0]));
+
+        assert!(stroke_calc_stats(Vec::new()).unwrap().bounds.is_none());
+    }
+}
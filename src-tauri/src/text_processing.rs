@@ -0,0 +1,241 @@
+// text_processing.rs — 文字排版与光栅化
+// 使用随应用打包的 DejaVu Sans 字体（fonts/DejaVuSans.ttf）将文字绘制到 RGBA 画布，
+// 并提供在图片上绘制文字（批注导出、水印）的 Tauri IPC 命令
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::color_calc_from_hex;
+use crate::image_processing::{image_encode_png_base64, image_load_base64, pixel_blend_over};
+
+/// 文字排版尺寸，单位均为像素
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextMetrics {
+    /// 最宽一行的宽度
+    pub width: f32,
+    /// 文字块总高度
+    pub height: f32,
+    pub line_count: usize,
+    /// 基线以上高度
+    pub ascent: f32,
+    /// 基线以下深度（正数）
+    pub descent: f32,
+}
+
+/// 打包的字体文件，许可证见 fonts/LICENSE-DejaVu.txt
+static BUNDLED_FONT_DATA: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+/// 首次使用时解析字体，之后复用
+static BUNDLED_FONT: Lazy<Result<FontRef<'static>, String>> = Lazy::new(|| {
+    FontRef::try_from_slice(BUNDLED_FONT_DATA)
+        .map_err(|e| format!("Failed to load bundled font: {}", e))
+});
+
+/// 获取打包字体
+pub fn font_fetch_bundled() -> Result<&'static FontRef<'static>, String> {
+    BUNDLED_FONT.as_ref().map_err(|e| e.clone())
+}
+
+/// 单行文字的水平宽度（含字距调整）
+fn text_calc_line_width<F: Font>(font: &F, scale: PxScale, line: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut width = 0.0;
+    let mut previous = None;
+
+    for c in line.chars() {
+        let glyph_id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            width += scaled.kern(prev, glyph_id);
+        }
+        width += scaled.h_advance(glyph_id);
+        previous = Some(glyph_id);
+    }
+
+    width
+}
+
+/// 文字块的包围尺寸 (宽, 高)，按换行符分行
+pub fn text_calc_block_size(text: &str, font_size: f32) -> Result<(f32, f32), String> {
+    let font = font_fetch_bundled()?;
+    let scale = PxScale::from(font_size);
+    let scaled = font.as_scaled(scale);
+
+    let line_height = scaled.height() + scaled.line_gap();
+    let lines: Vec<&str> = text.split('\n').collect();
+    let width = lines.iter()
+        .map(|line| text_calc_line_width(font, scale, line))
+        .fold(0.0f32, f32::max);
+    let height = scaled.height() + line_height * (lines.len() - 1) as f32;
+
+    Ok((width, height))
+}
+
+/// 在画布上绘制文字（alpha 混合），支持换行，超出画布部分被裁剪
+///
+/// # 参数
+/// * `canvas` — 目标画布
+/// * `text` — 文字内容，"\n" 分行
+/// * `x` / `y` — 文字块左上角坐标
+/// * `font_size` — 字号（像素）
+/// * `color` — 文字颜色，alpha 与字形覆盖率相乘
+pub fn text_render_on_canvas(
+    canvas: &mut RgbaImage,
+    text: &str,
+    x: f32,
+    y: f32,
+    font_size: f32,
+    color: Rgba<u8>,
+) -> Result<(), String> {
+    if font_size <= 0.0 || !font_size.is_finite() {
+        return Err(format!("Invalid font size: {}", font_size));
+    }
+
+    let font = font_fetch_bundled()?;
+    let scale = PxScale::from(font_size);
+    let scaled = font.as_scaled(scale);
+    let line_height = scaled.height() + scaled.line_gap();
+    let (canvas_w, canvas_h) = (canvas.width() as i32, canvas.height() as i32);
+
+    for (line_index, line) in text.split('\n').enumerate() {
+        let baseline = y + scaled.ascent() + line_height * line_index as f32;
+        if baseline - scaled.ascent() > canvas_h as f32 {
+            break;
+        }
+
+        let mut caret = x;
+        let mut previous = None;
+
+        for c in line.chars() {
+            let glyph_id = scaled.glyph_id(c);
+            if let Some(prev) = previous {
+                caret += scaled.kern(prev, glyph_id);
+            }
+            previous = Some(glyph_id);
+
+            let glyph = glyph_id.with_scale_and_position(scale, point(caret, baseline));
+            caret += scaled.h_advance(glyph_id);
+
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            let (left, top) = (bounds.min.x as i32, bounds.min.y as i32);
+
+            outlined.draw(|gx, gy, coverage| {
+                let px = left + gx as i32;
+                let py = top + gy as i32;
+                if px < 0 || py < 0 || px >= canvas_w || py >= canvas_h {
+                    return;
+                }
+                let alpha = (color[3] as f32 * coverage.clamp(0.0, 1.0)).round() as u8;
+                if alpha == 0 {
+                    return;
+                }
+                let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                pixel_blend_over(&mut pixel.0, &[color[0], color[1], color[2], alpha]);
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Tauri IPC 命令：在图片上绘制文字，用于批注导出和水印
+///
+/// # 参数
+/// * `image_data` — base64 编码的图片数据
+/// * `text` — 文字内容，"\n" 分行
+/// * `x` / `y` — 文字块左上角坐标，可为负数（超出部分被裁剪）
+/// * `font_size` — 字号（像素）
+/// * `color` — #RRGGBB 或 #RRGGBBAA 格式颜色
+///
+/// # 返回值
+/// * `Ok(String)` — 绘制后的 PNG data URL
+///
+/// # 异常
+/// * 颜色格式或字号非法
+/// * base64 解析失败或图像格式不支持
+#[tauri::command]
+pub fn text_render_image(image_data: String, text: String, x: i32, y: i32, font_size: f32, color: String) -> Result<String, String> {
+    let color = color_calc_from_hex(&color)?;
+    let mut canvas = image_load_base64(&image_data)?.to_rgba8();
+
+    text_render_on_canvas(&mut canvas, &text, x as f32, y as f32, font_size, color)?;
+
+    image_encode_png_base64(&image::DynamicImage::ImageRgba8(canvas))
+}
+
+/// Tauri IPC 命令：测量文字块尺寸，便于前端在绘制前布局标签
+///
+/// 使用与 text_render_image 相同的打包字体和排版规则
+///
+/// # 参数
+/// * `text` — 文字内容，"\n" 分行
+/// * `font_size` — 字号（像素）
+///
+/// # 返回值
+/// * `Ok(TextMetrics)` — 宽高、行数及字体上下伸高度
+///
+/// # 异常
+/// * 字号非法
+/// * 字体加载失败
+#[tauri::command]
+pub fn text_calc_metrics(text: String, font_size: f32) -> Result<TextMetrics, String> {
+    if font_size <= 0.0 || !font_size.is_finite() {
+        return Err(format!("Invalid font size: {}", font_size));
+    }
+
+    let (width, height) = text_calc_block_size(&text, font_size)?;
+    let scaled = font_fetch_bundled()?.as_scaled(PxScale::from(font_size));
+
+    Ok(TextMetrics {
+        width,
+        height,
+        line_count: text.split('\n').count(),
+        ascent: scaled.ascent(),
+        descent: -scaled.descent(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_render_image_only_touches_text_region() {
+        let base = RgbaImage::from_pixel(120, 60, Rgba([255, 255, 255, 255]));
+        let data = image_encode_png_base64(&image::DynamicImage::ImageRgba8(base.clone())).unwrap();
+        let rendered = image_load_base64(&text_render_image(data, "Hi".to_string(), 20, 10, 24.0, "#000000".to_string()).unwrap())
+            .unwrap()
+            .to_rgba8();
+
+        let (width, height) = text_calc_block_size("Hi", 24.0).unwrap();
+        let inside = |x: u32, y: u32| {
+            (20.0..=20.0 + width.ceil()).contains(&(x as f32)) && (10.0..=10.0 + height.ceil()).contains(&(y as f32))
+        };
+        let mut changed_inside = 0;
+        for (x, y, pixel) in rendered.enumerate_pixels() {
+            if pixel != base.get_pixel(x, y) {
+                assert!(inside(x, y), "pixel ({}, {}) outside the text block changed", x, y);
+                changed_inside += 1;
+            }
+        }
+        assert!(changed_inside > 20);
+    }
+
+    #[test]
+    fn text_metrics_grow_with_length_and_lines() {
+        let short = text_calc_metrics("Hi".to_string(), 20.0).unwrap();
+        let long = text_calc_metrics("Hello, world".to_string(), 20.0).unwrap();
+        assert!(long.width > short.width);
+        assert_eq!(short.line_count, 1);
+
+        let two_lines = text_calc_metrics("Hi\nthere".to_string(), 20.0).unwrap();
+        assert_eq!(two_lines.line_count, 2);
+        assert!(two_lines.height > short.height);
+        assert!(short.ascent > 0.0 && short.descent > 0.0);
+        assert!(text_calc_metrics("Hi".to_string(), 0.0).is_err());
+    }
+}
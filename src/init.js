@@ -218,6 +218,11 @@ async function settings_load_camera_config() {
                 DRAW_CONFIG.penEffectMode = settings.penEffectMode;
             }
 
+            // 平滑强度与后端 stroke_format_smooth_default 共用同一配置项
+            if (settings.smoothStrength !== undefined) {
+                DRAW_CONFIG.penSmoothness = settings.smoothStrength;
+            }
+
             const themeName = settings.theme || 'com.viewstage.theme.simplify';
             await ThemeManager.theme_update_active(themeName);
 
@@ -287,6 +292,10 @@ async function main_init_all() {
         await dir_init_cache_path();
         console.log('[init] dir_init_cache_path done');
 
+        // 后台预热线程池与图像解码器，不等待结果
+        window.__TAURI__?.core.invoke('app_init_warmup')
+            .catch(e => console.log('[init] app_init_warmup error:', e));
+
         try {
             console.log('[init] cache_validate_auto_clear');
             const cleared = await window.__TAURI__.core.invoke('cache_validate_auto_clear');